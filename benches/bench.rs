@@ -1,5 +1,6 @@
 extern crate criterion;
 extern crate rand;
+extern crate rand_xorshift;
 
 extern crate social_schedule;
 
@@ -7,8 +8,8 @@ use criterion::{criterion_group, criterion_main, Criterion};
 use rand::SeedableRng;
 
 fn criterion_benchmark(c: &mut Criterion) {
-    let rng = rand::thread_rng();
-    let mut generator = social_schedule::schedule::Generator::new(rng, 24, 6);
+    let rng = rand_xorshift::XorShiftRng::seed_from_u64(0);
+    let mut generator = social_schedule::schedule::Generator::new(rng, 24, 6, 6);
     c.bench_function("6 by 4 process", |b| b.iter(|| generator.process()));
     c.bench_function("6 by 4 find_unique_opponents", |b| {
         b.iter(|| generator.best.find_unique_opponents())
@@ -6,7 +6,7 @@ use futures::FutureExt;
 use futures::{SinkExt, StreamExt};
 use std::sync::{
     atomic::{AtomicUsize, Ordering},
-    Arc,
+    Arc, Mutex,
 };
 
 use schedule_util::{BatchDeserialize, BatchOutputSerialize};
@@ -32,9 +32,11 @@ fn solving_thread(
     in_queue: std::sync::mpsc::Receiver<Vec<u8>>,
     in_queue_size: Arc<AtomicUsize>,
     sender: tokio::sync::mpsc::UnboundedSender<(Vec<u8>, schedule_util::Stats)>,
+    search_stats: Arc<Mutex<schedule_solver::SearchStats>>,
 ) {
     let scheduler = schedule_solver::Scheduler::new(&tables, rounds);
     let mut buffer = vec![0; scheduler.get_block_size()];
+    let mut batch_stats = schedule_solver::SearchStats::new();
     while let Ok(next) = in_queue.recv() {
         in_queue_size.fetch_sub(1, Ordering::Relaxed);
         let deserialized = if let Ok(de) = BatchDeserialize::deserialize(&next) {
@@ -51,6 +53,7 @@ fn solving_thread(
         let mut steps: u64 = 0;
         let start = std::time::Instant::now();
         let mut emptied = false;
+        let mut notable = Vec::new();
         'inner_loop: while steps <= steps_per_sync {
             let target_size = (current_depth + 2) * scheduler.get_block_size();
             if target_size > buffer.len() {
@@ -58,9 +61,10 @@ fn solving_thread(
             }
             let buffer: &mut [u64] = &mut buffer[current_depth * scheduler.get_block_size()..];
             let (buf_1, buf_2) = buffer.split_at_mut(scheduler.get_block_size());
-            if let Some(finished) = scheduler.step(buf_1, buf_2) {
+            if let Some(finished) = scheduler.step(buf_1, buf_2, &mut batch_stats) {
                 if finished {
                     println!("Found a solution: {:?}", buf_1);
+                    notable.extend_from_slice(buf_1);
                     if current_depth == 0 {
                         emptied = true;
                         break 'inner_loop;
@@ -95,9 +99,23 @@ fn solving_thread(
         let stats = schedule_util::Stats {
             steps,
             elapsed: start.elapsed(),
+            propagations: batch_stats.get_propagations(),
+            branches: batch_stats.get_branches(),
+            branch_candidates: batch_stats.get_branch_candidates(),
+            table_overfull: batch_stats.get_failures(schedule_solver::FailureReason::TableOverfull),
+            no_candidate: batch_stats.get_failures(schedule_solver::FailureReason::NoCandidate),
+            propagation_wipe_out: batch_stats.get_wipe_outs(),
+            nodes_per_depth: batch_stats.get_nodes_per_depth().to_vec(),
         };
-        let batch_result =
-            BatchOutputSerialize::new(id, scheduler.get_block_size(), output, &[], stats);
+        search_stats.lock().unwrap().merge(&batch_stats);
+        batch_stats.clear();
+        let batch_result = BatchOutputSerialize::new(
+            id,
+            scheduler.get_block_size(),
+            output,
+            &notable,
+            stats.clone(),
+        );
         let mut buf = vec![0; batch_result.get_size()];
         batch_result.serialize(&mut buf).unwrap();
         if let Err(error) = sender.send((buf, stats)) {
@@ -139,9 +157,34 @@ async fn handle_recv(
     }
 }
 
+fn display_search_stats(stats: &schedule_solver::SearchStats) {
+    println!(
+        "Nodes: {} Branching factor: {:.2} Propagations: {}",
+        stats.get_nodes(),
+        stats.get_branching_factor(),
+        stats.get_propagations()
+    );
+    println!(
+        "Failures: table overfull: {} no candidate: {} Propagation wipe-outs: {}",
+        stats.get_failures(schedule_solver::FailureReason::TableOverfull),
+        stats.get_failures(schedule_solver::FailureReason::NoCandidate),
+        stats.get_wipe_outs()
+    );
+    let nodes_per_depth = stats.get_nodes_per_depth();
+    let max_depth = stats.get_max_depth();
+    print!("Nodes per depth:");
+    for (depth, count) in nodes_per_depth.iter().enumerate().take(max_depth + 1) {
+        if *count != 0 {
+            print!(" {}:{}", depth, count);
+        }
+    }
+    println!();
+}
+
 async fn handle_display(
     total_steps: Arc<AtomicUsize>,
     threads: Vec<(Arc<AtomicUsize>, std::sync::mpsc::Sender<Vec<u8>>)>,
+    search_stats: Arc<Mutex<schedule_solver::SearchStats>>,
 ) {
     let start = std::time::Instant::now();
     loop {
@@ -151,6 +194,7 @@ async fn handle_display(
             total_steps,
             total_steps as f32 / start.elapsed().as_secs_f32()
         );
+        display_search_stats(&search_stats.lock().unwrap());
         for (queue_size, _) in threads.iter() {
             println!("Queue size: {}", queue_size.load(Ordering::Relaxed));
         }
@@ -197,6 +241,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     tables.sort_unstable();
 
     let mut threads = Vec::new();
+    let search_stats = Arc::new(Mutex::new(schedule_solver::SearchStats::new()));
     let (tx, rx) = tokio::sync::mpsc::unbounded_channel();
     for _ in 0..opts
         .jobs
@@ -209,6 +254,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let queue_size_base = Arc::new(AtomicUsize::new(0));
         let queue_size = queue_size_base.clone();
         let iterations_per_sync = opts.iterations_per_sync;
+        let search_stats = search_stats.clone();
         let _thread = std::thread::spawn(move || {
             solving_thread(
                 tables,
@@ -217,6 +263,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 local_rx,
                 queue_size,
                 tx,
+                search_stats,
             )
         });
         threads.push((queue_size_base, local_tx));
@@ -235,7 +282,11 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let total_steps = Arc::new(AtomicUsize::new(0));
     let handle_batches = tokio::spawn(handle_send(total_steps.clone(), rx, ws_tx));
     let handle_blocks = tokio::spawn(handle_recv(ws_rx, threads.clone()));
-    let _handle_display = tokio::spawn(handle_display(total_steps.clone(), threads.clone()));
+    let _handle_display = tokio::spawn(handle_display(
+        total_steps.clone(),
+        threads.clone(),
+        search_stats.clone(),
+    ));
     pin_mut!(handle_batches);
     pin_mut!(handle_blocks);
     let result = futures::future::select(&mut handle_batches, &mut handle_blocks)
@@ -5,6 +5,8 @@ extern crate quickcheck;
 extern crate quickcheck_macros;
 
 mod scheduler;
+mod stats;
 mod util;
 mod word;
 pub use scheduler::*;
+pub use stats::*;
@@ -1,3 +1,4 @@
+use crate::stats::{FailureReason, SearchStats};
 use crate::util::*;
 use crate::word::Word;
 use crate::word::*;
@@ -702,6 +703,30 @@ impl<'a> Scheduler<'a> {
         Ok(())
     }
 
+    /// Convert the players placed in the buffer into rounds of tables of player numbers
+    pub fn get_matches<'b>(&self, buffer: Schedule<'b>) -> Vec<Vec<Vec<usize>>> {
+        let mut matches = Vec::with_capacity(self.rounds);
+        for round in 0..self.rounds {
+            let mut tables = Vec::with_capacity(self.tables.len());
+            for table in 0..self.tables.len() {
+                let mut players = Vec::with_capacity(self.tables[table]);
+                for byte in 0..self.player_bit_word_count {
+                    let mut temp = buffer.rest[self.offsets.played_on_table_offset
+                        + self.player_bit_word_count * (round * self.tables.len() + table)
+                        + byte];
+                    while temp != 0 {
+                        let trailing_zeros = temp.trailing_zeros() as usize;
+                        temp &= !(1 << trailing_zeros);
+                        players.push(byte * Self::word_size() + trailing_zeros);
+                    }
+                }
+                tables.push(players);
+            }
+            matches.push(tables);
+        }
+        matches
+    }
+
     pub fn get_schedule<'b>(&self, buffer: Schedule<'b>) -> &'b [u64] {
         &buffer.rest[self.offsets.played_on_table_offset..][..self.offsets.played_on_table_size]
     }
@@ -876,7 +901,9 @@ impl<'a> Scheduler<'a> {
         buffer.rest[self.offsets.empty_table_count_offset]
     }
 
-    pub fn find_hidden_singles<'b>(&self, buffer: &mut Schedule<'b>) {
+    /// Place every player that only has a single possible position, returning the number placed
+    pub fn find_hidden_singles<'b>(&self, buffer: &mut Schedule<'b>) -> usize {
+        let mut placed = 0;
         let mut round_range = self.round_range;
         while let Some(round) = round_range.next() {
             let mut byte = 0;
@@ -912,6 +939,7 @@ impl<'a> Scheduler<'a> {
                     if let Some(table) = only_position {
                         //println!("Found single location: {:?}", (round, table, player));
                         self.apply_player(buffer, round, table, player);
+                        placed += 1;
                     }
                 }
                 byte += 1;
@@ -954,11 +982,13 @@ impl<'a> Scheduler<'a> {
                     if let Some(round) = only_position {
                         //println!("Found single location: {:?}", (round, table, player));
                         self.apply_player(buffer, round, table, player);
+                        placed += 1;
                     }
                 }
                 byte += 1;
             }
         }
+        placed
     }
 
     const fn get_fixed_count<'b>(&self, buffer: &Schedule<'b>, round: Round, table: Table) -> u32 {
@@ -1018,14 +1048,23 @@ impl<'a> Scheduler<'a> {
         true
     }
 
-    pub fn step(&self, buffer_1: &mut [u64], buffer_2: &mut [u64]) -> Option<bool> {
+    /// Expand the node in buffer_1, writing a child into buffer_2 if it branches.
+    /// Returns None for a dead end, Some(false) after branching and Some(true) once complete
+    pub fn step(
+        &self,
+        buffer_1: &mut [u64],
+        buffer_2: &mut [u64],
+        stats: &mut SearchStats,
+    ) -> Option<bool> {
         let buffer_1 = &mut buffer_1[..self.offsets.block_size];
         let buffer_2 = &mut buffer_2[..self.offsets.block_size];
 
         let mut buffer_1 = self.import_buffer(buffer_1)?;
         let mut buffer_2 = self.import_buffer(buffer_2)?;
 
-        self.find_hidden_singles(&mut buffer_1);
+        stats.record_node(buffer_1.rest[self.offsets.players_placed_counter_offset] as usize);
+        let placed = self.find_hidden_singles(&mut buffer_1);
+        stats.record_propagations(placed);
         let offset = self.offsets.potential_on_table_offset;
 
         let mut lowest: Option<(u32, Round, Table)> = None;
@@ -1057,6 +1096,7 @@ impl<'a> Scheduler<'a> {
                         let fixed_index = self.offsets.played_on_table_offset
                             + self.player_bit_word_count
                                 * (round.as_usize() * self.tables.len() + table.as_usize());
+                        let mut placed = 0;
                         for byte in 0..self.player_bit_word_count {
                             loop {
                                 let potential = buffer_1.rest[potential_index + byte]
@@ -1072,6 +1112,7 @@ impl<'a> Scheduler<'a> {
                                         player,
                                     ) {
                                         self.apply_player(&mut buffer_1, round, table, player);
+                                        placed += 1;
                                     } else {
                                         buffer_1.rest[potential_index + byte] &= !player_bit;
                                     }
@@ -1080,6 +1121,11 @@ impl<'a> Scheduler<'a> {
                                 }
                             }
                         }
+                        stats.record_propagations(placed);
+                        if self.get_potential_count(buffer_1, round, table) < table_size {
+                            // Some of the forced players clashed, so the table can no longer be filled
+                            stats.record_wipe_out();
+                        }
                     } else {
                         lowest = Some(if let Some(lowest) = lowest {
                             if fixed_player_count < lowest.0 {
@@ -1108,11 +1154,17 @@ impl<'a> Scheduler<'a> {
                     }
                     continue;
                 }
-                core::cmp::Ordering::Greater => return None,
+                core::cmp::Ordering::Greater => {
+                    stats.record_failure(FailureReason::TableOverfull);
+                    return None;
+                }
             }
         }
 
-        if let Some((_, round, table)) = lowest {
+        if let Some((fixed_player_count, round, table)) = lowest {
+            stats.record_branch(
+                self.get_potential_count(buffer_1, round, table) - fixed_player_count,
+            );
             for byte in 0..self.player_bit_word_count {
                 let fixed = buffer_1.rest[self.offsets.played_on_table_offset
                     + self.player_bit_word_count
@@ -1153,6 +1205,7 @@ impl<'a> Scheduler<'a> {
                     return Some(false);
                 }
             }
+            stats.record_failure(FailureReason::NoCandidate);
             return None; // Could not place any player but fixed_player_count < table_size
         }
         Some(true)
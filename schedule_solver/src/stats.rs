@@ -0,0 +1,131 @@
+/// Reasons for `Scheduler::step` abandoning a branch
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum FailureReason {
+    /// A table ended up with more fixed players than it has seats
+    TableOverfull,
+    /// The table chosen for branching had no player that could be placed on it
+    NoCandidate,
+}
+
+/// Counters collected while stepping through the search tree.
+/// Only does integer increments on the hot path, so is left on all the time
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SearchStats {
+    nodes_per_depth: Vec<u64>,
+    branches: u64,
+    branch_candidates: u64,
+    table_overfull: u64,
+    no_candidate: u64,
+    /// Forced placements which left a table unable to be filled. The step carries on, so the branch fails later
+    propagation_wipe_out: u64,
+    propagations: u64,
+}
+
+impl SearchStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub(crate) fn record_node(&mut self, depth: usize) {
+        if depth >= self.nodes_per_depth.len() {
+            self.nodes_per_depth.resize(depth + 1, 0);
+        }
+        self.nodes_per_depth[depth] += 1;
+    }
+
+    pub(crate) fn record_branch(&mut self, candidates: u32) {
+        self.branches += 1;
+        self.branch_candidates += u64::from(candidates);
+    }
+
+    pub(crate) fn record_failure(&mut self, reason: FailureReason) {
+        match reason {
+            FailureReason::TableOverfull => self.table_overfull += 1,
+            FailureReason::NoCandidate => self.no_candidate += 1,
+        }
+    }
+
+    pub(crate) fn record_wipe_out(&mut self) {
+        self.propagation_wipe_out += 1;
+    }
+
+    pub(crate) fn record_propagations(&mut self, amount: usize) {
+        self.propagations += amount as u64;
+    }
+
+    /// Number of nodes expanded at each depth, where depth is the number of players placed
+    pub fn get_nodes_per_depth(&self) -> &[u64] {
+        &self.nodes_per_depth
+    }
+
+    pub fn get_nodes(&self) -> u64 {
+        self.nodes_per_depth.iter().sum()
+    }
+
+    pub fn get_max_depth(&self) -> usize {
+        self.nodes_per_depth
+            .iter()
+            .rposition(|&count| count != 0)
+            .unwrap_or(0)
+    }
+
+    pub const fn get_branches(&self) -> u64 {
+        self.branches
+    }
+
+    /// Total number of candidate players seen at branch points
+    pub const fn get_branch_candidates(&self) -> u64 {
+        self.branch_candidates
+    }
+
+    /// Average number of candidate players at each branch point
+    pub fn get_branching_factor(&self) -> f64 {
+        if self.branches == 0 {
+            0.0
+        } else {
+            self.branch_candidates as f64 / self.branches as f64
+        }
+    }
+
+    pub const fn get_failures(&self, reason: FailureReason) -> u64 {
+        match reason {
+            FailureReason::TableOverfull => self.table_overfull,
+            FailureReason::NoCandidate => self.no_candidate,
+        }
+    }
+
+    /// Number of times forced placement left a table with fewer potential players than seats
+    pub const fn get_wipe_outs(&self) -> u64 {
+        self.propagation_wipe_out
+    }
+
+    /// Number of players placed by propagation rather than by branching
+    pub const fn get_propagations(&self) -> u64 {
+        self.propagations
+    }
+
+    pub fn merge(&mut self, other: &Self) {
+        if other.nodes_per_depth.len() > self.nodes_per_depth.len() {
+            self.nodes_per_depth.resize(other.nodes_per_depth.len(), 0);
+        }
+        for (total, count) in self.nodes_per_depth.iter_mut().zip(&other.nodes_per_depth) {
+            *total += count;
+        }
+        self.branches += other.branches;
+        self.branch_candidates += other.branch_candidates;
+        self.table_overfull += other.table_overfull;
+        self.no_candidate += other.no_candidate;
+        self.propagation_wipe_out += other.propagation_wipe_out;
+        self.propagations += other.propagations;
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes_per_depth.clear();
+        self.branches = 0;
+        self.branch_candidates = 0;
+        self.table_overfull = 0;
+        self.no_candidate = 0;
+        self.propagation_wipe_out = 0;
+        self.propagations = 0;
+    }
+}
@@ -0,0 +1,501 @@
+/// Formats a schedule can be rendered in for people and other programs
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Format {
+    /// One row per game with its round, table and players
+    Csv,
+    /// The schedule's shape and matches, with names in place of player numbers if there are any
+    Json,
+    /// A table with a row per round and a column per table
+    Markdown,
+    /// A self-contained page which prints each round on its own sheet
+    Html,
+}
+
+impl Format {
+    pub const ALL: [Format; 4] = [Format::Csv, Format::Json, Format::Markdown, Format::Html];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Json => "json",
+            Format::Markdown => "markdown",
+            Format::Html => "html",
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Format::Csv => "text/csv; charset=utf-8",
+            Format::Json => "application/json",
+            Format::Markdown => "text/markdown; charset=utf-8",
+            Format::Html => "text/html; charset=utf-8",
+        }
+    }
+}
+
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "md" => Ok(Format::Markdown),
+            _ => Format::ALL
+                .iter()
+                .copied()
+                .find(|format| format.name() == text)
+                .ok_or_else(|| {
+                    format!(
+                        "Unknown format '{}', expected csv, json, markdown or html",
+                        text
+                    )
+                }),
+        }
+    }
+}
+
+/// Where a player sits in one round, as the table and everyone at it listed team by team
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Seat {
+    pub table: usize,
+    pub players: Vec<usize>,
+}
+
+/// A schedule to render, as rounds of tables of player numbers listed team by team
+#[derive(Copy, Clone, Debug)]
+pub struct Layout<'a> {
+    pub player_count: usize,
+    pub matches: &'a [Vec<Vec<usize>>],
+    /// Number of teams at each table, 1 when players don't play in teams
+    pub teams: usize,
+    /// Names shown in place of player numbers, indexed by player number. Players without a name are shown by number
+    pub names: &'a [String],
+}
+
+impl<'a> Layout<'a> {
+    pub fn new(player_count: usize, matches: &'a [Vec<Vec<usize>>]) -> Self {
+        Self {
+            player_count,
+            matches,
+            teams: 1,
+            names: &[],
+        }
+    }
+
+    pub fn label(&self, player: usize) -> String {
+        match self.names.get(player) {
+            Some(name) => name.clone(),
+            None => player.to_string(),
+        }
+    }
+
+    /// The players at a table split into teams, with the first players in the first team and the extra players in the earlier teams
+    fn teams_at(&self, players: &[usize]) -> Vec<Vec<String>> {
+        let teams = self.teams.max(1);
+        let mut split = vec![Vec::new(); teams];
+        for (position, &player) in players.iter().enumerate() {
+            split[position * teams / players.len()].push(self.label(player));
+        }
+        split
+    }
+
+    /// A game as "A, B, C", or "A+B vs C+D" when playing in teams
+    pub fn game(&self, players: &[usize]) -> String {
+        if self.teams > 1 {
+            self.teams_at(players)
+                .iter()
+                .map(|team| team.join("+"))
+                .collect::<Vec<String>>()
+                .join(" vs ")
+        } else {
+            self.teams_at(players)[0].join(", ")
+        }
+    }
+
+    /// Everyone else at the player's seat
+    pub fn others(&self, player: usize, seat: &Seat) -> Vec<String> {
+        seat.players
+            .iter()
+            .filter(|&&other| other != player)
+            .map(|&other| self.label(other))
+            .collect()
+    }
+
+    /// Who the player meets at a seat as "B, C", or the whole game as "A+B vs C+D" when playing in teams
+    pub fn meets(&self, player: usize, seat: &Seat) -> String {
+        if self.teams > 1 {
+            self.game(&seat.players)
+        } else {
+            self.others(player, seat).join(", ")
+        }
+    }
+
+    fn tables(&self) -> usize {
+        self.matches.first().map_or(0, |round| round.len())
+    }
+}
+
+/// Render the schedule in the format
+pub fn render(format: Format, layout: &Layout) -> String {
+    match format {
+        Format::Csv => csv(layout),
+        Format::Json => json(layout),
+        Format::Markdown => markdown(layout),
+        Format::Html => html(layout),
+    }
+}
+
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+/// A header row, then a row of round, table and players for each game, with rounds and tables numbered from 1
+pub fn csv(layout: &Layout) -> String {
+    let mut output = String::from("round,table,players\r\n");
+    for (round, games) in layout.matches.iter().enumerate() {
+        for (table, players) in games.iter().enumerate() {
+            output.push_str(&format!(
+                "{},{},{}\r\n",
+                round + 1,
+                table + 1,
+                csv_field(&layout.game(players))
+            ));
+        }
+    }
+    output
+}
+
+fn json_string(text: &str) -> String {
+    let mut output = String::from("\"");
+    for character in text.chars() {
+        match character {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            character if (character as u32) < 0x20 => {
+                output.push_str(&format!("\\u{:04x}", character as u32))
+            }
+            character => output.push(character),
+        }
+    }
+    output.push('"');
+    output
+}
+
+/// Pretty printed, in the same shape as saved schedules with one table to a line. Players are numbers, or strings when they have names
+pub fn json(layout: &Layout) -> String {
+    let player = |player: usize| match layout.names.get(player) {
+        Some(name) => json_string(name),
+        None => player.to_string(),
+    };
+    let mut output = format!(
+        "{{\n  \"player_count\": {},\n  \"tables\": {},\n  \"rounds\": {},\n",
+        layout.player_count,
+        layout.tables(),
+        layout.matches.len()
+    );
+    if layout.teams > 1 {
+        output.push_str(&format!("  \"teams\": {},\n", layout.teams));
+    }
+    output.push_str("  \"matches\": [");
+    for (round, games) in layout.matches.iter().enumerate() {
+        output.push_str(if round == 0 { "\n    [" } else { ",\n    [" });
+        for (table, players) in games.iter().enumerate() {
+            output.push_str(if table == 0 {
+                "\n      ["
+            } else {
+                ",\n      ["
+            });
+            output.push_str(
+                &players
+                    .iter()
+                    .map(|&number| player(number))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            );
+            output.push(']');
+        }
+        output.push_str("\n    ]");
+    }
+    output.push_str("\n  ]\n}\n");
+    output
+}
+
+fn markdown_cell(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace('|', "\\|")
+        .replace(['\n', '\r'], " ")
+}
+
+/// A table with a row for each round and a column for each table
+pub fn markdown(layout: &Layout) -> String {
+    let tables = layout.tables();
+    let mut output = String::from("| Round |");
+    for table in 0..tables {
+        output.push_str(&format!(" Table {} |", table + 1));
+    }
+    output.push_str("\n| ---: |");
+    for _ in 0..tables {
+        output.push_str(" --- |");
+    }
+    output.push('\n');
+    for (round, games) in layout.matches.iter().enumerate() {
+        output.push_str(&format!("| {} |", round + 1));
+        for players in games.iter() {
+            output.push_str(&format!(" {} |", markdown_cell(&layout.game(players))));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+fn html_text(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// A page with no outside resources, with each round as a table on its own printed sheet
+pub fn html(layout: &Layout) -> String {
+    let mut output = String::from(concat!(
+        "<!DOCTYPE html>\n",
+        "<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Schedule</title>\n<style>\n",
+        "body { font-family: sans-serif; }\n",
+        "section { break-after: page; page-break-after: always; }\n",
+        "section:last-child { break-after: auto; page-break-after: auto; }\n",
+        "table { border-collapse: collapse; width: 100%; font-size: 1.5em; }\n",
+        "th, td { border: 1px solid black; padding: 0.5em; text-align: left; }\n",
+        "</style>\n</head>\n<body>\n"
+    ));
+    for (round, games) in layout.matches.iter().enumerate() {
+        output.push_str(&format!(
+            "<section>\n<h1>Round {}</h1>\n<table>\n<tr><th>Table</th><th>Players</th></tr>\n",
+            round + 1
+        ));
+        for (table, players) in games.iter().enumerate() {
+            output.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td></tr>\n",
+                table + 1,
+                html_text(&layout.game(players))
+            ));
+        }
+        output.push_str("</table>\n</section>\n");
+    }
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+/// Render where players sit in the format. itineraries has a player number and their seat in every round for each player to show
+pub fn render_itineraries(
+    format: Format,
+    layout: &Layout,
+    itineraries: &[(usize, Vec<Seat>)],
+) -> String {
+    match format {
+        Format::Csv => itinerary_csv(layout, itineraries),
+        Format::Json => itinerary_json(layout, itineraries),
+        Format::Markdown => itinerary_markdown(layout, itineraries),
+        Format::Html => itinerary_cards(layout, itineraries),
+    }
+}
+
+/// A header row, then a row of player, round, table and who they meet for each player in each round, with rounds and tables numbered from 1
+pub fn itinerary_csv(layout: &Layout, itineraries: &[(usize, Vec<Seat>)]) -> String {
+    let mut output = String::from("player,round,table,meets\r\n");
+    for &(player, ref seats) in itineraries.iter() {
+        for (round, seat) in seats.iter().enumerate() {
+            output.push_str(&format!(
+                "{},{},{},{}\r\n",
+                csv_field(&layout.label(player)),
+                round + 1,
+                seat.table + 1,
+                csv_field(&layout.meets(player, seat))
+            ));
+        }
+    }
+    output
+}
+
+/// Pretty printed, with the table and everyone else at it for each round of each player, with tables numbered from 1
+pub fn itinerary_json(layout: &Layout, itineraries: &[(usize, Vec<Seat>)]) -> String {
+    let player = |player: usize| match layout.names.get(player) {
+        Some(name) => json_string(name),
+        None => player.to_string(),
+    };
+    let mut output = String::from("[");
+    for (index, (number, seats)) in itineraries.iter().enumerate() {
+        output.push_str(if index == 0 { "\n" } else { ",\n" });
+        output.push_str(&format!(
+            "  {{\n    \"player\": {},\n    \"rounds\": [",
+            player(*number)
+        ));
+        for (round, seat) in seats.iter().enumerate() {
+            output.push_str(if round == 0 { "\n" } else { ",\n" });
+            output.push_str(&format!(
+                "      {{\"table\": {}, \"with\": [{}]}}",
+                seat.table + 1,
+                seat.players
+                    .iter()
+                    .filter(|&other| other != number)
+                    .map(|&other| player(other))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            ));
+        }
+        output.push_str("\n    ]\n  }");
+    }
+    output.push_str("\n]\n");
+    output
+}
+
+/// A heading and a table with a row for each round for each player
+pub fn itinerary_markdown(layout: &Layout, itineraries: &[(usize, Vec<Seat>)]) -> String {
+    let mut output = String::new();
+    for &(player, ref seats) in itineraries.iter() {
+        output.push_str(&format!(
+            "## {}\n\n| Round | Table | Meets |\n| ---: | ---: | --- |\n",
+            markdown_cell(&layout.label(player))
+        ));
+        for (round, seat) in seats.iter().enumerate() {
+            output.push_str(&format!(
+                "| {} | {} | {} |\n",
+                round + 1,
+                seat.table + 1,
+                markdown_cell(&layout.meets(player, seat))
+            ));
+        }
+        output.push('\n');
+    }
+    output
+}
+
+/// A page with no outside resources, with a card for each player to cut out and hand them, which is never split between sheets
+pub fn itinerary_cards(layout: &Layout, itineraries: &[(usize, Vec<Seat>)]) -> String {
+    let mut output = String::from(concat!(
+        "<!DOCTYPE html>\n",
+        "<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Itineraries</title>\n<style>\n",
+        "body { font-family: sans-serif; }\n",
+        "section { display: inline-block; vertical-align: top; width: 45%; margin: 1%; padding: 1em; ",
+        "border: 1px dashed black; break-inside: avoid; page-break-inside: avoid; }\n",
+        "table { border-collapse: collapse; width: 100%; }\n",
+        "th, td { border: 1px solid black; padding: 0.3em; text-align: left; }\n",
+        "</style>\n</head>\n<body>\n"
+    ));
+    for &(player, ref seats) in itineraries.iter() {
+        output.push_str(&format!(
+            "<section>\n<h2>{}</h2>\n<table>\n<tr><th>Round</th><th>Table</th><th>Meets</th></tr>\n",
+            html_text(&layout.label(player))
+        ));
+        for (round, seat) in seats.iter().enumerate() {
+            output.push_str(&format!(
+                "<tr><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                round + 1,
+                seat.table + 1,
+                html_text(&layout.meets(player, seat))
+            ));
+        }
+        output.push_str("</table>\n</section>\n");
+    }
+    output.push_str("</body>\n</html>\n");
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches() -> Vec<Vec<Vec<usize>>> {
+        vec![vec![vec![0, 1], vec![2, 3]], vec![vec![0, 2], vec![1, 3]]]
+    }
+
+    #[test]
+    fn renders_numbers_and_teams() {
+        let matches = matches();
+        let mut layout = Layout::new(4, &matches);
+        assert_eq!(
+            csv(&layout),
+            "round,table,players\r\n1,1,\"0, 1\"\r\n1,2,\"2, 3\"\r\n2,1,\"0, 2\"\r\n2,2,\"1, 3\"\r\n"
+        );
+        assert_eq!(
+            markdown(&layout),
+            "| Round | Table 1 | Table 2 |\n| ---: | --- | --- |\n| 1 | 0, 1 | 2, 3 |\n| 2 | 0, 2 | 1, 3 |\n"
+        );
+        assert!(json(&layout).contains(
+            "\"rounds\": 2,\n  \"matches\": [\n    [\n      [0, 1],\n      [2, 3]\n    ],"
+        ));
+        layout.teams = 2;
+        assert_eq!(layout.game(&[0, 1]), "0 vs 1");
+        assert!(json(&layout).contains("\"teams\": 2"));
+    }
+
+    #[test]
+    fn names_replace_numbers_and_are_escaped() {
+        let matches = matches();
+        let names = vec![
+            "Ann".to_string(),
+            "Bo \"B\" | <b>".to_string(),
+            "Cy, Jr".to_string(),
+        ];
+        let layout = Layout {
+            names: &names,
+            ..Layout::new(4, &matches)
+        };
+        assert_eq!(layout.game(&[0, 1]), "Ann, Bo \"B\" | <b>");
+        assert_eq!(layout.game(&[2, 3]), "Cy, Jr, 3");
+        assert!(csv(&layout).contains("1,1,\"Ann, Bo \"\"B\"\" | <b>\"\r\n"));
+        assert!(markdown(&layout).contains("| 1 | Ann, Bo \"B\" \\| <b> |"));
+        assert!(json(&layout).contains("[\"Ann\", \"Bo \\\"B\\\" | <b>\"]"));
+        assert!(json(&layout).contains("[\"Cy, Jr\", 3]"));
+        let page = html(&layout);
+        assert!(page.contains("<td>Ann, Bo &quot;B&quot; | &lt;b&gt;</td>"));
+        assert_eq!(page.matches("<section>").count(), 2);
+    }
+
+    #[test]
+    fn itineraries_show_who_each_player_meets() {
+        let matches = matches();
+        let names = vec!["Ann".to_string(), "Bo, Jr".to_string()];
+        let mut layout = Layout {
+            names: &names,
+            ..Layout::new(4, &matches)
+        };
+        let seat = |table: usize, players: &[usize]| Seat {
+            table,
+            players: players.to_vec(),
+        };
+        let itineraries = vec![
+            (0, vec![seat(0, &[0, 1]), seat(0, &[0, 2])]),
+            (1, vec![seat(0, &[0, 1]), seat(1, &[1, 3])]),
+        ];
+        assert_eq!(
+            itinerary_csv(&layout, &itineraries),
+            "player,round,table,meets\r\nAnn,1,1,\"Bo, Jr\"\r\nAnn,2,1,2\r\n\"Bo, Jr\",1,1,Ann\r\n\"Bo, Jr\",2,2,3\r\n"
+        );
+        assert!(itinerary_markdown(&layout, &itineraries).starts_with(
+            "## Ann\n\n| Round | Table | Meets |\n| ---: | ---: | --- |\n| 1 | 1 | Bo, Jr |\n"
+        ));
+        assert!(itinerary_json(&layout, &itineraries).contains(
+            "\"player\": \"Bo, Jr\",\n    \"rounds\": [\n      {\"table\": 1, \"with\": [\"Ann\"]},\n      {\"table\": 2, \"with\": [3]}"
+        ));
+        let cards = itinerary_cards(&layout, &itineraries);
+        assert_eq!(cards.matches("<section>").count(), 2);
+        assert!(cards.contains("<tr><td>2</td><td>2</td><td>3</td></tr>"));
+        layout.teams = 2;
+        assert_eq!(layout.meets(0, &itineraries[0].1[0]), "Ann vs Bo, Jr");
+    }
+
+    #[test]
+    fn parses_format_names() {
+        for &format in Format::ALL.iter() {
+            assert_eq!(format.name().parse(), Ok(format));
+        }
+        assert_eq!("md".parse(), Ok(Format::Markdown));
+        assert!("pdf".parse::<Format>().is_err());
+    }
+}
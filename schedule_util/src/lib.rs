@@ -4,6 +4,9 @@ extern crate quickcheck;
 #[macro_use(quickcheck)]
 extern crate quickcheck_macros;
 
+pub mod format;
+pub mod validate;
+
 #[derive(
     Clone, PartialOrd, Ord, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize, Debug,
 )]
@@ -27,10 +30,57 @@ impl ScheduleArg {
     }
 }
 
-#[derive(Debug, Default, Copy, Clone)]
+#[derive(Debug, Default, Clone)]
 pub struct Stats {
     pub steps: u64,
     pub elapsed: std::time::Duration,
+    pub propagations: u64,
+    pub branches: u64,
+    pub branch_candidates: u64,
+    pub table_overfull: u64,
+    pub no_candidate: u64,
+    /// Forced placements which left a table unable to be filled. These aren't failures, the branch fails later
+    pub propagation_wipe_out: u64,
+    /// Number of nodes expanded at each depth, where depth is the number of players placed
+    pub nodes_per_depth: Vec<u64>,
+}
+
+impl Stats {
+    pub fn merge(&mut self, other: &Self) {
+        self.steps += other.steps;
+        self.elapsed += other.elapsed;
+        self.propagations += other.propagations;
+        self.branches += other.branches;
+        self.branch_candidates += other.branch_candidates;
+        self.table_overfull += other.table_overfull;
+        self.no_candidate += other.no_candidate;
+        self.propagation_wipe_out += other.propagation_wipe_out;
+        if other.nodes_per_depth.len() > self.nodes_per_depth.len() {
+            self.nodes_per_depth.resize(other.nodes_per_depth.len(), 0);
+        }
+        for (total, count) in self.nodes_per_depth.iter_mut().zip(&other.nodes_per_depth) {
+            *total += count;
+        }
+    }
+
+    pub fn failures(&self) -> u64 {
+        self.table_overfull + self.no_candidate
+    }
+
+    pub fn max_depth(&self) -> usize {
+        self.nodes_per_depth
+            .iter()
+            .rposition(|&count| count != 0)
+            .unwrap_or(0)
+    }
+
+    pub fn branching_factor(&self) -> f64 {
+        if self.branches == 0 {
+            0.0
+        } else {
+            self.branch_candidates as f64 / self.branches as f64
+        }
+    }
 }
 
 #[derive(
@@ -199,6 +249,10 @@ impl<'a> BatchDeserialize<'a> {
     }
 }
 
+/// Id, lengths, step count, elapsed time, then the search counters from `Stats` ending with the number of depths.
+/// The nodes at each depth come after the notable blocks
+const OUTPUT_HEADER_SIZE: usize = 44 + 7 * 8;
+
 #[derive(Debug)]
 pub struct BatchOutputSerialize<'a> {
     base: BatchId,
@@ -225,7 +279,8 @@ impl<'a> BatchOutputSerialize<'a> {
         }
     }
     pub fn get_size(&self) -> usize {
-        44 + (self.children.len() + self.notable.len()) * 8
+        OUTPUT_HEADER_SIZE
+            + (self.children.len() + self.notable.len() + self.stats.nodes_per_depth.len()) * 8
     }
 
     pub fn serialize(&self, buf: &mut [u8]) -> Result<(), ConvertError> {
@@ -236,7 +291,19 @@ impl<'a> BatchOutputSerialize<'a> {
             buf[24..32].copy_from_slice(&self.stats.steps.to_le_bytes());
             buf[32..40].copy_from_slice(&self.stats.elapsed.as_secs().to_le_bytes());
             buf[40..44].copy_from_slice(&self.stats.elapsed.subsec_nanos().to_le_bytes());
-            let offset = 44;
+            let counters = [
+                self.stats.propagations,
+                self.stats.branches,
+                self.stats.branch_candidates,
+                self.stats.table_overfull,
+                self.stats.no_candidate,
+                self.stats.propagation_wipe_out,
+                self.stats.nodes_per_depth.len() as u64,
+            ];
+            for (i, val) in counters.iter().enumerate() {
+                buf[44 + i * 8..44 + (i + 1) * 8].copy_from_slice(&val.to_le_bytes());
+            }
+            let offset = OUTPUT_HEADER_SIZE;
             for (i, val) in self.children.iter().enumerate() {
                 buf[i * 8 + offset..(i + 1) * 8 + offset].copy_from_slice(&val.to_le_bytes());
             }
@@ -244,6 +311,10 @@ impl<'a> BatchOutputSerialize<'a> {
             for (i, val) in self.notable.iter().enumerate() {
                 buf[i * 8 + offset..(i + 1) * 8 + offset].copy_from_slice(&val.to_le_bytes());
             }
+            let offset = offset + self.notable.len() * 8;
+            for (i, val) in self.stats.nodes_per_depth.iter().enumerate() {
+                buf[i * 8 + offset..(i + 1) * 8 + offset].copy_from_slice(&val.to_le_bytes());
+            }
             Ok(())
         } else {
             Err(ConvertError {})
@@ -262,7 +333,7 @@ pub struct BatchOutputDeserialize<'a> {
 
 impl<'a> BatchOutputDeserialize<'a> {
     pub fn deserialize(block_size: usize, data: &'a [u8]) -> Result<Self, ConvertError> {
-        if data.len() < 44 {
+        if data.len() < OUTPUT_HEADER_SIZE {
             return Err(ConvertError {});
         }
         let mut array = [0; 8];
@@ -279,11 +350,27 @@ impl<'a> BatchOutputDeserialize<'a> {
         let mut array = [0; 4];
         array.copy_from_slice(&data[40..44]);
         let nanos = u32::from_le_bytes(array);
-        let total_length = ((children_length + notable_length) as u64 * 8 + 44) as usize;
-        if total_length > data.len() {
+        let mut counters = [0; 7];
+        let mut array = [0; 8];
+        for (i, counter) in counters.iter_mut().enumerate() {
+            array.copy_from_slice(&data[44 + i * 8..44 + (i + 1) * 8]);
+            *counter = u64::from_le_bytes(array);
+        }
+        let depths = counters[6];
+        let total_length =
+            ((children_length + notable_length) * 8 + OUTPUT_HEADER_SIZE as u64) as usize;
+        if total_length > data.len() || depths > ((data.len() - total_length) / 8) as u64 {
             return Err(ConvertError {});
         }
-        let (children, notable) = data[44..total_length].split_at(children_length as usize * 8);
+        let (children, notable) =
+            data[OUTPUT_HEADER_SIZE..total_length].split_at(children_length as usize * 8);
+        let nodes_per_depth = data[total_length..total_length + depths as usize * 8]
+            .chunks_exact(8)
+            .map(|chunk| {
+                array.copy_from_slice(chunk);
+                u64::from_le_bytes(array)
+            })
+            .collect();
         Ok(Self {
             base: BatchId::new(id),
             block_size,
@@ -292,6 +379,13 @@ impl<'a> BatchOutputDeserialize<'a> {
             stats: Stats {
                 steps: steps,
                 elapsed: std::time::Duration::new(secs, nanos),
+                propagations: counters[0],
+                branches: counters[1],
+                branch_candidates: counters[2],
+                table_overfull: counters[3],
+                no_candidate: counters[4],
+                propagation_wipe_out: counters[5],
+                nodes_per_depth,
             },
         })
     }
@@ -309,7 +403,7 @@ impl<'a> BatchOutputDeserialize<'a> {
         }
     }
     pub fn get_stats(&self) -> Stats {
-        self.stats
+        self.stats.clone()
     }
 }
 
@@ -329,6 +423,37 @@ mod tests {
             vec![0]
         );
     }
+    #[test]
+    fn nodes_per_depth_are_sent_and_merged() {
+        let mut stats = Stats {
+            steps: 3,
+            propagation_wipe_out: 1,
+            nodes_per_depth: vec![1, 2, 0],
+            ..Stats::default()
+        };
+        let s = BatchOutputSerialize::new(BatchId::new(7), 1, &[5], &[6], stats.clone());
+        let mut buf = vec![0; s.get_size()];
+        s.serialize(&mut buf).unwrap();
+        let deserialize = BatchOutputDeserialize::deserialize(1, &buf).unwrap();
+        assert_eq!(deserialize.get_stats().nodes_per_depth, vec![1, 2, 0]);
+        assert_eq!(
+            deserialize.get_children().flatten().collect::<Vec<_>>(),
+            vec![5]
+        );
+        assert_eq!(
+            deserialize.get_notable().flatten().collect::<Vec<_>>(),
+            vec![6]
+        );
+        assert!(BatchOutputDeserialize::deserialize(1, &buf[..buf.len() - 1]).is_err());
+        stats.merge(&Stats {
+            nodes_per_depth: vec![0, 1, 1, 4],
+            ..Stats::default()
+        });
+        assert_eq!(stats.nodes_per_depth, vec![1, 3, 1, 4]);
+        assert_eq!(stats.max_depth(), 3);
+        assert_eq!(stats.failures(), 0);
+    }
+
     #[quickcheck]
     fn deserialize_does_not_panic(block_size: std::num::NonZeroUsize, data: Vec<u8>) {
         let _ = BatchOutputDeserialize::deserialize(block_size.get(), &data);
@@ -342,6 +467,8 @@ mod tests {
         notable: Vec<u64>,
         steps: u64,
         elapsed: std::time::Duration,
+        propagations: u64,
+        no_candidate: u64,
     ) {
         let block_size = block_size.get();
         let children = &children[..children.len() / block_size * block_size];
@@ -351,7 +478,13 @@ mod tests {
             block_size,
             children,
             notable,
-            Stats { steps, elapsed },
+            Stats {
+                steps,
+                elapsed,
+                propagations,
+                no_candidate,
+                ..Stats::default()
+            },
         );
         let mut buf = vec![0; s.get_size()];
         s.serialize(&mut buf).unwrap();
@@ -360,6 +493,8 @@ mod tests {
         assert_eq!(id, deserialize.get_base().id);
         assert_eq!(steps, deserialize.get_stats().steps);
         assert_eq!(elapsed, deserialize.get_stats().elapsed);
+        assert_eq!(propagations, deserialize.get_stats().propagations);
+        assert_eq!(no_candidate, deserialize.get_stats().no_candidate);
         assert_eq!(id, deserialize.get_base().id);
         assert_eq!(
             &children,
@@ -0,0 +1,513 @@
+/// Shape a schedule is expected to have
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Spec {
+    pub player_count: usize,
+    pub rounds: usize,
+    /// Minimum and maximum number of players allowed at each table
+    pub capacities: Vec<(usize, usize)>,
+}
+
+impl Spec {
+    /// Every table has exactly the given number of players, as used by the exact solver
+    pub fn exact(table_sizes: &[usize], rounds: usize) -> Self {
+        Self {
+            player_count: table_sizes.iter().sum(),
+            rounds,
+            capacities: table_sizes.iter().map(|&size| (size, size)).collect(),
+        }
+    }
+
+    /// Players spread as evenly as possible over the tables, as used by the local search
+    pub fn balanced(player_count: usize, tables: usize, rounds: usize) -> Self {
+        let min = player_count / tables.max(1);
+        let max = min + usize::from(!player_count.is_multiple_of(tables.max(1)));
+        Self {
+            player_count,
+            rounds,
+            capacities: vec![(min, max); tables],
+        }
+    }
+}
+
+/// Scores that a schedule claims to reach. Unset fields are not checked
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Claim {
+    pub unique_opponents: Option<u32>,
+    pub min_unique_opponents: Option<u32>,
+    pub unique_games_played: Option<u32>,
+    pub max_repeat_pairings: Option<usize>,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ClaimKind {
+    UniqueOpponents,
+    MinUniqueOpponents,
+    UniqueGamesPlayed,
+    RepeatPairings,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Violation {
+    RoundCount {
+        expected: usize,
+        found: usize,
+    },
+    TableCount {
+        round: usize,
+        expected: usize,
+        found: usize,
+    },
+    TableSize {
+        round: usize,
+        table: usize,
+        min: usize,
+        max: usize,
+        found: usize,
+    },
+    PlayerOutOfRange {
+        round: usize,
+        table: usize,
+        player: usize,
+    },
+    PlayerRepeated {
+        round: usize,
+        player: usize,
+    },
+    PlayerMissing {
+        round: usize,
+        player: usize,
+    },
+    ClaimNotMet {
+        kind: ClaimKind,
+        claimed: u64,
+        found: u64,
+    },
+    /// Fewer than two tables, so there is nobody to swap with
+    TooFewTables {
+        found: usize,
+    },
+    NoRounds,
+    /// Fewer than two players for each table
+    TooFewPlayers {
+        tables: usize,
+        found: usize,
+    },
+    /// A table which could seat fewer than two players, or whose minimum is above its maximum
+    TableCapacity {
+        table: usize,
+        min: usize,
+        max: usize,
+    },
+    /// A schedule listing table capacities for a different number of tables than it has
+    CapacityCount {
+        expected: usize,
+        found: usize,
+    },
+    /// More teams than players at the smallest table, or more than 255
+    TooManyTeams {
+        teams: usize,
+        max: usize,
+    },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Violation::RoundCount { expected, found } => {
+                write!(f, "expected {} rounds, found {}", expected, found)
+            }
+            Violation::TableCount {
+                round,
+                expected,
+                found,
+            } => write!(
+                f,
+                "round {}: expected {} tables, found {}",
+                round + 1,
+                expected,
+                found
+            ),
+            Violation::TableSize {
+                round,
+                table,
+                min,
+                max,
+                found,
+            } => write!(
+                f,
+                "round {} table {}: {} players, expected between {} and {}",
+                round + 1,
+                table + 1,
+                found,
+                min,
+                max
+            ),
+            Violation::PlayerOutOfRange {
+                round,
+                table,
+                player,
+            } => write!(
+                f,
+                "round {} table {}: unknown player {}",
+                round + 1,
+                table + 1,
+                player
+            ),
+            Violation::PlayerRepeated { round, player } => {
+                write!(
+                    f,
+                    "round {}: player {} placed more than once",
+                    round + 1,
+                    player
+                )
+            }
+            Violation::PlayerMissing { round, player } => {
+                write!(f, "round {}: player {} not placed", round + 1, player)
+            }
+            Violation::ClaimNotMet {
+                kind,
+                claimed,
+                found,
+            } => write!(f, "claimed {:?} of {}, found {}", kind, claimed, found),
+            Violation::TooFewTables { found } => {
+                write!(f, "expected at least 2 tables, found {}", found)
+            }
+            Violation::NoRounds => write!(f, "expected at least 1 round"),
+            Violation::TooFewPlayers { tables, found } => write!(
+                f,
+                "expected at least {} players for {} tables, found {}",
+                2 * tables,
+                tables,
+                found
+            ),
+            Violation::TableCapacity { table, min, max } => write!(
+                f,
+                "table {}: between {} and {} players, expected at least 2",
+                table + 1,
+                min,
+                max
+            ),
+            Violation::CapacityCount { expected, found } => write!(
+                f,
+                "expected capacities for {} tables, found {}",
+                expected, found
+            ),
+            Violation::TooManyTeams { teams, max } => {
+                write!(f, "{} teams, expected at most {}", teams, max)
+            }
+        }
+    }
+}
+
+/// A pair of players who share a table in more than one round
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct RepeatPairing {
+    pub players: (usize, usize),
+    pub meetings: usize,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Report {
+    pub violations: Vec<Violation>,
+    pub repeat_pairings: Vec<RepeatPairing>,
+    pub unique_opponents: u32,
+    pub min_unique_opponents: u32,
+    pub unique_games_played: u32,
+}
+
+impl Report {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+/// Problems with the spec itself which stop the local search from holding a schedule of that shape:
+/// fewer than two tables, no rounds, a table which could seat fewer than two players, or more teams than the smallest table has players or 255
+pub fn check_spec(spec: &Spec, teams: usize) -> Vec<Violation> {
+    let mut violations = Vec::new();
+    let tables = spec.capacities.len();
+    if tables < 2 {
+        violations.push(Violation::TooFewTables { found: tables });
+    }
+    if spec.rounds == 0 {
+        violations.push(Violation::NoRounds);
+    }
+    if spec.player_count < 2 * tables {
+        violations.push(Violation::TooFewPlayers {
+            tables,
+            found: spec.player_count,
+        });
+    } else {
+        for (table, &(min, max)) in spec.capacities.iter().enumerate() {
+            if min < 2 || min > max {
+                violations.push(Violation::TableCapacity { table, min, max });
+            }
+        }
+    }
+    let max_teams = spec
+        .capacities
+        .iter()
+        .map(|&(min, _)| min)
+        .fold(usize::from(u8::MAX), usize::min);
+    if teams > max_teams {
+        violations.push(Violation::TooManyTeams {
+            teams,
+            max: max_teams,
+        });
+    }
+    violations
+}
+
+/// Check a schedule, stored as rounds of tables of player numbers, against the spec and claim.
+/// Does not stop at the first problem, so the report lists every violation found
+pub fn validate(spec: &Spec, matches: &[Vec<Vec<usize>>], claim: &Claim) -> Report {
+    let player_count = spec.player_count;
+    let mut violations = Vec::new();
+    if matches.len() != spec.rounds {
+        violations.push(Violation::RoundCount {
+            expected: spec.rounds,
+            found: matches.len(),
+        });
+    }
+    let tables = spec.capacities.len();
+    let mut meetings = vec![0_usize; player_count * player_count];
+    let max_tables = tables.max(matches.iter().map(Vec::len).max().unwrap_or(0));
+    let mut played_on_table = vec![false; player_count * max_tables];
+    for (round_number, round) in matches.iter().enumerate() {
+        if round.len() != tables {
+            violations.push(Violation::TableCount {
+                round: round_number,
+                expected: tables,
+                found: round.len(),
+            });
+        }
+        let mut placed = vec![false; player_count];
+        for (table_number, table) in round.iter().enumerate() {
+            if let Some(&(min, max)) = spec.capacities.get(table_number) {
+                if table.len() < min || table.len() > max {
+                    violations.push(Violation::TableSize {
+                        round: round_number,
+                        table: table_number,
+                        min,
+                        max,
+                        found: table.len(),
+                    });
+                }
+            }
+            for (i, &player) in table.iter().enumerate() {
+                if player >= player_count {
+                    violations.push(Violation::PlayerOutOfRange {
+                        round: round_number,
+                        table: table_number,
+                        player,
+                    });
+                    continue;
+                }
+                if placed[player] {
+                    violations.push(Violation::PlayerRepeated {
+                        round: round_number,
+                        player,
+                    });
+                    continue;
+                }
+                placed[player] = true;
+                played_on_table[table_number * player_count + player] = true;
+                for &other in &table[..i] {
+                    if other < player_count && other != player {
+                        meetings[player * player_count + other] += 1;
+                        meetings[other * player_count + player] += 1;
+                    }
+                }
+            }
+        }
+        for (player, &was_placed) in placed.iter().enumerate() {
+            if !was_placed {
+                violations.push(Violation::PlayerMissing {
+                    round: round_number,
+                    player,
+                });
+            }
+        }
+    }
+
+    let mut repeat_pairings = Vec::new();
+    let mut unique_opponents = 0;
+    let mut min_unique_opponents = if player_count == 0 { 0 } else { u32::MAX };
+    for player in 0..player_count {
+        let row = &meetings[player * player_count..(player + 1) * player_count];
+        let opponents = row.iter().filter(|&&count| count != 0).count() as u32;
+        unique_opponents += opponents;
+        min_unique_opponents = min_unique_opponents.min(opponents);
+        for (other, &count) in row.iter().enumerate().skip(player + 1) {
+            if count > 1 {
+                repeat_pairings.push(RepeatPairing {
+                    players: (player, other),
+                    meetings: count,
+                });
+            }
+        }
+    }
+    let unique_games_played = played_on_table.iter().filter(|&&played| played).count() as u32;
+
+    let mut check = |kind: ClaimKind, claimed: Option<u64>, found: u64, higher_is_better: bool| {
+        if let Some(claimed) = claimed {
+            if (higher_is_better && found < claimed) || (!higher_is_better && found > claimed) {
+                violations.push(Violation::ClaimNotMet {
+                    kind,
+                    claimed,
+                    found,
+                });
+            }
+        }
+    };
+    check(
+        ClaimKind::UniqueOpponents,
+        claim.unique_opponents.map(u64::from),
+        u64::from(unique_opponents),
+        true,
+    );
+    check(
+        ClaimKind::MinUniqueOpponents,
+        claim.min_unique_opponents.map(u64::from),
+        u64::from(min_unique_opponents),
+        true,
+    );
+    check(
+        ClaimKind::UniqueGamesPlayed,
+        claim.unique_games_played.map(u64::from),
+        u64::from(unique_games_played),
+        true,
+    );
+    check(
+        ClaimKind::RepeatPairings,
+        claim.max_repeat_pairings.map(|max| max as u64),
+        repeat_pairings.len() as u64,
+        false,
+    );
+
+    Report {
+        violations,
+        repeat_pairings,
+        unique_opponents,
+        min_unique_opponents,
+        unique_games_played,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ideal() -> Vec<Vec<Vec<usize>>> {
+        vec![
+            vec![vec![0, 1], vec![2, 5], vec![3, 4]],
+            vec![vec![4, 5], vec![0, 3], vec![1, 2]],
+            vec![vec![2, 3], vec![1, 4], vec![0, 5]],
+        ]
+    }
+
+    #[test]
+    fn ideal_schedule_is_valid() {
+        let claim = Claim {
+            unique_opponents: Some(18),
+            unique_games_played: Some(18),
+            max_repeat_pairings: Some(0),
+            ..Claim::default()
+        };
+        let report = validate(&Spec::balanced(6, 3, 3), &ideal(), &claim);
+        assert!(report.is_valid(), "{:?}", report.violations);
+        assert_eq!(report.min_unique_opponents, 3);
+    }
+
+    #[test]
+    fn reports_every_shape_violation() {
+        let mut matches = ideal();
+        matches[0][0] = vec![0, 0, 1];
+        matches[1][2] = vec![1, 7];
+        let report = validate(&Spec::exact(&[2, 2, 2], 3), &matches, &Claim::default());
+        assert!(report.violations.contains(&Violation::PlayerRepeated {
+            round: 0,
+            player: 0
+        }));
+        assert!(report.violations.contains(&Violation::TableSize {
+            round: 0,
+            table: 0,
+            min: 2,
+            max: 2,
+            found: 3
+        }));
+        assert!(report.violations.contains(&Violation::PlayerOutOfRange {
+            round: 1,
+            table: 2,
+            player: 7
+        }));
+        assert!(report.violations.contains(&Violation::PlayerMissing {
+            round: 1,
+            player: 2
+        }));
+    }
+
+    #[test]
+    fn reports_unsupported_specs() {
+        assert!(check_spec(&Spec::balanced(6, 3, 3), 2).is_empty());
+        assert_eq!(
+            check_spec(&Spec::balanced(3, 1, 0), 1),
+            vec![Violation::TooFewTables { found: 1 }, Violation::NoRounds]
+        );
+        assert_eq!(
+            check_spec(&Spec::balanced(5, 3, 1), 1),
+            vec![Violation::TooFewPlayers {
+                tables: 3,
+                found: 5
+            }]
+        );
+        let spec = Spec {
+            player_count: 7,
+            rounds: 1,
+            capacities: vec![(1, 3), (4, 3), (3, 3)],
+        };
+        assert_eq!(
+            check_spec(&spec, 3),
+            vec![
+                Violation::TableCapacity {
+                    table: 0,
+                    min: 1,
+                    max: 3
+                },
+                Violation::TableCapacity {
+                    table: 1,
+                    min: 4,
+                    max: 3
+                },
+                Violation::TooManyTeams { teams: 3, max: 1 }
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_repeat_pairings_and_unmet_claims() {
+        let round = vec![vec![0, 1], vec![2, 3]];
+        let matches = vec![round.clone(), round];
+        let claim = Claim {
+            max_repeat_pairings: Some(0),
+            ..Claim::default()
+        };
+        let report = validate(&Spec::balanced(4, 2, 2), &matches, &claim);
+        assert_eq!(report.repeat_pairings.len(), 2);
+        assert_eq!(
+            report.repeat_pairings[0],
+            RepeatPairing {
+                players: (0, 1),
+                meetings: 2
+            }
+        );
+        assert_eq!(
+            report.violations,
+            vec![Violation::ClaimNotMet {
+                kind: ClaimKind::RepeatPairings,
+                claimed: 0,
+                found: 2
+            }]
+        );
+    }
+}
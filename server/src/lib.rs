@@ -75,6 +75,7 @@ pub struct Client {
     last_message: Mutex<std::time::Instant>,
     claimed: Mutex<HashMap<BatchId, (Arc<Batch>, std::time::Instant)>>,
     step_counts: Mutex<RateStats>,
+    search_stats: Mutex<schedule_util::Stats>,
     data_sent: Mutex<RateStats>,
     data_recieved: Mutex<RateStats>,
 }
@@ -100,6 +101,7 @@ impl Client {
             last_message: Mutex::new(std::time::Instant::now()),
             claimed: Mutex::new(HashMap::new()),
             step_counts: Mutex::new(RateStats::new()),
+            search_stats: Mutex::new(schedule_util::Stats::default()),
             data_sent: Mutex::new(RateStats::new()),
             data_recieved: Mutex::new(RateStats::new()),
         }
@@ -126,6 +128,12 @@ impl Client {
     }
     pub fn add_stats(&self, stats: &schedule_util::Stats) {
         self.step_counts.lock().unwrap().add(stats.steps as usize);
+        self.search_stats.lock().unwrap().merge(stats);
+    }
+
+    /// Totals of every batch result the client has sent
+    pub fn get_search_stats(&self) -> schedule_util::Stats {
+        self.search_stats.lock().unwrap().clone()
     }
 
     pub fn get_rate(&self) -> f32 {
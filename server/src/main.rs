@@ -14,10 +14,11 @@ async fn main() {
 
     let favicon = warp::path("favicon.ico").map(favicon);
     let html = ui_pages::get_html_filter(state.clone());
+    let found = ui_pages::get_found_filter(state.clone());
     let api = api::get_api_filter(state.clone());
     println!("Server Launched");
 
-    warp::serve(favicon.or(html).or(api))
+    warp::serve(favicon.or(html).or(found).or(api))
         .run(([0, 0, 0, 0], 3000))
         .await;
 }
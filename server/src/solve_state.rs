@@ -1,5 +1,5 @@
 use crate::*;
-use schedule_util::{Batch, BatchData, BatchId};
+use schedule_util::{validate, Batch, BatchData, BatchId};
 use std::collections::HashSet;
 
 use futures::TryFutureExt;
@@ -11,6 +11,7 @@ pub struct ScheduleState {
     queue: Mutex<VecDeque<(Arc<Client>, OneShotSender)>>,
     next_block_id: AtomicU64,
     block_size: usize,
+    notable: Mutex<Vec<Vec<Vec<Vec<usize>>>>>,
 }
 
 type OneShotSender = tokio::sync::oneshot::Sender<Arc<Batch>>;
@@ -31,6 +32,7 @@ impl ScheduleState {
             queue: Mutex::new(Default::default()),
             next_block_id: AtomicU64::new(1),
             block_size,
+            notable: Mutex::new(Vec::new()),
         }
     }
 
@@ -101,7 +103,9 @@ impl ScheduleState {
                         BatchData::new(child.collect()),
                     )));
                 }
-                // TODO: Handle notable
+                for notable in result.get_notable() {
+                    self.add_notable(client, notable.collect());
+                }
             } else {
                 panic!("Invalid batch result");
             }
@@ -110,6 +114,38 @@ impl ScheduleState {
             // Client has been removed
         }
     }
+    /// Validate a complete schedule sent by a client, only keeping it if no pair meets twice
+    fn add_notable(&self, client: &Arc<Client>, mut block: Vec<u64>) {
+        let scheduler =
+            schedule_solver::Scheduler::new(self.arg.get_tables(), self.arg.get_rounds());
+        let buffer = if let Some(buffer) = scheduler.import_buffer(&mut block) {
+            buffer
+        } else {
+            println!("Invalid notable block size from {}", client.get_id());
+            return;
+        };
+        let matches = scheduler.get_matches(buffer);
+        let spec = validate::Spec::exact(self.arg.get_tables(), self.arg.get_rounds());
+        let claim = validate::Claim {
+            max_repeat_pairings: Some(0),
+            ..validate::Claim::default()
+        };
+        let report = validate::validate(&spec, &matches, &claim);
+        if report.is_valid() {
+            self.notable.lock().unwrap().push(matches);
+        } else {
+            println!("Rejected notable result from {}:", client.get_id());
+            for violation in report.violations.iter() {
+                println!("    {}", violation);
+            }
+        }
+    }
+
+    /// Every validated complete schedule found so far
+    pub fn get_notable(&self) -> Vec<Vec<Vec<Vec<usize>>>> {
+        self.notable.lock().unwrap().clone()
+    }
+
     pub fn free_all_from_client(&self, client: &Arc<Client>) {
         if self.clients.lock().unwrap().remove(client) {
             for (_id, (data, _instant)) in client.get_claimed().lock().unwrap().drain() {
@@ -91,6 +91,7 @@ impl Page for Status {
             let mut total_rate = 0.0;
             let mut total_recieved_rate = 0.0;
             let mut total_sent_rate = 0.0;
+            let mut total_search_stats = schedule_util::Stats::default();
             for client in solve_state.get_clients().iter() {
                 let rate = client.get_rate();
                 total_rate += rate;
@@ -98,12 +99,15 @@ impl Page for Status {
                 total_recieved_rate += recieved_rate;
                 let sent_rate = client.get_sent_rate();
                 total_sent_rate += sent_rate;
+                let search_stats = client.get_search_stats();
+                total_search_stats.merge(&search_stats);
                 clients.push(tr![
                     td![client.get_id().to_string()],
                     td![client.claimed_len(), " claimed"],
                     td![rate.to_string(), " ops/s"],
                     td![to_appropriate_unit(recieved_rate as u128), " /s"],
                     td![to_appropriate_unit(sent_rate as u128), " /s"],
+                    search_stats_cells(&search_stats),
                 ]);
             }
             let total = tr![
@@ -112,10 +116,15 @@ impl Page for Status {
                 td![total_rate.to_string(), " ops/s"],
                 td![to_appropriate_unit(total_recieved_rate as u128), " /s"],
                 td![to_appropriate_unit(total_sent_rate as u128), " /s"],
+                search_stats_cells(&total_search_stats),
             ];
             let node: Node<()> = div![format!(
-                "{:?}: {} unclaimed, {} in queue, total rate: {} steps/s",
-                arg, unclaimed, queue, total_rate
+                "{:?}: {} unclaimed, {} in queue, {} found, total rate: {} steps/s",
+                arg,
+                unclaimed,
+                queue,
+                solve_state.get_notable().len(),
+                total_rate
             )];
             let heading = tr![
                 td!["Client"],
@@ -123,13 +132,106 @@ impl Page for Status {
                 td!["Rate"],
                 td!["Recieved"],
                 td!["Sent"],
+                td!["Steps"],
+                td!["Max depth"],
+                td!["Branching factor"],
+                td!["Propagations"],
+                td!["Table overfull"],
+                td!["No candidate"],
+                td!["Propagation wipe-out"],
+                td!["Nodes per depth"],
             ];
             nodes.push(div![node, table![heading, total, clients]]);
         }
+        let mut found = Vec::new();
+        for (index, (arg, _)) in all_found(&state).iter().enumerate() {
+            let mut links = vec![td![format!("{:?}", arg)]];
+            for format in schedule_util::format::Format::ALL.iter() {
+                links.push(td![a![
+                    attrs! {At::Href => format!("/found/{}/{}", index, format.name())},
+                    format.name()
+                ]]);
+            }
+            found.push(tr![links]);
+        }
+        nodes.push(div![h2!["Found schedules"], table![found]]);
         div![nodes]
     }
 }
 
+/// Every schedule found by the clients, in the same order each time so they can be linked to by index
+fn all_found(state: &State) -> Vec<(Arc<schedule_util::ScheduleArg>, Vec<Vec<Vec<usize>>>)> {
+    let mut solve_states: Vec<_> = state.all_schedule_solve_states().into_iter().collect();
+    solve_states.sort_by(|(arg1, _), (arg2, _)| arg1.cmp(arg2));
+    solve_states
+        .into_iter()
+        .flat_map(|(arg, solve_state)| {
+            solve_state
+                .get_notable()
+                .into_iter()
+                .map(move |matches| (arg.clone(), matches))
+        })
+        .collect()
+}
+
+fn found_schedule(state: Arc<State>, index: usize, format: String) -> warp::reply::Response {
+    let format: schedule_util::format::Format = match format.parse() {
+        Ok(format) => format,
+        Err(error) => {
+            return warp::reply::with_status(error, warp::http::StatusCode::BAD_REQUEST)
+                .into_response()
+        }
+    };
+    match all_found(&state).get(index) {
+        Some((arg, matches)) => {
+            let layout = schedule_util::format::Layout::new(arg.get_tables().iter().sum(), matches);
+            warp::reply::with_header(
+                schedule_util::format::render(format, &layout),
+                "content-type",
+                format.content_type(),
+            )
+            .into_response()
+        }
+        None => warp::reply::with_status(
+            "No such schedule".to_string(),
+            warp::http::StatusCode::NOT_FOUND,
+        )
+        .into_response(),
+    }
+}
+
+/// Found schedules at /found/{index}/{format}, in any of the shared formats
+pub fn get_found_filter(state: Arc<State>) -> BoxedFilter<(impl Reply,)> {
+    warp::path!("found" / usize / String)
+        .map(move |index, format| found_schedule(state.clone(), index, format))
+        .boxed()
+}
+
+fn search_stats_cells(stats: &schedule_util::Stats) -> Vec<Node<()>> {
+    vec![
+        td![stats.steps.to_string()],
+        td![stats.max_depth().to_string()],
+        td![format!("{:.2}", stats.branching_factor())],
+        td![stats.propagations.to_string()],
+        td![stats.table_overfull.to_string()],
+        td![stats.no_candidate.to_string()],
+        td![stats.propagation_wipe_out.to_string()],
+        td![nodes_per_depth(stats)],
+    ]
+}
+
+/// The nodes expanded at each depth with any, as depth:count
+fn nodes_per_depth(stats: &schedule_util::Stats) -> String {
+    stats
+        .nodes_per_depth
+        .iter()
+        .enumerate()
+        .filter(|(_, &count)| count != 0)
+        .map(|(depth, count)| format!("{}:{}", depth, count))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 const PAGES: &[&dyn Page] = &[&SetSchedule {}, &Status {}, &Config {}];
 
 fn set_schedule_frame(state: Arc<State>) -> String {
@@ -1,14 +1,16 @@
 use seed::prelude::*;
 
-use crate::{alert, database, schedule, style_control, Msg};
+use crate::{alert, database, schedule, style_control, view_pairing, Msg};
 
 use schedule::ScheduleStructure;
 
+/** Scores entered for an event, keyed by round, table and the player's position at the table*/
+type ScoreInputs = std::collections::HashMap<(usize, usize, usize), usize>;
+
 #[derive(Default)]
 pub struct AddMatchResults {
     expanded_schedules: std::collections::HashSet<u32>,
-    score_inputs:
-        std::collections::HashMap<u32, std::collections::HashMap<(usize, usize, usize), usize>>,
+    score_inputs: std::collections::HashMap<u32, ScoreInputs>,
 }
 
 impl AddMatchResults {
@@ -23,36 +25,38 @@ impl AddMatchResults {
         id: u32,
         round: usize,
         table: usize,
-        player_number: usize,
+        position: usize,
         score: String,
     ) {
         if score.is_empty() {
             self.score_inputs
                 .entry(id)
                 .or_default()
-                .remove(&(round, table, player_number));
+                .remove(&(round, table, position));
         } else if let Ok(score) = score.parse::<usize>() {
             self.score_inputs
                 .entry(id)
                 .or_default()
-                .insert((round, table, player_number), score);
+                .insert((round, table, position), score);
         } else {
             alert(&("Not a Number: ".to_owned() + &score))
         }
     }
+    /** Save the entered scores of every round without results, including rounds played before the event was re-planned*/
     pub fn add_results(&mut self, id: u32, database: &mut database::Database) {
         if let Some(event) = database.get_event(id) {
             let event = event.clone();
             if let Some(event_matches) = self.score_inputs.get(&id) {
-                let mut data: Vec<Vec<Vec<(u32, usize)>>> = Vec::with_capacity(event.tables);
-                for round in 0..event.tables {
-                    let mut round_vec: Vec<Vec<(u32, usize)>> = Vec::with_capacity(event.tables);
-                    for table in 0..event.tables {
-                        let mut table_vec: Vec<(u32, usize)> = Vec::new();
-                        for player_number in event.schedule.get_players_from_game(round, table) {
-                            let player_id = event.players[player_number];
-                            if let Some(score) = event_matches.get(&(round, table, player_number)) {
-                                table_vec.push((player_id, *score));
+                let rounds = event.rounds_by_id();
+                let mut match_ids = event.matches_by_round();
+                let mut data: Vec<Vec<Vec<usize>>> = Vec::with_capacity(rounds.len());
+                for (round, tables) in rounds.iter().enumerate().skip(match_ids.len()) {
+                    let mut round_vec: Vec<Vec<usize>> = Vec::with_capacity(tables.len());
+                    for (table, players) in tables.iter().enumerate() {
+                        let mut table_vec: Vec<usize> = Vec::with_capacity(players.len());
+                        for position in 0..players.len() {
+                            if let Some(score) = event_matches.get(&(round, table, position)) {
+                                table_vec.push(*score);
                             } else {
                                 alert("Haven't entered all scores");
                                 return;
@@ -62,13 +66,10 @@ impl AddMatchResults {
                     }
                     data.push(round_vec);
                 }
-                let mut match_ids: Vec<Vec<u32>> = Vec::with_capacity(event.tables);
-                for round in data.iter() {
-                    let mut round_vec: Vec<u32> = Vec::with_capacity(event.tables);
-                    for table in round.iter() {
-                        let id =
-                            database.add_match(table.iter().map(|(_, score)| *score).collect());
-                        round_vec.push(id);
+                for round in data.into_iter() {
+                    let mut round_vec: Vec<u32> = Vec::with_capacity(round.len());
+                    for table in round.into_iter() {
+                        round_vec.push(database.add_match(table));
                     }
                     match_ids.push(round_vec);
                 }
@@ -82,19 +83,20 @@ impl AddMatchResults {
     }
 }
 
-fn view_schedule_with_result_boxes<T: schedule::ScheduleStructure>(
-    schedule: &T,
-    players: &[u32],
+/** Show every round of the event from first_round on, with a box to enter each player's score*/
+fn view_schedule_with_result_boxes(
+    event: &database::Event,
+    first_round: usize,
     database: &database::Database,
     id: u32,
-    score_inputs: &std::collections::HashMap<
-        u32,
-        std::collections::HashMap<(usize, usize, usize), usize>,
-    >,
+    score_inputs: &std::collections::HashMap<u32, ScoreInputs>,
     style: &style_control::StyleControl,
 ) -> Node<Msg> {
     table![style![St::BorderSpacing => "5px 10px"; ], {
-        let tables = schedule.get_tables();
+        let rounds = event.rounds_by_id();
+        let played = event.played_rounds.len();
+        // Tables may have changed when the event was re-planned
+        let tables = rounds.iter().map(|round| round.len()).max().unwrap_or(0);
 
         let mut table: Vec<Node<Msg>> = Vec::with_capacity(tables + 1);
         let mut heading: Vec<Node<Msg>> = Vec::with_capacity(tables + 1);
@@ -103,16 +105,18 @@ fn view_schedule_with_result_boxes<T: schedule::ScheduleStructure>(
             heading.push(th![format!("Table {:}", game)]);
         }
         table.push(tr![heading]);
-        for round in 0..tables {
+        for (round, round_players) in rounds.iter().enumerate().skip(first_round) {
             table.push(tr![{
                 let mut row: Vec<Node<Msg>> = Vec::with_capacity(tables + 1);
-                row.push(td![format!("Round {:}", round + 1)]);
-                for table in 0..tables {
+                row.push(td![if round < played {
+                    format!("Round {:} (played)", round + 1)
+                } else {
+                    format!("Round {:}", round + 1)
+                }]);
+                for (table, player_list) in round_players.iter().enumerate() {
                     row.push(td![{
-                        let player_list = schedule.get_players_from_game(round, table);
                         let mut data: Vec<Node<Msg>> = Vec::new();
-                        for player_number in player_list {
-                            let player_id = players[player_number];
+                        for (position, &player_id) in player_list.iter().enumerate() {
                             if let Some(player) = database.get_player(player_id) {
                                 data.push(span![
                                     style![St::Display => "Flex";
@@ -121,26 +125,18 @@ fn view_schedule_with_result_boxes<T: schedule::ScheduleStructure>(
                                     input![
                                         style.input_style(),
                                         {
-                                            if let Some(event_matches) = score_inputs.get(&id) {
-                                                if let Some(score) = event_matches.get(&(
-                                                    round,
-                                                    table,
-                                                    player_number,
-                                                )) {
-                                                    attrs! {At::Value => score}
-                                                } else {
-                                                    attrs! {}
-                                                }
+                                            if let Some(score) =
+                                                score_inputs.get(&id).and_then(|scores| {
+                                                    scores.get(&(round, table, position))
+                                                })
+                                            {
+                                                attrs! {At::Value => score}
                                             } else {
                                                 attrs! {}
                                             }
                                         },
                                         input_ev(Ev::Input, move |score| Msg::ARSetScore(
-                                            id,
-                                            round,
-                                            table,
-                                            player_number,
-                                            score
+                                            id, round, table, position, score
                                         )),
                                         attrs! {At::Type => "number"},
                                         style! {St::Width => "3em"; St::FlexGrow => "0"}
@@ -149,6 +145,15 @@ fn view_schedule_with_result_boxes<T: schedule::ScheduleStructure>(
                                 ]);
                             }
                         }
+                        if round >= played && event.schedule.get_teams() > 1 {
+                            data.push(view_pairing(
+                                &event.schedule,
+                                &event.players,
+                                database,
+                                round - played,
+                                table,
+                            ));
+                        }
                         data
                     }]);
                 }
@@ -181,7 +186,8 @@ St::FlexGrow=> "1";];
                 let events_list = database.get_events();
                 let mut node_list: Vec<Node<Msg>> = Vec::with_capacity(events_list.len());
                 for (&id, event) in &events_list {
-                    if event.matches.is_empty() {
+                    let first_round = event.matches_by_round().len();
+                    if first_round < event.total_rounds() {
                         node_list.push(tr![
                             td![id.to_string()],
                             td![event.name],
@@ -217,8 +223,8 @@ St::FlexGrow=> "1";];
                                     &box_style,
                                     if model.expanded_schedules.contains(&id) {
                                         view_schedule_with_result_boxes(
-                                            &event.schedule,
-                                            &event.players,
+                                            event,
+                                            first_round,
                                             database,
                                             id,
                                             &model.score_inputs,
@@ -0,0 +1,115 @@
+use schedule::ScheduleStructure;
+
+/** Who meets who in a schedule and where everyone sits, for explaining how fair a schedule is.
+Players meet when they are at the same table in a round, whether as opponents or teammates*/
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct Analysis {
+    /**How many rounds each pair of players are at the same table, indexed by both players. 0 for a player with themselves*/
+    pub meetings: Vec<Vec<u32>>,
+    /**How many rounds each player is at each table, indexed by player then table*/
+    pub table_visits: Vec<Vec<u32>>,
+}
+
+impl Analysis {
+    pub fn new<T: ScheduleStructure>(schedule: &T) -> Self {
+        let player_count = schedule.get_player_count();
+        let mut meetings = vec![vec![0; player_count]; player_count];
+        let mut table_visits = vec![vec![0; schedule.get_tables()]; player_count];
+        for round in 0..schedule.get_rounds() {
+            for (table, players) in (0..schedule.get_tables())
+                .map(|table| (table, schedule.get_players_from_game(round, table)))
+            {
+                for &player1 in players.iter() {
+                    table_visits[player1][table] += 1;
+                    for &player2 in players.iter().filter(|&&player2| player2 != player1) {
+                        meetings[player1][player2] += 1;
+                    }
+                }
+            }
+        }
+        Self {
+            meetings,
+            table_visits,
+        }
+    }
+
+    pub fn player_count(&self) -> usize {
+        self.meetings.len()
+    }
+
+    /**Number of different players the player meets*/
+    pub fn unique_opponents(&self, player: usize) -> usize {
+        self.meetings[player]
+            .iter()
+            .filter(|&&count| count > 0)
+            .count()
+    }
+
+    /**How many players meet each number of different players, indexed by that number*/
+    pub fn unique_opponent_distribution(&self) -> Vec<usize> {
+        let mut distribution = vec![0; self.player_count()];
+        for player in 0..self.player_count() {
+            distribution[self.unique_opponents(player)] += 1;
+        }
+        while distribution.len() > 1 && distribution.last() == Some(&0) {
+            distribution.pop();
+        }
+        distribution
+    }
+
+    /**Every pair of players, lowest first, with the number of times they meet*/
+    fn pairs(&self) -> impl Iterator<Item = (usize, usize, u32)> + '_ {
+        (0..self.player_count()).flat_map(move |player1| {
+            (player1 + 1..self.player_count())
+                .map(move |player2| (player1, player2, self.meetings[player1][player2]))
+        })
+    }
+
+    /**Number of pairs of players who meet more than once*/
+    pub fn repeat_pairs(&self) -> usize {
+        self.pairs().filter(|&(_, _, count)| count > 1).count()
+    }
+
+    /**Pairs of players who never meet, lowest first*/
+    pub fn never_met(&self) -> Vec<(usize, usize)> {
+        self.pairs()
+            .filter(|&(_, _, count)| count == 0)
+            .map(|(player1, player2, _)| (player1, player2))
+            .collect()
+    }
+
+    /**Most times any pair of players meets*/
+    pub fn most_meetings(&self) -> u32 {
+        self.pairs().map(|(_, _, count)| count).max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schedule::SerdeSchedule;
+
+    #[test]
+    fn counts_meetings_and_visits() {
+        let schedule: SerdeSchedule = serde_json::from_str(
+            r#"{"player_count":6,"tables":2,"matches":[[[0,1,2],[3,4,5]],[[0,1,3],[2,4,5]]]}"#,
+        )
+        .unwrap();
+        let analysis = Analysis::new(&schedule);
+        assert_eq!(analysis.meetings[0][1], 2);
+        assert_eq!(analysis.meetings[1][0], 2);
+        assert_eq!(analysis.meetings[0][0], 0);
+        assert_eq!(analysis.meetings[0][4], 0);
+        assert_eq!(analysis.table_visits[2], vec![1, 1]);
+        assert_eq!(analysis.table_visits[5], vec![0, 2]);
+        assert_eq!(analysis.unique_opponents(0), 3);
+        assert_eq!(analysis.unique_opponents(2), 4);
+        assert_eq!(analysis.unique_opponent_distribution(), vec![0, 0, 0, 4, 2]);
+        assert_eq!(analysis.repeat_pairs(), 2);
+        assert_eq!(analysis.most_meetings(), 2);
+        assert_eq!(
+            analysis.never_met(),
+            vec![(0, 4), (0, 5), (1, 4), (1, 5), (2, 3)]
+        );
+    }
+}
@@ -0,0 +1,201 @@
+/** Numbers from first to last, including both*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SizeRange {
+    pub first: usize,
+    pub last: usize,
+}
+
+impl SizeRange {
+    pub fn iter(self) -> std::ops::RangeInclusive<usize> {
+        self.first..=self.last
+    }
+}
+
+impl std::str::FromStr for SizeRange {
+    type Err = String;
+
+    /** Parse a single number, or a range such as 8..64 or 8-64 which includes both ends*/
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let parse = |number: &str| {
+            number
+                .trim()
+                .parse::<usize>()
+                .map_err(|_| format!("Expected a number or a range such as 8..64, got '{}'", text))
+        };
+        let (first, last) = match text.split_once("..").or_else(|| text.split_once('-')) {
+            Some((first, last)) => (parse(first)?, parse(last)?),
+            None => (parse(text)?, parse(text)?),
+        };
+        if first > last {
+            return Err(format!("The range {} is empty", text));
+        }
+        Ok(Self { first, last })
+    }
+}
+
+/** The number of players, tables and rounds of a schedule*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Size {
+    pub players: usize,
+    pub tables: usize,
+    pub rounds: usize,
+}
+
+/** Every size of schedule in the ranges which can be made, in order of players, then tables, then rounds.
+Rounds default to the number of tables. Sizes with fewer than two tables, or fewer than two players at a table, are left out*/
+pub fn sizes(players: SizeRange, tables: SizeRange, rounds: Option<SizeRange>) -> Vec<Size> {
+    let mut sizes = Vec::new();
+    for player_count in players.iter() {
+        for table_count in tables.iter() {
+            if table_count < 2 || player_count < 2 * table_count {
+                continue;
+            }
+            let rounds = rounds.unwrap_or(SizeRange {
+                first: table_count,
+                last: table_count,
+            });
+            for round_count in rounds.iter().filter(|&rounds| rounds > 0) {
+                sizes.push(Size {
+                    players: player_count,
+                    tables: table_count,
+                    rounds: round_count,
+                });
+            }
+        }
+    }
+    sizes
+}
+
+/** How the search for one size of schedule went*/
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct Summary {
+    pub players: usize,
+    pub tables: usize,
+    pub rounds: usize,
+    /**Score of the best schedule found, or already in the cache*/
+    pub score: u64,
+    /**Upper bound on the score, see Schedule::score_bound*/
+    pub bound: u64,
+    /**Whether the schedule reaches the ideal scores*/
+    pub ideal: bool,
+    /**Whether the score reaches the bound, so no better schedule exists*/
+    pub optimal: bool,
+    /**Seconds spent searching, 0 when the cache already had an ideal schedule*/
+    pub seconds: f64,
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value {
+        "yes"
+    } else {
+        "no"
+    }
+}
+
+/** A header row, then a row for each size*/
+pub fn csv(summaries: &[Summary]) -> String {
+    let mut output = String::from("players,tables,rounds,score,bound,ideal,optimal,seconds\r\n");
+    for summary in summaries {
+        output.push_str(&format!(
+            "{},{},{},{},{},{},{},{:.1}\r\n",
+            summary.players,
+            summary.tables,
+            summary.rounds,
+            summary.score,
+            summary.bound,
+            yes_no(summary.ideal),
+            yes_no(summary.optimal),
+            summary.seconds
+        ));
+    }
+    output
+}
+
+/** A table with a row for each size, and a count of how many are ideal and optimal*/
+pub fn markdown(summaries: &[Summary]) -> String {
+    let mut output = String::from(concat!(
+        "| Players | Tables | Rounds | Score | Bound | Ideal | Optimal | Seconds |\n",
+        "| ---: | ---: | ---: | ---: | ---: | --- | --- | ---: |\n"
+    ));
+    for summary in summaries {
+        output.push_str(&format!(
+            "| {} | {} | {} | {} | {} | {} | {} | {:.1} |\n",
+            summary.players,
+            summary.tables,
+            summary.rounds,
+            summary.score,
+            summary.bound,
+            yes_no(summary.ideal),
+            yes_no(summary.optimal),
+            summary.seconds
+        ));
+    }
+    output.push_str(&format!(
+        "\n{} sizes, {} ideal, {} optimal\n",
+        summaries.len(),
+        summaries.iter().filter(|summary| summary.ideal).count(),
+        summaries.iter().filter(|summary| summary.optimal).count()
+    ));
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_ranges() {
+        assert_eq!("8..64".parse(), Ok(SizeRange { first: 8, last: 64 }));
+        assert_eq!("2-12".parse(), Ok(SizeRange { first: 2, last: 12 }));
+        assert_eq!("5".parse(), Ok(SizeRange { first: 5, last: 5 }));
+        assert!("9..8".parse::<SizeRange>().is_err());
+        assert!("a..8".parse::<SizeRange>().is_err());
+    }
+
+    #[test]
+    fn sizes_skip_impossible_schedules() {
+        let sizes = sizes(
+            SizeRange { first: 5, last: 6 },
+            SizeRange { first: 1, last: 3 },
+            None,
+        );
+        let found: Vec<(usize, usize, usize)> = sizes
+            .iter()
+            .map(|size| (size.players, size.tables, size.rounds))
+            .collect();
+        assert_eq!(found, vec![(5, 2, 2), (6, 2, 2), (6, 3, 3)]);
+    }
+
+    #[test]
+    fn reports_every_size() {
+        let summaries = vec![
+            Summary {
+                players: 8,
+                tables: 2,
+                rounds: 2,
+                score: 10,
+                bound: 10,
+                ideal: true,
+                optimal: true,
+                seconds: 0.0,
+            },
+            Summary {
+                players: 9,
+                tables: 2,
+                rounds: 2,
+                score: 7,
+                bound: 11,
+                ideal: false,
+                optimal: false,
+                seconds: 2.0,
+            },
+        ];
+        assert_eq!(
+            csv(&summaries),
+            "players,tables,rounds,score,bound,ideal,optimal,seconds\r\n8,2,2,10,10,yes,yes,0.0\r\n9,2,2,7,11,no,no,2.0\r\n"
+        );
+        let table = markdown(&summaries);
+        assert!(table.contains("| 9 | 2 | 2 | 7 | 11 | no | no | 2.0 |\n"));
+        assert!(table.ends_with("2 sizes, 1 ideal, 1 optimal\n"));
+    }
+}
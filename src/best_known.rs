@@ -0,0 +1,352 @@
+use objective;
+use schedule::{ScheduleStructure, SerdeSchedule};
+use schedule_util::validate;
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+
+/** The best schedule found so far for one size of schedule, with how it was found*/
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct BestKnown {
+    pub schedule: SerdeSchedule,
+    /**Score of the schedule with the objective it was found with*/
+    pub score: u64,
+    /**The objective the score is for, in the form objective::parse takes*/
+    pub objective: String,
+    /**Seed of the run which found it*/
+    pub seed: u64,
+    /**The strategy which found it, in the form strategy::parse takes*/
+    pub strategy: String,
+    /**Seconds since 1970-01-01 when it was found*/
+    pub timestamp: u64,
+}
+
+impl BestKnown {
+    /** Score the schedule with the default objective, which entries are ranked by whichever objective they were found with. None if the schedule isn't valid*/
+    pub fn default_score(&self) -> Option<u64> {
+        if !self
+            .schedule
+            .validate(&validate::Claim::default())
+            .is_valid()
+        {
+            return None;
+        }
+        Some(self.schedule.to_schedule().generate_score())
+    }
+}
+
+/** Name of the entry for these numbers of players, tables, rounds and teams*/
+pub fn name(players: usize, tables: usize, rounds: usize, teams: usize) -> String {
+    // Keep the original cache names when there is one round per table
+    let mut name = if rounds == tables {
+        format!("{}_players_{}_tables", players, tables)
+    } else {
+        format!("{}_players_{}_tables_{}_rounds", players, tables, rounds)
+    };
+    if teams > 1 {
+        name.push_str(&format!("_{}_teams", teams));
+    }
+    name
+}
+
+/** Every entry of a cache in one file, so that people can share what they have found. Keyed by the name of each size of schedule*/
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct Bundle {
+    pub entries: BTreeMap<String, BestKnown>,
+}
+
+/** Best known schedules saved in a directory, in a file named after each size of schedule*/
+pub struct Cache {
+    directory: PathBuf,
+}
+
+impl Cache {
+    /** Use the directory as a cache, which is created when the first schedule is saved*/
+    pub fn new(directory: PathBuf) -> Self {
+        Self { directory }
+    }
+
+    /** The saved entry for the size of schedule. None if there is none, or it can't be read*/
+    pub fn load(&self, name: &str) -> Option<BestKnown> {
+        let contents = std::fs::read_to_string(self.directory.join(name)).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /** Save the candidate if there is no valid entry for the size of schedule yet, or the candidate is better than the entry with the default objective.
+    Both are scored the same way so that a shared bundle can't pick an objective which favours its own schedules.
+    The name must be the one for the size of the candidate's schedule, see name. Returns whether it was saved*/
+    pub fn offer(&self, name: &str, candidate: &BestKnown) -> Result<bool, String> {
+        if name.is_empty()
+            || !name
+                .chars()
+                .all(|character| character.is_ascii_alphanumeric() || character == '_')
+        {
+            return Err(format!("Invalid schedule name '{}'", name));
+        }
+        objective::parse(&candidate.objective)?;
+        let candidate_score = candidate
+            .default_score()
+            .ok_or_else(|| format!("Schedule for {} is invalid", name))?;
+        let schedule = &candidate.schedule;
+        let expected = self::name(
+            schedule.get_player_count(),
+            schedule.get_tables(),
+            schedule.get_rounds(),
+            schedule.get_teams(),
+        );
+        if name != expected {
+            return Err(format!("Schedule for {} is for {}", name, expected));
+        }
+        let better = match self.load(name).and_then(|entry| entry.default_score()) {
+            Some(score) => candidate_score > score,
+            None => true,
+        };
+        if better {
+            let contents = serde_json::to_string(candidate).map_err(|error| error.to_string())?;
+            std::fs::create_dir_all(&self.directory)
+                .and_then(|_| std::fs::write(self.directory.join(name), contents))
+                .map_err(|error| format!("Failed to save {}: {}", name, error))?;
+        }
+        Ok(better)
+    }
+
+    /** Names of every file in the cache, whether or not it can be read, in order*/
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> = match std::fs::read_dir(&self.directory) {
+            Ok(files) => files
+                .filter_map(|file| file.ok()?.file_name().into_string().ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        names.sort();
+        names
+    }
+
+    /** Every readable entry in the cache*/
+    pub fn export(&self) -> Bundle {
+        let mut bundle = Bundle::default();
+        for name in self.names() {
+            if let Some(entry) = self.load(&name) {
+                bundle.entries.insert(name, entry);
+            }
+        }
+        bundle
+    }
+
+    /** Remove every entry which can't be read, isn't a valid schedule, or keep returns false for. Returns the names of the entries removed*/
+    pub fn prune<F: Fn(&str, &BestKnown) -> bool>(&self, keep: F) -> Result<Vec<String>, String> {
+        let mut removed = Vec::new();
+        for name in self.names() {
+            let kept = self.load(&name).is_some_and(|entry| {
+                entry
+                    .schedule
+                    .validate(&validate::Claim::default())
+                    .is_valid()
+                    && keep(&name, &entry)
+            });
+            if !kept {
+                std::fs::remove_file(self.directory.join(&name))
+                    .map_err(|error| format!("Failed to remove {}: {}", name, error))?;
+                removed.push(name);
+            }
+        }
+        Ok(removed)
+    }
+
+    /** Offer every entry of the bundle to the cache, see offer. Returns how many were saved*/
+    pub fn import(&self, bundle: &Bundle) -> Result<usize, String> {
+        let mut saved = 0;
+        for (name, entry) in bundle.entries.iter() {
+            if self.offer(name, entry)? {
+                saved += 1;
+            }
+        }
+        Ok(saved)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schedule::Schedule;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    /** A cache in its own directory, so tests running at the same time don't share one. Removed when dropped*/
+    struct TestCache(Cache);
+
+    impl std::ops::Deref for TestCache {
+        type Target = Cache;
+
+        fn deref(&self) -> &Cache {
+            &self.0
+        }
+    }
+
+    impl Drop for TestCache {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.0.directory);
+        }
+    }
+
+    fn cache() -> TestCache {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let directory = std::env::temp_dir().join(format!(
+            "social_schedule_test_{}_{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = std::fs::remove_dir_all(&directory);
+        TestCache(Cache::new(directory))
+    }
+
+    fn entry(schedule: &Schedule, seed: u64) -> BestKnown {
+        BestKnown {
+            schedule: schedule.to_serde_schedule(),
+            score: schedule.get_score(),
+            objective: "default".to_string(),
+            seed,
+            strategy: "hill-climbing".to_string(),
+            timestamp: 0,
+        }
+    }
+
+    fn schedules() -> (Schedule, Schedule) {
+        let mut good = Schedule::new(12, 3, 3);
+        good.normal_fill();
+        good.generate_score();
+        let mut bad = Schedule::new(12, 3, 3);
+        bad.import_vec(&vec![
+            vec![
+                vec![0, 1, 2, 3],
+                vec![4, 5, 6, 7],
+                vec![8, 9, 10, 11]
+            ];
+            3
+        ]);
+        (good, bad)
+    }
+
+    fn two_rounds() -> Schedule {
+        let mut schedule = Schedule::new(12, 3, 2);
+        schedule.normal_fill();
+        schedule.generate_score();
+        schedule
+    }
+
+    #[test]
+    fn only_saves_better_schedules() {
+        let cache = cache();
+        let (good, bad) = schedules();
+        assert!(good.get_score() > bad.get_score());
+        assert_eq!(
+            cache.offer("12_players_3_tables", &entry(&bad, 1)),
+            Ok(true)
+        );
+        assert_eq!(
+            cache.offer("12_players_3_tables", &entry(&good, 2)),
+            Ok(true)
+        );
+        assert_eq!(
+            cache.offer("12_players_3_tables", &entry(&bad, 3)),
+            Ok(false)
+        );
+        let saved = cache.load("12_players_3_tables").unwrap();
+        assert_eq!(saved.seed, 2);
+        assert_eq!(saved.score, good.get_score());
+
+        let mut invalid = entry(&good, 4);
+        invalid.schedule = serde_json::from_str(
+            r#"{"player_count":12,"tables":3,"matches":[[[0,0,1,2],[3,4,5,6],[7,8,9,10]]]}"#,
+        )
+        .unwrap();
+        assert!(cache.offer("12_players_3_tables", &invalid).is_err());
+        assert!(cache
+            .offer("../12_players_3_tables", &entry(&good, 5))
+            .is_err());
+
+        let mut unsupported = entry(&good, 6);
+        unsupported.schedule = serde_json::from_str(
+            r#"{"player_count":3,"tables":1,"rounds":1,"matches":[[[0,1,2]]]}"#,
+        )
+        .unwrap();
+        assert!(cache.offer("3_players_1_tables", &unsupported).is_err());
+    }
+
+    #[test]
+    fn names_must_match_the_schedule() {
+        let cache = cache();
+        let (good, _) = schedules();
+        assert!(cache
+            .offer("12_players_3_tables_2_rounds", &entry(&good, 1))
+            .is_err());
+        assert!(cache
+            .offer("12_players_4_tables", &entry(&good, 1))
+            .is_err());
+        assert_eq!(cache.names(), Vec::<String>::new());
+        assert_eq!(name(12, 3, 2, 2), "12_players_3_tables_2_rounds_2_teams");
+    }
+
+    #[test]
+    fn objectives_cant_favour_worse_schedules() {
+        let cache = cache();
+        let (good, bad) = schedules();
+        cache
+            .offer("12_players_3_tables", &entry(&good, 1))
+            .unwrap();
+        for objective in ["max-min", "variance:100", "weighted:0,0,1"].iter() {
+            let mut candidate = entry(&bad, 2);
+            candidate.objective = objective.to_string();
+            assert_eq!(cache.offer("12_players_3_tables", &candidate), Ok(false));
+        }
+        assert_eq!(cache.load("12_players_3_tables").unwrap().seed, 1);
+    }
+
+    #[test]
+    fn import_merges_bundles() {
+        let (good, bad) = schedules();
+        let ours = cache();
+        ours.offer("12_players_3_tables", &entry(&bad, 1)).unwrap();
+        let theirs = cache();
+        theirs
+            .offer("12_players_3_tables", &entry(&good, 2))
+            .unwrap();
+        theirs
+            .offer("12_players_3_tables_2_rounds", &entry(&two_rounds(), 3))
+            .unwrap();
+
+        let bundle: Bundle =
+            serde_json::from_str(&serde_json::to_string(&theirs.export()).unwrap()).unwrap();
+        assert_eq!(bundle.entries.len(), 2);
+        assert_eq!(ours.import(&bundle), Ok(2));
+        assert_eq!(ours.import(&bundle), Ok(0));
+        assert_eq!(ours.load("12_players_3_tables").unwrap().seed, 2);
+    }
+
+    #[test]
+    fn prune_removes_unreadable_and_unwanted_entries() {
+        let cache = cache();
+        let (good, _) = schedules();
+        cache
+            .offer("12_players_3_tables", &entry(&good, 1))
+            .unwrap();
+        cache
+            .offer("12_players_3_tables_2_rounds", &entry(&two_rounds(), 2))
+            .unwrap();
+        std::fs::write(cache.directory.join("broken"), "{").unwrap();
+        assert_eq!(
+            cache.names(),
+            vec![
+                "12_players_3_tables",
+                "12_players_3_tables_2_rounds",
+                "broken"
+            ]
+        );
+        assert_eq!(
+            cache.prune(|_, entry| entry.seed != 2),
+            Ok(vec![
+                "12_players_3_tables_2_rounds".to_string(),
+                "broken".to_string()
+            ])
+        );
+        assert_eq!(cache.names(), vec!["12_players_3_tables"]);
+    }
+}
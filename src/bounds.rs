@@ -0,0 +1,137 @@
+/** Sizes of social golfer schedules where fewer rounds without anyone meeting twice are possible than counting alone allows,
+as (tables, players at each table, most rounds). There are no affine planes of order 6 or 10, so there are at most 3 rounds for 6 tables of 6,
+and a schedule of n tables of n with n rounds where nobody meets twice could always be completed to one with n + 1 rounds, so at most 9 for 10 tables of 10*/
+const KNOWN_MOST_ROUNDS: [(usize, usize, usize); 2] = [(6, 6, 3), (10, 10, 9)];
+
+/** Number of pairs in a group of players*/
+const fn pairs(players: usize) -> usize {
+    players * players.saturating_sub(1) / 2
+}
+
+/** Fewest pairs of players at a table of size who must have been at the same table as each other in the previous round, which had groups tables.
+The players are spread as evenly as possible between the previous tables*/
+pub fn fewest_repeated_pairs(size: usize, groups: usize) -> usize {
+    let per_group = size / groups;
+    let larger_groups = size % groups;
+    larger_groups * pairs(per_group + 1) + (groups - larger_groups) * pairs(per_group)
+}
+
+/** Most that value summed over the tables can be, when seating player_count players at tables with the (minimum, maximum) capacities*/
+fn most_over_table_sizes<F: Fn(usize) -> usize>(
+    player_count: usize,
+    capacities: &[(usize, usize)],
+    value: F,
+) -> usize {
+    // most[seated] is the best total for the tables so far with that many players seated at them
+    let mut most: Vec<Option<usize>> = vec![None; player_count + 1];
+    most[0] = Some(0);
+    for &(min, max) in capacities {
+        let mut next = vec![None; player_count + 1];
+        for (seated, total) in most
+            .iter()
+            .enumerate()
+            .filter_map(|(seated, total)| total.map(|total| (seated, total)))
+        {
+            for size in min..=max.min(player_count - seated) {
+                let new_total = total + value(size);
+                if next[seated + size].is_none_or(|best| new_total > best) {
+                    next[seated + size] = Some(new_total);
+                }
+            }
+        }
+        most = next;
+    }
+    most[player_count].unwrap_or(0)
+}
+
+/** Upper bound on how many different pairs of players can meet over the rounds, with tables of the (minimum, maximum) capacities.
+Each round can't have more pairs than its tables hold, every round after the first has pairs who were at the same table in the round before,
+no more pairs can meet than there are, and sizes with known social golfer results have at least one pair meeting twice in every run of rounds longer than the most known*/
+pub fn most_unique_pairs(
+    player_count: usize,
+    capacities: &[(usize, usize)],
+    rounds: usize,
+) -> usize {
+    if rounds == 0 {
+        return 0;
+    }
+    let tables = capacities.len();
+    let first_round = most_over_table_sizes(player_count, capacities, pairs);
+    let later_rounds = most_over_table_sizes(player_count, capacities, |size| {
+        pairs(size) - fewest_repeated_pairs(size, tables)
+    });
+    let mut most = first_round + (rounds - 1) * later_rounds;
+    let (_, size) = capacities[0];
+    if size * tables == player_count && capacities.iter().all(|&capacity| capacity == (size, size))
+    {
+        if let Some(&(_, _, most_rounds)) = KNOWN_MOST_ROUNDS
+            .iter()
+            .find(|&&(known_tables, known_size, _)| (known_tables, known_size) == (tables, size))
+        {
+            most -= rounds / (most_rounds + 1);
+        }
+    }
+    most.min(pairs(player_count))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use objective::{Lexicographic, Metric};
+    use rand::SeedableRng;
+    use run::{std_clock, StopCriteria, StopReason};
+    use schedule::{Generator, Schedule};
+    use std::sync::Arc;
+
+    #[test]
+    fn later_rounds_repeat_when_tables_are_larger_than_their_count() {
+        assert_eq!(fewest_repeated_pairs(5, 3), 2);
+        assert_eq!(fewest_repeated_pairs(4, 6), 0);
+        assert_eq!(most_unique_pairs(6, &[(3, 3); 2], 2), 6 + 4);
+        assert_eq!(most_unique_pairs(15, &[(5, 5); 3], 2), 30 + 24);
+        assert_eq!(most_unique_pairs(15, &[(5, 5); 3], 9), pairs(15));
+        assert_eq!(most_unique_pairs(24, &[(4, 4); 6], 3), 3 * 36);
+    }
+
+    #[test]
+    fn uneven_tables_use_the_best_sizes() {
+        assert_eq!(
+            most_unique_pairs(10, &[(2, 6), (2, 6), (2, 2)], 1),
+            pairs(6) + pairs(2) + pairs(2)
+        );
+    }
+
+    #[test]
+    fn known_results_forbid_rounds() {
+        assert_eq!(most_unique_pairs(36, &[(6, 6); 6], 3), 3 * 90);
+        assert_eq!(most_unique_pairs(36, &[(6, 6); 6], 4), 4 * 90 - 1);
+        assert_eq!(most_unique_pairs(36, &[(6, 6); 6], 7), pairs(36) - 1);
+    }
+
+    #[test]
+    fn bound_is_reached_and_stops_search() {
+        let played = [
+            vec![vec![0, 1, 2], vec![3, 4, 5]],
+            vec![vec![0, 1, 3], vec![2, 4, 5]],
+        ];
+        let schedule = Schedule::from_vec(6, 2, 2, &played);
+        assert_eq!(schedule.ideal_unique_opponents, 2 * 10);
+        assert_eq!(schedule.unique_opponents(), schedule.ideal_unique_opponents);
+
+        let mut generator = Generator::warm_start(
+            rand_xorshift::XorShiftRng::seed_from_u64(5),
+            Schedule::new(6, 2, 2),
+            &played,
+        );
+        generator.set_objective(Arc::new(Lexicographic(vec![Metric::UniqueOpponents])));
+        let criteria = StopCriteria {
+            max_evaluations: Some(1_000_000),
+            stop_at_ideal: true,
+            ..StopCriteria::default()
+        };
+        let (reason, stats) = generator.run_until(&criteria, &std_clock(), |_| {});
+        assert_eq!(reason, StopReason::Ideal);
+        assert!(stats.evaluations < 1_000_000);
+        assert_eq!(generator.best_score, generator.best.score_bound());
+    }
+}
@@ -1,4 +1,7 @@
+use crate::history;
+use crate::ratings;
 use crate::schedule;
+use crate::schedule::ScheduleStructure;
 
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct Email {
@@ -65,6 +68,15 @@ pub struct Event {
     pub players: Vec<u32>,
     pub matches: Vec<Vec<u32>>,
     pub tables: usize,
+    /** Events saved before rounds could differ from tables have 0 here, use get_rounds instead*/
+    #[serde(default)]
+    pub rounds: usize,
+    /** Rounds which had already been played when the event was re-planned, as the player ids at each table. The schedule holds the rounds after them*/
+    #[serde(default)]
+    pub played_rounds: Vec<Vec<Vec<u32>>>,
+    /** Match ids of the results entered for played_rounds, in the same order. Empty if they haven't been entered*/
+    #[serde(default)]
+    pub played_matches: Vec<Vec<u32>>,
 }
 
 impl Group {
@@ -74,12 +86,38 @@ impl Group {
 }
 
 impl Event {
+    pub fn get_rounds(&self) -> usize {
+        if self.rounds == 0 {
+            self.tables
+        } else {
+            self.rounds
+        }
+    }
+
+    /** Check the stored schedule matches the event's players, tables and rounds, and is a valid schedule*/
+    pub fn is_valid(&self) -> bool {
+        if self.schedule.get_player_count() != self.players.len()
+            || self.schedule.get_tables() != self.tables
+            || self.schedule.get_rounds() != self.get_rounds()
+        {
+            return false;
+        }
+        let report = self
+            .schedule
+            .validate(&schedule_util::validate::Claim::default());
+        for violation in report.violations.iter() {
+            log!(format!("{}: {}", self.name, violation));
+        }
+        report.is_valid()
+    }
+
     pub fn from(
         name: String,
         date: String,
         schedule: schedule::Schedule,
         players: Vec<u32>,
         tables: usize,
+        rounds: usize,
     ) -> Option<Self> {
         Some(Self {
             name,
@@ -88,8 +126,44 @@ impl Event {
             players,
             matches: Vec::new(),
             tables,
+            rounds,
+            played_rounds: Vec::new(),
+            played_matches: Vec::new(),
         })
     }
+
+    /** Number of rounds including the played rounds from before the event was re-planned*/
+    pub fn total_rounds(&self) -> usize {
+        self.played_rounds.len() + self.get_rounds()
+    }
+
+    /** Every round of the event as the player ids at each table, starting with the played rounds from before it was re-planned*/
+    pub fn rounds_by_id(&self) -> Vec<Vec<Vec<u32>>> {
+        let mut rounds = self.played_rounds.clone();
+        for round in 0..self.schedule.get_rounds() {
+            rounds.push(
+                (0..self.schedule.get_tables())
+                    .map(|table| {
+                        self.schedule
+                            .get_players_from_game(round, table)
+                            .iter()
+                            .map(|&player| self.players[player])
+                            .collect()
+                    })
+                    .collect(),
+            );
+        }
+        rounds
+    }
+
+    /** Match ids of every round with results, in the same order as rounds_by_id. Results are added for all the rounds without them at once, so these are the first rounds*/
+    pub fn matches_by_round(&self) -> Vec<Vec<u32>> {
+        let mut matches = self.played_matches.clone();
+        if matches.len() == self.played_rounds.len() {
+            matches.extend(self.matches.iter().cloned());
+        }
+        matches
+    }
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
@@ -119,6 +193,15 @@ impl Database {
 
     pub fn import(&mut self, data: &str) -> Result<(), ()> {
         if let Ok(database) = serde_json::from_str::<Database>(data) {
+            for (id, event) in database.events.iter() {
+                if !event.is_valid() {
+                    log!(format!(
+                        "Event {} ({}) has an invalid schedule",
+                        id, event.name
+                    ));
+                    return Err(());
+                }
+            }
             *self = database;
             self.dump();
             Ok(())
@@ -252,8 +335,9 @@ impl Database {
         event_schedule: schedule::Schedule,
         players: Vec<u32>,
         tables: usize,
+        rounds: usize,
     ) {
-        if let Some(event) = Event::from(name, date, event_schedule, players, tables) {
+        if let Some(event) = Event::from(name, date, event_schedule, players, tables, rounds) {
             for id in (self.events.len() as u32)..std::u32::MAX {
                 if !self.events.contains_key(&id) {
                     self.events.insert(id, event);
@@ -263,12 +347,108 @@ impl Database {
             }
         }
     }
+    /** Replace the event with the given id, such as after its players or tables were changed.
+    played_rounds are the rounds kept from before the change, with event_schedule holding the rounds after them.
+    Results entered for the played rounds are kept, the others are cleared since they were for the old schedule*/
+    pub fn update_event(
+        &mut self,
+        id: u32,
+        name: String,
+        date: String,
+        event_schedule: schedule::Schedule,
+        players: Vec<u32>,
+        tables: usize,
+        rounds: usize,
+        played_rounds: Vec<Vec<Vec<u32>>>,
+    ) {
+        if let Some(mut event) = Event::from(name, date, event_schedule, players, tables, rounds) {
+            // The played rounds are the first rounds of the old event
+            let mut played_matches = self
+                .events
+                .get(&id)
+                .map_or_else(Vec::new, |old| old.matches_by_round());
+            played_matches.truncate(played_rounds.len());
+            event.played_rounds = played_rounds;
+            event.played_matches = played_matches;
+            self.events.insert(id, event);
+            self.dump();
+        }
+    }
     pub fn get_events(&self) -> Vec<(&u32, &Event)> {
         self.events.iter().collect()
     }
     pub fn get_event(&self, id: u32) -> Option<&Event> {
         self.events.get(&id)
     }
+    /** How much each pair of the players met at the given events, with players numbered by their position in players.
+    With a half life, events count half as much for every half_life_days before date. Events with a date that can't be read count fully*/
+    pub fn pair_history(
+        &self,
+        event_ids: &[u32],
+        players: &[u32],
+        date: &str,
+        half_life_days: Option<u32>,
+    ) -> history::PairHistory {
+        let mut pair_history = history::PairHistory::new(players.len());
+        let today = history::days_from_date(date);
+        for event in event_ids.iter().filter_map(|&id| self.get_event(id)) {
+            let age = match (today, history::days_from_date(&event.date)) {
+                (Some(today), Some(then)) => today - then,
+                _ => 0,
+            };
+            let matches: Vec<Vec<Vec<usize>>> = event
+                .rounds_by_id()
+                .iter()
+                .map(|round| {
+                    round
+                        .iter()
+                        .map(|table| {
+                            table
+                                .iter()
+                                .filter_map(|id| players.iter().position(|player| player == id))
+                                .collect()
+                        })
+                        .collect()
+                })
+                .collect();
+            pair_history.add_event(&matches, history::decayed_weight(age, half_life_days));
+        }
+        pair_history
+    }
+    /** Elo ratings of the players from the match results of every event, in date order. Players without results get ratings::INITIAL_RATING*/
+    pub fn ratings(&self, players: &[u32]) -> Vec<f64> {
+        let mut events: Vec<&Event> = self.events.values().collect();
+        events.sort_by(|event1, event2| event1.date.cmp(&event2.date));
+        let mut ids: Vec<u32> = players.to_vec();
+        let mut results: Vec<Vec<(usize, usize)>> = Vec::new();
+        for event in events {
+            for (round, match_ids) in event.rounds_by_id().iter().zip(event.matches_by_round()) {
+                for (table, match_id) in round.iter().zip(match_ids) {
+                    if let Some(scores) = self.get_match(match_id) {
+                        // Scores are in the same order as the players are listed when results are added
+                        let result = table
+                            .iter()
+                            .zip(scores.s.iter())
+                            .map(|(&id, &score)| {
+                                let index = match ids.iter().position(|&other| other == id) {
+                                    Some(index) => index,
+                                    None => {
+                                        ids.push(id);
+                                        ids.len() - 1
+                                    }
+                                };
+                                (index, score)
+                            })
+                            .collect();
+                        results.push(result);
+                    }
+                }
+            }
+        }
+        let mut ratings = ratings::elo_ratings(ids.len(), &results);
+        ratings.truncate(players.len());
+        ratings
+    }
     pub fn remove_event(&mut self, event_id: u32) {
         self.events.remove(&event_id);
         self.dump();
@@ -283,9 +463,12 @@ impl Database {
         self.dump();
         id
     }
-    pub fn set_matches(&mut self, id: u32, matches: Vec<Vec<u32>>) {
+    /** Set the match ids of the event's results, in the same order as Event::rounds_by_id*/
+    pub fn set_matches(&mut self, id: u32, mut matches: Vec<Vec<u32>>) {
         if let Some(event) = self.events.get_mut(&id) {
-            event.matches = matches;
+            let played = event.played_rounds.len().min(matches.len());
+            event.matches = matches.split_off(played);
+            event.played_matches = matches;
             self.dump();
         }
     }
@@ -0,0 +1,183 @@
+use schedule_solver::{Scheduler, SearchStats};
+use std::sync::{Condvar, Mutex};
+
+/** Number of steps each thread takes between checking whether to stop or give work to idle threads*/
+const STEPS_PER_CHECK: u64 = 10_000;
+
+/** How an exact search ended*/
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    /**A schedule where nobody meets anyone twice or sits at the same table twice, as the players at each table of each round*/
+    Found(Vec<Vec<Vec<usize>>>),
+    /**The whole search tree was explored, so no such schedule exists*/
+    Impossible,
+    /**The search was stopped before it finished*/
+    Stopped,
+}
+
+/** Number of players at each table when player_count players are spread as evenly as possible between tables, with the larger tables first*/
+pub fn table_sizes(player_count: usize, tables: usize) -> Vec<usize> {
+    (0..tables)
+        .map(|table| player_count / tables + usize::from(table < player_count % tables))
+        .collect()
+}
+
+/** Work shared between the threads of a search*/
+struct Shared {
+    /**Nodes of the search tree which no thread is exploring yet*/
+    pending: Vec<Vec<u64>>,
+    /**Number of threads exploring a node*/
+    busy: usize,
+    threads: usize,
+    found: Option<Vec<Vec<Vec<usize>>>>,
+    stopped: bool,
+    stats: SearchStats,
+}
+
+/** Search every way of seating players at tables of the sizes for the rounds with schedule_solver, for a schedule where nobody meets anyone twice
+or sits at the same table twice. The threads share the unexplored parts of the search tree, giving part of theirs away whenever a thread is idle.
+should_stop is checked by every thread every few thousand steps, and stops the search when it returns true*/
+pub fn solve(
+    sizes: &[usize],
+    rounds: usize,
+    threads: usize,
+    should_stop: &(dyn Fn() -> bool + Sync),
+) -> (Outcome, SearchStats) {
+    let scheduler = Scheduler::new(sizes, rounds);
+    let block_size = scheduler.get_block_size();
+    let mut root = vec![0; block_size];
+    if !scheduler.initialise_buffer(&mut root) {
+        return (Outcome::Impossible, SearchStats::new());
+    }
+    let shared = Mutex::new(Shared {
+        pending: vec![root],
+        busy: 0,
+        threads: threads.max(1),
+        found: None,
+        stopped: false,
+        stats: SearchStats::new(),
+    });
+    let changed = Condvar::new();
+    std::thread::scope(|scope| {
+        for _ in 0..threads.max(1) {
+            scope.spawn(|| search_thread(&scheduler, &shared, &changed, should_stop));
+        }
+    });
+    let shared = shared.into_inner().expect("Exact search thread panicked");
+    let outcome = match shared.found {
+        Some(matches) => Outcome::Found(matches),
+        None if shared.stopped => Outcome::Stopped,
+        None => Outcome::Impossible,
+    };
+    (outcome, shared.stats)
+}
+
+/** Take nodes from the shared pending list and search below them depth first, until a schedule is found, the search is stopped,
+or every thread is out of work*/
+fn search_thread(
+    scheduler: &Scheduler,
+    shared: &Mutex<Shared>,
+    changed: &Condvar,
+    should_stop: &(dyn Fn() -> bool + Sync),
+) {
+    let block_size = scheduler.get_block_size();
+    let mut stats = SearchStats::new();
+    let mut steps: u64 = 0;
+    loop {
+        let mut node = {
+            let mut state = shared.lock().unwrap();
+            loop {
+                if state.found.is_some() || state.stopped {
+                    state.stats.merge(&stats);
+                    return;
+                }
+                if let Some(node) = state.pending.pop() {
+                    state.busy += 1;
+                    break node;
+                }
+                if state.busy == 0 {
+                    state.stats.merge(&stats);
+                    return;
+                }
+                state = changed.wait(state).unwrap();
+            }
+        };
+        // The node being expanded is at depth, and every block below it is a sibling to come back to
+        let mut depth = 0;
+        loop {
+            if node.len() < (depth + 2) * block_size {
+                node.resize((depth + 2) * block_size, 0);
+            }
+            let (buffer_1, buffer_2) = node[depth * block_size..].split_at_mut(block_size);
+            match scheduler.step(buffer_1, buffer_2, &mut stats) {
+                Some(true) => {
+                    let matches = scheduler.get_matches(scheduler.import_buffer(buffer_1).unwrap());
+                    let mut state = shared.lock().unwrap();
+                    state.found.get_or_insert(matches);
+                    state.busy -= 1;
+                    changed.notify_all();
+                    break;
+                }
+                Some(false) => depth += 1,
+                None if depth == 0 => {
+                    let mut state = shared.lock().unwrap();
+                    state.busy -= 1;
+                    changed.notify_all();
+                    break;
+                }
+                None => depth -= 1,
+            }
+            steps += 1;
+            if steps.is_multiple_of(STEPS_PER_CHECK) {
+                let stop = should_stop();
+                let mut state = shared.lock().unwrap();
+                if stop {
+                    state.stopped = true;
+                }
+                if state.found.is_some() || state.stopped {
+                    state.busy -= 1;
+                    changed.notify_all();
+                    break;
+                }
+                // Give the shallowest sibling away, since it is likely to have the most work under it
+                if depth > 0 && state.pending.is_empty() && state.busy < state.threads {
+                    state.pending.push(node.drain(..block_size).collect());
+                    depth -= 1;
+                    changed.notify_all();
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use schedule::Schedule;
+
+    #[test]
+    fn finds_ideal_schedules() {
+        assert_eq!(table_sizes(10, 3), vec![4, 3, 3]);
+        for &threads in &[1, 4] {
+            match solve(&table_sizes(12, 4), 4, threads, &|| false) {
+                (Outcome::Found(matches), stats) => {
+                    assert!(stats.get_nodes() > 0);
+                    let schedule = Schedule::from_vec(12, 4, 4, &matches);
+                    assert!(schedule.is_ideal());
+                    assert!(schedule
+                        .to_serde_schedule()
+                        .validate(&schedule.ideal_claim())
+                        .is_valid());
+                    assert_eq!(schedule.get_rounds(), 4);
+                }
+                (outcome, _) => panic!("Expected a schedule, got {:?}", outcome),
+            }
+        }
+    }
+
+    #[test]
+    fn proves_impossible_schedules() {
+        // Whoever shares the second table in the second round sat together at the first table in the first round
+        assert_eq!(solve(&[2, 2], 2, 2, &|| false).0, Outcome::Impossible);
+    }
+}
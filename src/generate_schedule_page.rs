@@ -4,16 +4,30 @@ use seed::prelude::*;
 use num_format::{Locale, WriteFormatted};
 
 use crate::{
-    alert, database, next_tick, performance_now, schedule, style_control, view_schedule, Msg,
+    alert, database, history, next_tick, objective, performance_now, run, schedule, strategy,
+    style_control, view_schedule, Msg,
 };
 
+/** How much keeping a player's seat from the original schedule is worth, in unique opponents*/
+const MINIMISE_CHANGES_WEIGHT: u64 = 2;
+
 pub struct GenerateSchedule {
     players: Vec<u32>,
     tables: usize,
+    rounds: usize,
+    /** Number of teams at each table, 1 when players don't play in teams*/
+    teams: usize,
     schedule: Option<schedule::Generator<rand_xorshift::XorShiftRng>>,
     rng: rand_xorshift::XorShiftRng,
+    strategy: String,
+    /** The id of the saved event being changed, None when creating a new event*/
+    original_event: Option<u32>,
+    /** Rounds of the saved event which have been played and are kept as they are, as player ids at each table. rounds doesn't include them*/
+    played_rounds: Vec<Vec<Vec<u32>>>,
+    minimise_changes: bool,
+    /** How strongly tables of even strength are preferred over variety, 0 ignores ratings*/
+    table_balance: u64,
     cpu_usage: f64,
-    loops_per_milli: u32,
     operations_per_second: u32,
     operation_history: [f64; 35],
     total_operations: u64,
@@ -32,6 +46,8 @@ impl Default for GenerateSchedule {
         Self {
             players: Vec::new(),
             tables: 2,
+            rounds: 2,
+            teams: 1,
             schedule: None,
             rng: {
                 let mut seed: [u8; 16] = [0; 16];
@@ -40,8 +56,12 @@ impl Default for GenerateSchedule {
                 };
                 rand_xorshift::XorShiftRng::from_seed(seed)
             },
+            strategy: String::from("hill-climbing"),
+            original_event: None,
+            played_rounds: Vec::new(),
+            minimise_changes: true,
+            table_balance: 0,
             cpu_usage: 99.0,
-            loops_per_milli: 1,
             operations_per_second: 0,
             operation_history: [0.0; 35],
             total_operations: 0,
@@ -57,17 +77,27 @@ impl Default for GenerateSchedule {
 }
 
 impl GenerateSchedule {
+    /** Start generating a schedule. When changing a saved event, the first completed rounds of it are kept as they are,
+    and rounds is the number of rounds to generate after them*/
     pub fn apply_parameters(
         &mut self,
         players: Vec<u32>,
         tables: usize,
+        rounds: usize,
+        teams: usize,
         event_name: &str,
         event_date: &str,
+        original_event: Option<u32>,
+        completed: usize,
+        pair_history: &history::PairHistory,
+        table_balance: u64,
         database: &database::Database,
     ) {
         if let Ok(rng) = rand_xorshift::XorShiftRng::from_rng(&mut self.rng) {
             self.players = players;
             self.tables = tables;
+            self.rounds = rounds;
+            self.teams = teams;
             self.event_name = String::from(event_name);
             self.event_date = String::from(event_date);
             self.running = true;
@@ -75,11 +105,58 @@ impl GenerateSchedule {
             self.operations_per_second = 0;
             self.operation_history = [0.0; 35];
             self.total_operations = 1;
-            self.schedule = Some(schedule::Generator::new(
-                rng,
-                self.players.len(),
-                self.tables,
-            ));
+            self.original_event = original_event;
+            self.played_rounds = Vec::new();
+            let mut schedule =
+                schedule::Schedule::new(self.players.len(), self.tables, self.rounds);
+            if teams > 1 {
+                schedule.set_teams(teams);
+            }
+            schedule.set_pair_history(pair_history);
+            self.table_balance = table_balance;
+            if table_balance > 0 {
+                schedule.set_ratings(&database.ratings(&self.players));
+            }
+            let mut generator = match original_event.and_then(|id| database.get_event(id)) {
+                Some(event) => {
+                    // Number the players of every round, including those who have since left
+                    let rounds_by_id = event.rounds_by_id();
+                    let mut ids: Vec<u32> = event.players.clone();
+                    for &id in rounds_by_id.iter().flatten().flatten() {
+                        if !ids.contains(&id) {
+                            ids.push(id);
+                        }
+                    }
+                    let original: Vec<Vec<Vec<usize>>> = rounds_by_id
+                        .iter()
+                        .map(|round| {
+                            round
+                                .iter()
+                                .map(|table| {
+                                    table
+                                        .iter()
+                                        .filter_map(|id| ids.iter().position(|old_id| old_id == id))
+                                        .collect()
+                                })
+                                .collect()
+                        })
+                        .collect();
+                    let completed = completed.min(rounds_by_id.len());
+                    self.played_rounds = rounds_by_id[..completed].to_vec();
+                    let players: Vec<Option<usize>> = self
+                        .players
+                        .iter()
+                        .map(|new_id| ids.iter().position(|id| id == new_id))
+                        .collect();
+                    schedule::Generator::replan(rng, schedule, &original, completed, &players)
+                }
+                None => schedule::Generator::with_schedule(rng, schedule),
+            };
+            generator.set_objective(self.objective());
+            if let Ok(strategy) = strategy::parse(&self.strategy) {
+                generator.set_strategy(strategy);
+            }
+            self.schedule = Some(generator);
             self.generate_table_display(&database);
         }
     }
@@ -103,6 +180,42 @@ impl GenerateSchedule {
         }
     }
 
+    pub fn set_strategy(&mut self, strategy: String) {
+        match strategy::parse(&strategy) {
+            Ok(parsed) => {
+                if let Some(schedule) = &mut self.schedule {
+                    schedule.set_strategy(parsed);
+                }
+                self.strategy = strategy;
+            }
+            Err(error) => alert(&error),
+        }
+    }
+
+    /** How schedules are scored, from the table balance and whether changes to a saved event are kept to a minimum*/
+    fn objective(&self) -> std::sync::Arc<dyn objective::Objective> {
+        let weights = objective::Weighted {
+            table_balance: self.table_balance,
+            ..objective::Weighted::default()
+        };
+        if self.original_event.is_some() && self.minimise_changes {
+            std::sync::Arc::new(objective::MinimiseChanges {
+                weights,
+                weight: MINIMISE_CHANGES_WEIGHT,
+            })
+        } else {
+            std::sync::Arc::new(weights)
+        }
+    }
+
+    pub fn toggle_minimise_changes(&mut self) {
+        self.minimise_changes = !self.minimise_changes;
+        let objective = self.objective();
+        if let Some(schedule) = &mut self.schedule {
+            schedule.set_objective(objective);
+        }
+    }
+
     fn generate_table_display(&mut self, database: &database::Database) {
         self.current_best = if let Some(schedule) = &self.schedule {
             let best = &schedule.best;
@@ -123,7 +236,28 @@ impl GenerateSchedule {
                     "Minimum number of unique opponents/teammates played with: {}",
                     best.min_unique_opponents()
                 )],
-                view_schedule(best, &self.players, &database, None),
+                p![format!(
+                    "Total unique games played: {} / proven upper bound {}",
+                    best.unique_games_played(),
+                    best.ideal_unique_games
+                )],
+                p![format!(
+                    "Total unique opponents/teammates played with: {} / proven upper bound {}",
+                    best.unique_opponents(),
+                    best.ideal_unique_opponents
+                )],
+                p![format!(
+                    "Best score found: {} / proven upper bound {}",
+                    schedule.best_score,
+                    best.score_bound()
+                )],
+                view_schedule(
+                    best,
+                    &self.players,
+                    &database,
+                    None,
+                    self.played_rounds.len()
+                ),
             ]
         } else {
             div![]
@@ -144,23 +278,23 @@ impl GenerateSchedule {
                 self.operations_per_second = 0;
                 return;
             }
-            let now = performance_now();
-            let ideal = if self.cpu_usage < 50.0 {
-                now + self.cpu_usage * 2.0
-            } else {
-                now + self.cpu_usage
+            let criteria = run::StopCriteria {
+                time_limit: Some(if self.cpu_usage < 50.0 {
+                    self.cpu_usage * 2.0
+                } else {
+                    self.cpu_usage
+                }),
+                stop_at_ideal: true,
+                ..run::StopCriteria::default()
             };
-            let mut operations: u32 = 0;
-            let mut loops: u32 = 0;
-            let old_score = schedule.best.get_score();
-            while performance_now() < ideal {
-                for _ in 0..(1.max(self.loops_per_milli / 2)) {
-                    operations += schedule.process().0;
-                    loops += 1;
+            let mut improved = false;
+            let (reason, stats) = schedule.run_until(&criteria, &performance_now, |event| {
+                if let run::RunEvent::NewBest { .. } = event {
+                    improved = true;
                 }
-            }
-            self.loops_per_milli = 1.max(loops / (self.cpu_usage as u32 + 1));
-            self.total_operations += operations as u64;
+            });
+            let operations = stats.evaluations;
+            self.total_operations += operations;
             self.iteration += 1;
             self.iteration %= 35;
             self.operation_history[self.iteration] = (operations as f64) * 10.0;
@@ -173,10 +307,10 @@ impl GenerateSchedule {
             } else {
                 next_tick(100.0 - self.cpu_usage);
             }
-            if schedule.best.is_ideal() {
+            if reason == run::StopReason::Ideal {
                 self.found_ideal = true
             }
-            if schedule.best.get_score() > old_score {
+            if improved {
                 self.generate_table_display(&database);
             }
         } else {
@@ -191,7 +325,22 @@ impl GenerateSchedule {
             let schedule = schedule_generator.best.clone();
             let players = self.players.clone();
             let tables = self.tables;
-            database.add_event(name, date, schedule, players, tables);
+            let rounds = self.rounds;
+            if let Some(id) = self.original_event {
+                let played_rounds = self.played_rounds.clone();
+                database.update_event(
+                    id,
+                    name,
+                    date,
+                    schedule,
+                    players,
+                    tables,
+                    rounds,
+                    played_rounds,
+                );
+            } else {
+                database.add_event(name, date, schedule, players, tables, rounds);
+            }
         }
     }
 }
@@ -224,7 +373,16 @@ St::FlexGrow=> "1";];
                 ul![
                     li![format!("On {}", model.event_date)],
                     li![format!("{} players", model.players.len())],
-                    li![format!("{} tables", model.tables)]
+                    li![format!("{} tables", model.tables)],
+                    li![format!("{} rounds", model.rounds)],
+                    if model.played_rounds.is_empty() {
+                        span![]
+                    } else {
+                        li![format!(
+                            "After the {} rounds already played, which are kept as they are",
+                            model.played_rounds.len()
+                        )]
+                    }
                 ],
                 p![
                 "The algorithm will attempt to generate a schedule maximise the number of unique games each player plays, \
@@ -274,6 +432,52 @@ St::FlexGrow=> "1";];
                 br![],
                 "This controls how much CPU the program will attempt to use. The high it is, the faster the schedule generation will be, but also the slower other programs running the this computer will be, and the higher temperature the CPU will reach."
             ],
+            p![
+                span!["Search strategy: "],
+                select![
+                    style.button_style(),
+                    input_ev(Ev::Input, Msg::GSSetStrategy),
+                    attrs! {At::Value => model.strategy},
+                    [
+                        ("hill-climbing", "Hill climbing"),
+                        ("annealing", "Simulated annealing"),
+                        ("tabu", "Tabu search"),
+                        ("ils", "Iterated local search"),
+                    ]
+                    .iter()
+                    .map(|(value, name)| option![
+                        style.option_style(),
+                        attrs! {At::Value => value},
+                        if *value == model.strategy {
+                            attrs! {At::Selected => "selected"}
+                        } else {
+                            attrs! {}
+                        },
+                        name
+                    ])
+                    .collect::<Vec<Node<Msg>>>()
+                ],
+                br![],
+                "Hill climbing only keeps changes which improve the schedule, and starts again from a random schedule when stuck. The other strategies sometimes accept worse schedules to escape from being stuck, which can find better schedules for larger events."
+            ],
+            if model.original_event.is_some() {
+                p![
+                    input![
+                        attrs! {At::Type => "checkbox"},
+                        if model.minimise_changes {
+                            attrs! {At::Checked => "checked"}
+                        } else {
+                            attrs! {}
+                        },
+                        simple_ev(Ev::Click, Msg::GSToggleMinimiseChanges)
+                    ],
+                    span!["Keep changes from the saved schedule to a minimum"],
+                    br![],
+                    "Players keep their seats from the saved schedule where possible, so fewer people need to be told about changes."
+                ]
+            } else {
+                p![]
+            },
             p![{
                 let mut writer = String::from("Testing ");
                 writer
@@ -0,0 +1,130 @@
+/** How much a pair of players meeting at one previous event counts, before any decay*/
+pub const MEETING_WEIGHT: u32 = 100;
+
+/** How much each pair of players has met at previous events, used to avoid seating the same players together again.
+Each event a pair met at counts MEETING_WEIGHT, less the longer ago it was when there is decay*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PairHistory {
+    player_count: usize,
+    /**Indexed by player1 * player_count + player2, and the same both ways round*/
+    weights: Vec<u32>,
+}
+
+impl PairHistory {
+    /** Create a history where no pair of players has met before*/
+    pub fn new(player_count: usize) -> Self {
+        Self {
+            player_count,
+            weights: vec![0; player_count * player_count],
+        }
+    }
+
+    /** Add a previous event in the same form as Schedule::import_vec takes, with the players numbered as in the new schedule.
+    Players who aren't in the new schedule should be left out. Pairs who met in several rounds of the event only count once*/
+    pub fn add_event(&mut self, matches: &[Vec<Vec<usize>>], weight: u32) {
+        let mut met = vec![false; self.player_count * self.player_count];
+        for players in matches.iter().flatten() {
+            for &player1 in players {
+                for &player2 in players {
+                    if player1 != player2 {
+                        met[player1 * self.player_count + player2] = true;
+                    }
+                }
+            }
+        }
+        for (total, _) in self.weights.iter_mut().zip(met).filter(|(_, met)| *met) {
+            *total += weight;
+        }
+    }
+
+    /** How much the two players have met before*/
+    pub fn get(&self, player1: usize, player2: usize) -> u32 {
+        self.weights[player1 * self.player_count + player2]
+    }
+
+    pub const fn get_player_count(&self) -> usize {
+        self.player_count
+    }
+
+    /** Whether no pair of players has met before*/
+    pub fn is_empty(&self) -> bool {
+        self.weights.iter().all(|&weight| weight == 0)
+    }
+
+    /** The weights of every pair, indexed by player1 * player_count + player2*/
+    pub fn weights(&self) -> &[u32] {
+        &self.weights
+    }
+}
+
+/** How much an event age_days ago counts. Without a half life every event counts MEETING_WEIGHT,
+otherwise an event counts half as much for every half_life_days that have passed since it*/
+pub fn decayed_weight(age_days: i64, half_life_days: Option<u32>) -> u32 {
+    match half_life_days {
+        Some(half_life) if half_life > 0 => {
+            let halvings = age_days.max(0) as f64 / f64::from(half_life);
+            (f64::from(MEETING_WEIGHT) * 0.5_f64.powf(halvings)).round() as u32
+        }
+        _ => MEETING_WEIGHT,
+    }
+}
+
+/** Number of days from 1970-01-01 to a date in the form YYYY-MM-DD, as entered for events. None if it isn't a valid date*/
+pub fn days_from_date(date: &str) -> Option<i64> {
+    let mut parts = date.trim().splitn(3, '-');
+    let year: i64 = parts.next()?.parse().ok()?;
+    let month: i64 = parts.next()?.parse().ok()?;
+    let day: i64 = parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    // Count years from March, so the leap day is at the end of the year
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    Some(era * 146_097 + day_of_era - 719_468)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pairs_count_once_per_event() {
+        let mut history = PairHistory::new(4);
+        assert!(history.is_empty());
+        history.add_event(
+            &[vec![vec![0, 1], vec![2, 3]], vec![vec![0, 1], vec![2]]],
+            MEETING_WEIGHT,
+        );
+        history.add_event(&[vec![vec![0, 2, 1]]], 50);
+        assert_eq!(history.get(0, 1), MEETING_WEIGHT + 50);
+        assert_eq!(history.get(1, 0), MEETING_WEIGHT + 50);
+        assert_eq!(history.get(2, 3), MEETING_WEIGHT);
+        assert_eq!(history.get(0, 3), 0);
+        assert_eq!(history.get(0, 0), 0);
+    }
+
+    #[test]
+    fn decay_halves_every_half_life() {
+        assert_eq!(decayed_weight(300, None), MEETING_WEIGHT);
+        assert_eq!(decayed_weight(0, Some(7)), MEETING_WEIGHT);
+        assert_eq!(decayed_weight(7, Some(7)), MEETING_WEIGHT / 2);
+        assert_eq!(decayed_weight(14, Some(7)), MEETING_WEIGHT / 4);
+        assert_eq!(decayed_weight(-3, Some(7)), MEETING_WEIGHT);
+    }
+
+    #[test]
+    fn parses_dates() {
+        assert_eq!(days_from_date("1970-01-01"), Some(0));
+        assert_eq!(days_from_date("2000-03-01"), Some(11_017));
+        assert_eq!(
+            days_from_date("2024-03-01").unwrap() - days_from_date("2024-02-28").unwrap(),
+            2
+        );
+        assert_eq!(days_from_date("not a date"), None);
+        assert_eq!(days_from_date("2024-13-01"), None);
+    }
+}
@@ -15,8 +15,26 @@ pub mod preferences_page;
 #[cfg(feature = "default")]
 pub mod style_control;
 
+pub mod analysis;
+pub mod batch;
+pub mod best_known;
+pub mod bounds;
+pub mod exact;
+pub mod history;
+pub mod objective;
+pub mod parallel;
+pub mod ratings;
+pub mod roster;
+pub mod run;
 pub mod schedule;
+pub mod strategy;
+#[cfg(feature = "cli")]
+pub mod tui;
+#[cfg(feature = "cli")]
+extern crate crossterm;
 extern crate rand;
+extern crate schedule_solver;
+extern crate schedule_util;
 
 #[cfg(feature = "default")]
 extern crate getrandom;
@@ -123,8 +141,16 @@ pub enum Msg {
     CERemovePlayer(u32),
     CERemoveAllPlayers,
     CESetTables(String),
+    CESetRounds(String),
+    CESetTeams(String),
+    CEToggleAvoidRepeats(u32),
+    CESetRepeatHalfLife(String),
+    CESetTableBalance(String),
     CEGenerateSchedule,
+    CEStopEditing,
     GSSetCpuUsage(String),
+    GSSetStrategy(String),
+    GSToggleMinimiseChanges,
     GSStop,
     GSResume,
     GSGenerate,
@@ -133,6 +159,8 @@ pub enum Msg {
     MEExpandSchedule(u32),
     MEHideSchedule(u32),
     MEDelete(u32),
+    MEEditEvent(u32),
+    MEReplanEvent(u32, String),
     MESetPlayerFilter(String),
     ARExpandSchedule(u32),
     ARHideSchedule(u32),
@@ -176,10 +204,20 @@ fn update(msg: Msg, model: &mut Model, _: &mut impl Orders<Msg>) {
         Msg::CERemovePlayer(id) => model.create_event.remove_player(id),
         Msg::CERemoveAllPlayers => model.create_event.remove_all_players(),
         Msg::CESetTables(tables) => model.create_event.set_tables(tables),
+        Msg::CESetRounds(rounds) => model.create_event.set_rounds(rounds),
+        Msg::CESetTeams(teams) => model.create_event.set_teams(teams),
+        Msg::CEToggleAvoidRepeats(id) => model.create_event.toggle_avoid_repeats_from(id),
+        Msg::CESetRepeatHalfLife(days) => model.create_event.set_repeat_half_life(days),
+        Msg::CESetTableBalance(table_balance) => {
+            model.create_event.set_table_balance(table_balance)
+        }
         Msg::CEGenerateSchedule => model
             .create_event
             .go_to_generate_schedule_page(&mut model.generate_schedule, &model.database),
+        Msg::CEStopEditing => model.create_event.stop_editing(),
         Msg::GSSetCpuUsage(cpu_usage) => model.generate_schedule.set_cpu_usage(cpu_usage),
+        Msg::GSSetStrategy(strategy) => model.generate_schedule.set_strategy(strategy),
+        Msg::GSToggleMinimiseChanges => model.generate_schedule.toggle_minimise_changes(),
         Msg::GSStop => model.generate_schedule.stop(),
         Msg::GSResume => model.generate_schedule.resume(),
         Msg::GSGenerate => model.generate_schedule.generate(&model.database),
@@ -188,12 +226,24 @@ fn update(msg: Msg, model: &mut Model, _: &mut impl Orders<Msg>) {
         Msg::MEExpandSchedule(id) => model.manage_events.expand_schedule(id),
         Msg::MEHideSchedule(id) => model.manage_events.hide_schedule(id),
         Msg::MEDelete(id) => model.manage_events.delete(id, &mut model.database),
+        Msg::MEEditEvent(id) => {
+            model.generate_schedule.stop();
+            model.create_event.edit_event(id, &model.database);
+            model.page = Page::CreateEvent;
+        }
+        Msg::MEReplanEvent(id, round) => {
+            if let Ok(round) = round.parse::<usize>() {
+                model.generate_schedule.stop();
+                model.create_event.replan_event(id, round, &model.database);
+                model.page = Page::CreateEvent;
+            }
+        }
         Msg::MESetPlayerFilter(id) => model.manage_events.set_filter_by_player(id),
         Msg::ARExpandSchedule(id) => model.add_match_results.expand_schedule(id),
         Msg::ARHideSchedule(id) => model.add_match_results.hide_schedule(id),
-        Msg::ARSetScore(id, round, table, player_number, score) => model
+        Msg::ARSetScore(id, round, table, position, score) => model
             .add_match_results
-            .set_score(id, round, table, player_number, score),
+            .set_score(id, round, table, position, score),
         Msg::ARAddMatchResults(id) => model.add_match_results.add_results(id, &mut model.database),
         Msg::MPAddPlayerNameInput(player_name) => {
             model.manage_players.set_player_name_input(player_name)
@@ -261,9 +311,11 @@ fn view_schedule<T: schedule::ScheduleStructure>(
     players: &[u32],
     database: &database::Database,
     matches: Option<&[Vec<u32>]>,
+    first_round: usize,
 ) -> Node<Msg> {
     table![style![St::BorderSpacing => "5px 10px"; ], {
         let tables = schedule.get_tables();
+        let rounds = schedule.get_rounds();
 
         let mut table: Vec<Node<Msg>> = Vec::with_capacity(tables + 1);
         let mut heading: Vec<Node<Msg>> = Vec::with_capacity(tables + 1);
@@ -272,7 +324,7 @@ fn view_schedule<T: schedule::ScheduleStructure>(
             heading.push(th![format!("Table {:}", game)]);
         }
         table.push(tr![heading]);
-        for round in 0..tables {
+        for round in 0..rounds {
             let matches_in_round = if let Some(matches) = matches {
                 Some(&matches[round])
             } else {
@@ -280,7 +332,7 @@ fn view_schedule<T: schedule::ScheduleStructure>(
             };
             table.push(tr![{
                 let mut row: Vec<Node<Msg>> = Vec::with_capacity(tables + 1);
-                row.push(td![format!("Round {:}", round + 1)]);
+                row.push(td![format!("Round {:}", first_round + round + 1)]);
                 for table in 0..tables {
                     let current_match = {
                         let mut current_match: Vec<usize> = Vec::new();
@@ -321,6 +373,9 @@ fn view_schedule<T: schedule::ScheduleStructure>(
                                 data.push(span![format!("Unknown player ID: {}", id)])
                             }
                         }
+                        if schedule.get_teams() > 1 {
+                            data.push(view_pairing(schedule, players, database, round, table));
+                        }
                         data
                     }]);
                 }
@@ -331,6 +386,77 @@ fn view_schedule<T: schedule::ScheduleStructure>(
     }]
 }
 
+/** Show who plays with who at a table played in teams, as "A+B vs C+D" using the player names*/
+#[cfg(feature = "default")]
+fn view_pairing<T: schedule::ScheduleStructure>(
+    schedule: &T,
+    players: &[u32],
+    database: &database::Database,
+    round: usize,
+    table: usize,
+) -> Node<Msg> {
+    let names: Vec<Vec<String>> = schedule
+        .get_teams_from_game(round, table)
+        .iter()
+        .map(|team| {
+            team.iter()
+                .map(|&player_number| {
+                    let id = players[player_number];
+                    database
+                        .get_player(id)
+                        .map_or_else(|| id.to_string(), |player| player.name.clone())
+                })
+                .collect()
+        })
+        .collect();
+    span![
+        style![St::FontStyle => "italic"],
+        schedule::format_pairing(&names)
+    ]
+}
+
+/** Show rounds which were played before an event was re-planned, given as the player ids at each table, with the scores entered for them*/
+#[cfg(feature = "default")]
+fn view_played_rounds(
+    played_rounds: &[Vec<Vec<u32>>],
+    played_matches: &[Vec<u32>],
+    database: &database::Database,
+) -> Node<Msg> {
+    table![
+        style![St::BorderSpacing => "5px 10px"; ],
+        played_rounds
+            .iter()
+            .enumerate()
+            .map(|(round, tables)| {
+                let mut row: Vec<Node<Msg>> = Vec::with_capacity(tables.len() + 1);
+                row.push(td![format!("Round {:} (played)", round + 1)]);
+                for (table, players) in tables.iter().enumerate() {
+                    let scores: &[usize] = played_matches
+                        .get(round)
+                        .and_then(|matches| matches.get(table))
+                        .and_then(|&match_id| database.get_match(match_id))
+                        .map_or(&[][..], |current_match| &current_match.s[..]);
+                    row.push(td![players
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &id)| {
+                            let name = match database.get_player(id) {
+                                Some(player) => player.name.clone(),
+                                None => format!("Unknown player ID: {}", id),
+                            };
+                            match scores.get(i) {
+                                Some(score) => span![format!("{} {}", name, score), br![]],
+                                None => span![name, br![]],
+                            }
+                        })
+                        .collect::<Vec<Node<Msg>>>()]);
+                }
+                tr![row]
+            })
+            .collect::<Vec<Node<Msg>>>()
+    ]
+}
+
 #[cfg(feature = "default")]
 fn view(model: &Model) -> impl View<Msg> {
     let tab_style = style![St::FlexGrow => "1";];
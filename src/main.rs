@@ -1,11 +1,15 @@
 #[cfg(feature = "cli")]
 extern crate clap;
 #[cfg(feature = "cli")]
+extern crate crossterm;
+#[cfg(feature = "cli")]
 extern crate dirs;
 #[cfg(feature = "cli")]
 extern crate num_cpus;
 extern crate num_format;
 extern crate rand;
+extern crate schedule_solver;
+extern crate schedule_util;
 
 #[cfg(test)]
 #[macro_use]
@@ -17,8 +21,34 @@ use num_format::{Locale, WriteFormatted};
 
 use std::io::prelude::*;
 
+#[cfg(feature = "cli")]
+pub mod analysis;
+#[cfg(feature = "cli")]
+pub mod batch;
+#[cfg(feature = "cli")]
+pub mod best_known;
+#[cfg(feature = "cli")]
+pub mod bounds;
+#[cfg(feature = "cli")]
+pub mod exact;
+#[cfg(feature = "cli")]
+pub mod history;
+#[cfg(feature = "cli")]
+pub mod objective;
+#[cfg(feature = "cli")]
+pub mod parallel;
+#[cfg(feature = "cli")]
+pub mod ratings;
+#[cfg(feature = "cli")]
+pub mod roster;
+#[cfg(feature = "cli")]
+pub mod run;
 #[cfg(feature = "cli")]
 pub mod schedule;
+#[cfg(feature = "cli")]
+pub mod strategy;
+#[cfg(feature = "cli")]
+pub mod tui;
 
 const PROCESS_LOOP_COUNT: usize = 1000;
 
@@ -26,12 +56,271 @@ const PROCESS_LOOP_COUNT: usize = 1000;
 #[derive(Clap)]
 #[clap(version = "1.0")]
 struct Opts {
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+enum Command {
+    /// Search for a good schedule with local search, until it is ideal or one of the limits is reached
+    Generate(Box<Generate>),
+    /// Search every schedule with the exact solver for one where nobody meets anyone twice or sits at the same table twice
+    Solve(Solve),
+    /// Check that a saved schedule seats every player once a round within the table sizes, and reaches any claimed scores
+    Verify(Verify),
+    /// Print a saved schedule with its scores
+    Show(Show),
+    /// Convert a saved schedule to another format and write it to a file
+    Export(Export),
+    /// Print where each player sits every round and who they meet. With --format html, prints a card for each player to hand out
+    Itinerary(Itinerary),
+    /// Report how fair a saved schedule is: how many different players each player meets, which pairs meet more than once or never,
+    /// and how often each player sits at each table, with heatmaps of meetings and table visits. With --format json, prints the counts
+    Analyse(Analyse),
+    /// Manage the cache of best known schedules
+    Cache(Cache),
+    /// Search every size of schedule in ranges of players, tables and rounds for a while each, saving the best to the cache, then write a summary.
+    /// The summary is csv, json or markdown, and text is markdown too
+    Batch(Batch),
+}
+
+/** How schedules are written by every command, as text with their scores or in one of the shared formats*/
+#[cfg(feature = "cli")]
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum Format {
+    Text,
+    Rendered(schedule_util::format::Format),
+}
+
+#[cfg(feature = "cli")]
+impl std::str::FromStr for Format {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "text" => Ok(Format::Text),
+            _ => text.parse().map(Format::Rendered).map_err(|_| {
+                format!(
+                    "Unknown format '{}', expected text, csv, json, markdown or html",
+                    text
+                )
+            }),
+        }
+    }
+}
+
+/** Options every command accepts*/
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Common {
+    /// Seed for the random number generators of commands which search randomly. Runs with the same seed and thread count find the same schedules. Defaults to a seed based on the current time
+    #[clap(long)]
+    seed: Option<u64>,
+    /// Number of threads to search on, defaults to the number of CPUs
+    #[clap(long)]
+    threads: Option<usize>,
+    /// Format to write schedules in: text, csv, json, markdown or html. The html is a page to print, with each round on its own sheet
+    #[clap(long, default_value = "text")]
+    format: Format,
+    /// Write schedules to this file instead of printing them
+    #[clap(long)]
+    output: Option<String>,
+    /// Show players by name, from a roster file with the name of each player in order on its own line, or a CSV file with a header row.
+    /// A CSV roster needs a name column, and can have these columns too: available (yes or no), which leaves members who aren't playing out of the player numbers;
+    /// table, the table a player must sit at every round counting from 1; avoid, names of members a player must never share a table with separated by semicolons;
+    /// and rating, used to balance tables when there is no --ratings. generate keeps to the tables and avoids
+    #[clap(long, alias = "names")]
+    roster: Option<String>,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Generate {
+    #[clap(flatten)]
+    common: Common,
+    players: usize,
+    tables: usize,
+    /// Defaults to the number of tables, so everyone can play every game once
+    #[clap(short, long)]
+    rounds: Option<usize>,
+    /// How schedules are scored: default, weighted:<unique opponents>,<min unique opponents>,<unique games played>[,<unique partners>[,<repeated pairs>[,<table balance>]]], lexicographic, max-min or variance:<penalty>
+    #[clap(short, long, default_value = "default")]
+    objective: String,
+    /// How schedules are searched: hill-climbing, annealing[:<initial temperature>,<cooling factor>], tabu[:<tenure>,<restart after>] or ils[:<perturbation strength>]
+    #[clap(short, long, default_value = "hill-climbing")]
+    strategy: String,
+    /// Extra moves to search with besides swapping and relocating single players, separated by commas: cycles, groups:<size> or exchanges
+    #[clap(long)]
+    moves: Option<String>,
+    /// Make the threads cooperate as islands, sending their best schedule to the next thread every this many epochs of 1000 steps
+    #[clap(long)]
+    migrate_every: Option<u64>,
+    /// Start from a saved schedule in JSON, such as one from the cache, instead of a fresh schedule. It can be for different numbers of players, tables or rounds. Use with --objective stable:<weight> to change it as little as possible
+    #[clap(long)]
+    start_from: Option<String>,
+    /// Split each table into this many teams, such as 2 for doubles, so partners are counted separately from opponents. Shown as A+B vs C+D
+    #[clap(long)]
+    teams: Option<usize>,
+    /// Avoid seating players together who met in these saved schedules in JSON, such as previous weeks of a club. Players are matched by number
+    #[clap(long)]
+    avoid_repeats_from: Vec<String>,
+    /// Balance the strength of the tables using a file of player ratings, one number for each player in order separated by whitespace. Use the table balance weight of --objective weighted to trade balance against variety
+    #[clap(long)]
+    ratings: Option<String>,
+    /// Merge a bundle of best known schedules, as written by --export-cache, into the cache before searching. Entries only replace worse ones
+    #[clap(long)]
+    import_cache: Option<String>,
+    /// Write every best known schedule in the cache to this file after searching, so others can use it with --import-cache
+    #[clap(long)]
+    export_cache: Option<String>,
+    /// Stop after this many seconds, even if the schedule isn't ideal
+    #[clap(long)]
+    time_limit: Option<f64>,
+    /// Stop after evaluating this many schedules
+    #[clap(long)]
+    max_evaluations: Option<u64>,
+    /// Stop once a schedule scores at least this much
+    #[clap(long)]
+    target_score: Option<u64>,
+    /// Stop after evaluating this many schedules without finding a better one
+    #[clap(long)]
+    stagnation: Option<u64>,
+    /// Show the search in a full screen view which updates in place, with keys to pause, save the best schedule, change strategy and quit
+    #[clap(long)]
+    tui: bool,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Solve {
+    #[clap(flatten)]
+    common: Common,
     players: usize,
     tables: usize,
+    /// Defaults to the number of tables. There can't be more rounds than tables, since nobody sits at the same table twice
+    #[clap(short, long)]
+    rounds: Option<usize>,
+    /// Stop after this many seconds, even if the search hasn't finished
+    #[clap(long)]
+    time_limit: Option<f64>,
 }
 
 #[cfg(feature = "cli")]
-fn display_schedule<T: schedule::ScheduleStructure>(output: &mut String, schedule: &T) {
+#[derive(Clap)]
+struct Verify {
+    #[clap(flatten)]
+    common: Common,
+    /// A schedule in JSON, or an entry of the best known cache
+    schedule: String,
+    /// Also check that the schedule reaches the proven upper bounds on unique games played and unique opponents
+    #[clap(long)]
+    ideal: bool,
+    /// Also check that no pair of players meets more than this many times
+    #[clap(long)]
+    max_meetings: Option<usize>,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Show {
+    #[clap(flatten)]
+    common: Common,
+    /// A schedule in JSON, or an entry of the best known cache
+    schedule: String,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Export {
+    #[clap(flatten)]
+    common: Common,
+    /// A schedule in JSON, or an entry of the best known cache
+    schedule: String,
+    /// Format to convert to: csv, json, markdown or html
+    target: schedule_util::format::Format,
+    /// File to write the converted schedule to
+    file: String,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Itinerary {
+    #[clap(flatten)]
+    common: Common,
+    /// A schedule in JSON, or an entry of the best known cache
+    schedule: String,
+    /// Only show this player, by name from the roster or by number
+    #[clap(long)]
+    player: Option<String>,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Analyse {
+    #[clap(flatten)]
+    common: Common,
+    /// A schedule in JSON, or an entry of the best known cache
+    schedule: String,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Cache {
+    #[clap(flatten)]
+    common: Common,
+    #[clap(subcommand)]
+    action: CacheAction,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+struct Batch {
+    #[clap(flatten)]
+    common: Common,
+    /// Numbers of players, as a range such as 8..64 which includes both ends, or a single number
+    #[clap(long, default_value = "8..64")]
+    players: batch::SizeRange,
+    /// Numbers of tables, as a range or a single number. Sizes with fewer than two players at a table are skipped
+    #[clap(long, default_value = "2..12")]
+    tables: batch::SizeRange,
+    /// Numbers of rounds, as a range or a single number. Defaults to the number of tables of each size
+    #[clap(long)]
+    rounds: Option<batch::SizeRange>,
+    /// Seconds to search each size for, unless an ideal schedule is found sooner
+    #[clap(long, default_value = "10")]
+    time_limit: f64,
+    /// How schedules are scored, see generate
+    #[clap(short, long, default_value = "default")]
+    objective: String,
+    /// How schedules are searched, see generate
+    #[clap(short, long, default_value = "hill-climbing")]
+    strategy: String,
+}
+
+#[cfg(feature = "cli")]
+#[derive(Clap)]
+enum CacheAction {
+    /// List the best known schedules with how they were found. With --format json, prints them all as a bundle like export
+    List,
+    /// Remove entries which can't be read or aren't valid schedules
+    Prune {
+        /// Also remove entries found more than this many days ago
+        #[clap(long)]
+        older_than: Option<u64>,
+    },
+    /// Merge a bundle of best known schedules, as written by cache export, into the cache. Entries only replace worse ones
+    Import { bundle: String },
+    /// Write every best known schedule in the cache to a bundle, so others can import it
+    Export { bundle: String },
+}
+
+#[cfg(feature = "cli")]
+fn display_schedule<T: schedule::ScheduleStructure>(
+    output: &mut String,
+    schedule: &T,
+    names: &[String],
+) {
     let schedule = schedule.to_schedule();
     output.push_str(&format!(
         "Average number of unique games played: {}\n",
@@ -45,48 +334,98 @@ fn display_schedule<T: schedule::ScheduleStructure>(output: &mut String, schedul
         "Minimum number of unique opponents/teammates played with: {}\n",
         schedule.min_unique_opponents()
     ));
-    output.push_str("     ");
+    output.push_str(&format!(
+        "Total unique games played: {} / proven upper bound {}\n",
+        schedule.unique_games_played(),
+        schedule.ideal_unique_games
+    ));
+    output.push_str(&format!(
+        "Total unique opponents/teammates played with: {} / proven upper bound {}\n",
+        schedule.unique_opponents(),
+        schedule.ideal_unique_opponents
+    ));
+    if schedule.get_teams() > 1 {
+        output.push_str(&format!(
+            "Average number of unique partners played with: {}\n",
+            (schedule.unique_partners() as f32 / schedule.get_player_count() as f32)
+        ));
+    }
+    let label = |player: usize| match names.get(player) {
+        Some(name) => name.clone(),
+        None => player.to_string(),
+    };
+    let games: Vec<Vec<Vec<String>>> = (0..schedule.get_rounds())
+        .map(|round| {
+            (0..schedule.get_tables())
+                // Players are listed team by team, so the pairings below line up with the table
+                .map(|table| {
+                    schedule
+                        .get_teams_from_game(round, table)
+                        .concat()
+                        .into_iter()
+                        .map(label)
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+    // Every column is as wide as the longest name or number in it, and at least 3 so small schedules keep their layout
+    let width = games
+        .iter()
+        .flatten()
+        .flatten()
+        .map(|name| name.chars().count())
+        .chain(std::iter::once(
+            schedule
+                .get_tables()
+                .max(schedule.get_rounds())
+                .to_string()
+                .len(),
+        ))
+        .fold(3, usize::max);
+    let rows = games.iter().flatten().map(Vec::len).fold(0, usize::max) + 1;
+    output.push_str(&" ".repeat(width + 2));
     for table in 0..schedule.get_tables() {
-        let now = (table + 1).to_string();
-        output.push('|');
-        for _ in 0..(3 - now.len()) {
-            output.push(' ');
-        }
-        output.push_str(&now);
-        output.push_str("  ");
+        output.push_str(&format!("|{:>width$}  ", table + 1, width = width));
     }
 
-    for round in 0..schedule.get_tables() {
-        output.push_str("\n-----");
+    for (round, tables) in games.iter().enumerate() {
+        output.push('\n');
+        output.push_str(&"-".repeat(width + 2));
         for _ in 0..schedule.get_tables() {
             output.push('+');
-            output.push_str("-----")
+            output.push_str(&"-".repeat(width + 2));
         }
-        for i in 0..(schedule.get_player_count() / schedule.get_tables() + 1) {
-            if i == (schedule.get_player_count() / schedule.get_tables() + 1) / 2 {
-                output.push('\n');
-                let now = (round + 1).to_string();
-                for _ in 0..(3 - now.len()) {
-                    output.push(' ');
-                }
-                output.push_str(&now);
-                output.push_str("  ");
+        for i in 0..rows {
+            if i == rows / 2 {
+                output.push_str(&format!("\n{:>width$}  ", round + 1, width = width));
             } else {
-                output.push_str("\n     ");
+                output.push('\n');
+                output.push_str(&" ".repeat(width + 2));
             }
+            for players in tables.iter() {
+                output.push_str(&format!(
+                    "|{:>width$}  ",
+                    players.get(i).map_or("", String::as_str),
+                    width = width
+                ));
+            }
+        }
+    }
+    if schedule.get_teams() > 1 {
+        for round in 0..schedule.get_rounds() {
+            output.push_str(&format!("\nRound {}:", round + 1));
             for table in 0..schedule.get_tables() {
-                let player_list = schedule.get_players_from_game(round, table);
-                output.push('|');
-                if let Some(now) = player_list.get(i) {
-                    let now = now.to_string();
-                    for _ in 0..(3 - now.len()) {
-                        output.push(' ');
-                    }
-                    output.push_str(&now);
-                    output.push_str("  ");
-                } else {
-                    output.push_str("     ");
-                }
+                let teams: Vec<Vec<String>> = schedule
+                    .get_teams_from_game(round, table)
+                    .into_iter()
+                    .map(|team| team.into_iter().map(label).collect())
+                    .collect();
+                output.push_str(&format!(
+                    "\n    Table {}: {}",
+                    table + 1,
+                    schedule::format_pairing(&teams)
+                ));
             }
         }
     }
@@ -110,31 +449,273 @@ fn display_performance(output: &mut String, operations: u64, random_starts: u64,
     output.push('\n');
 }
 
+/** Load a saved schedule in JSON, or an entry of the best known cache*/
 #[cfg(feature = "cli")]
-fn main() {
-    let opts: Opts = Opts::parse();
-    let path = match dirs::data_local_dir() {
+fn load_schedule(path: &str) -> Result<schedule::SerdeSchedule, String> {
+    std::fs::read_to_string(path)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| {
+            serde_json::from_str::<schedule::SerdeSchedule>(&contents)
+                .or_else(|_| {
+                    serde_json::from_str::<best_known::BestKnown>(&contents)
+                        .map(|entry| entry.schedule)
+                })
+                .map_err(|error| error.to_string())
+        })
+        .map_err(|error| format!("Failed to load {}: {}", path, error))
+}
+
+/** Load a saved schedule in JSON as the players at each table of each round*/
+#[cfg(feature = "cli")]
+fn load_matches(path: &str) -> Result<Vec<Vec<Vec<usize>>>, String> {
+    Ok(matches_of(&load_schedule(path)?))
+}
+
+/** Load the roster, see roster::Roster::parse. Empty when there is no file, so players are shown by number and have no constraints*/
+#[cfg(feature = "cli")]
+fn load_roster(common: &Common) -> Result<roster::Roster, String> {
+    match &common.roster {
+        Some(path) => std::fs::read_to_string(path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| roster::Roster::parse(&contents))
+            .map_err(|error| format!("Failed to load {}: {}", path, error)),
+        None => Ok(roster::Roster::default()),
+    }
+}
+
+/** Write a schedule in the format to the output file, or print it if there is none*/
+#[cfg(feature = "cli")]
+fn write_schedule(common: &Common, schedule: &schedule::SerdeSchedule) -> Result<(), String> {
+    let names = load_roster(common)?.names();
+    let text = match common.format {
+        Format::Text => {
+            let mut output = String::new();
+            display_schedule(&mut output, schedule, &names);
+            output.push('\n');
+            output
+        }
+        Format::Rendered(format) => schedule.render(format, &names),
+    };
+    match &common.output {
+        Some(path) => std::fs::write(path, text)
+            .map_err(|error| format!("Failed to write {}: {}", path, error)),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/** Where the caches are kept*/
+#[cfg(feature = "cli")]
+fn data_path() -> std::path::PathBuf {
+    match dirs::data_local_dir() {
         Some(path) => [path, std::path::PathBuf::from("social_schedule")]
             .iter()
             .collect(),
         None => std::path::PathBuf::from("."),
+    }
+}
+
+/** The cache of best known schedules*/
+#[cfg(feature = "cli")]
+fn best_known_cache() -> best_known::Cache {
+    best_known::Cache::new(
+        [data_path(), std::path::PathBuf::from("best")]
+            .iter()
+            .collect(),
+    )
+}
+
+/** Seconds since 1970-01-01*/
+#[cfg(feature = "cli")]
+fn unix_time() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or(0)
+}
+
+/** The cached best known schedule for these numbers of players, tables, rounds and teams. None if there is none, or it isn't a valid schedule of that size*/
+#[cfg(feature = "cli")]
+fn cached_entry(
+    best_known: &best_known::Cache,
+    players: usize,
+    tables: usize,
+    rounds: usize,
+    teams: usize,
+) -> Option<best_known::BestKnown> {
+    use schedule::ScheduleStructure;
+    best_known
+        .load(&best_known::name(players, tables, rounds, teams))
+        .filter(|entry| {
+            entry.schedule.get_player_count() == players
+                && entry.schedule.get_tables() == tables
+                && entry.schedule.get_rounds() == rounds
+                && entry.schedule.get_teams() == teams
+                && entry
+                    .schedule
+                    .validate(&schedule_util::validate::Claim::default())
+                    .is_valid()
+        })
+}
+
+/** The players at each table of each round of a schedule*/
+#[cfg(feature = "cli")]
+fn matches_of<T: schedule::ScheduleStructure>(schedule: &T) -> Vec<Vec<Vec<usize>>> {
+    (0..schedule.get_rounds())
+        .map(|round| {
+            (0..schedule.get_tables())
+                .map(|table| schedule.get_players_from_game(round, table))
+                .collect()
+        })
+        .collect()
+}
+
+#[cfg(feature = "cli")]
+fn generate(opts: &Generate) {
+    let common = &opts.common;
+    if opts.tables < 2 || opts.players < 2 * opts.tables {
+        println!("There must be at least two tables, with at least two players at each");
+        return;
+    }
+    if opts.rounds == Some(0) {
+        println!("There must be at least 1 round");
+        return;
+    }
+    let roster = match load_roster(common) {
+        Ok(roster) => roster,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let names = roster.names();
+    if common.roster.is_some() && names.len() != opts.players {
+        println!(
+            "The roster has {} players available, but there are {} players",
+            names.len(),
+            opts.players
+        );
+        return;
+    }
+    let constraints = match roster.constraints(opts.tables) {
+        Ok(constraints) => constraints,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
     };
-    let ideal_path_base: std::path::PathBuf =
-        [path, std::path::PathBuf::from("ideal")].iter().collect();
+    let rounds = opts.rounds.unwrap_or(opts.tables);
+    let objective = match objective::parse(&opts.objective) {
+        Ok(objective) => objective,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    if let Err(error) = strategy::parse(&opts.strategy) {
+        println!("{}", error);
+        return;
+    }
+    let neighbourhood = match opts.moves.as_deref().map(schedule::Neighbourhood::parse) {
+        Some(Ok(neighbourhood)) => neighbourhood,
+        Some(Err(error)) => {
+            println!("{}", error);
+            return;
+        }
+        None => schedule::Neighbourhood::default(),
+    };
+    let teams = opts.teams.unwrap_or(1);
+    if teams == 0 || teams > usize::from(u8::MAX) || opts.players < opts.tables * teams {
+        println!("Every table needs at least one player in each team, with at most 255 teams");
+        return;
+    }
+    let seats = opts.players.div_ceil(opts.tables);
+    for table in 0..opts.tables {
+        let pinned = constraints
+            .pinned
+            .iter()
+            .filter(|&&pinned| pinned == Some(table))
+            .count();
+        if pinned > seats {
+            println!(
+                "{} players are pinned to table {}, which only seats {}",
+                pinned,
+                table + 1,
+                seats
+            );
+            return;
+        }
+    }
+    let new_schedule = || {
+        let mut schedule = schedule::Schedule::new(opts.players, opts.tables, rounds);
+        if teams > 1 {
+            schedule.set_teams(teams);
+        }
+        schedule
+    };
+    let cache_name = best_known::name(opts.players, opts.tables, rounds, teams);
+    let best_known = best_known_cache();
+    if let Some(bundle_path) = &opts.import_cache {
+        let imported = std::fs::read_to_string(bundle_path)
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                serde_json::from_str::<best_known::Bundle>(&contents)
+                    .map_err(|error| error.to_string())
+            })
+            .and_then(|bundle| best_known.import(&bundle));
+        match imported {
+            Ok(count) => println!("Imported {} better schedules from {}", count, bundle_path),
+            Err(error) => {
+                println!("Failed to import {}: {}", bundle_path, error);
+                return;
+            }
+        }
+    }
+    let ideal_path_base: std::path::PathBuf = [data_path(), std::path::PathBuf::from("ideal")]
+        .iter()
+        .collect();
     let _ = std::fs::create_dir_all(&ideal_path_base);
-    let ideal_path: std::path::PathBuf = [
-        ideal_path_base,
-        std::path::PathBuf::from(format!("{}_players_{}_tables", opts.players, opts.tables)),
-    ]
-    .iter()
-    .collect();
-    {
+    let ideal_path: std::path::PathBuf = [ideal_path_base, std::path::PathBuf::from(&cache_name)]
+        .iter()
+        .collect();
+    let ratings: Option<Vec<f64>> = match &opts.ratings {
+        Some(path) => {
+            let loaded = std::fs::read_to_string(path)
+                .map_err(|error| error.to_string())
+                .and_then(|contents| {
+                    contents
+                        .split_whitespace()
+                        .map(|rating| rating.parse::<f64>().map_err(|error| error.to_string()))
+                        .collect::<Result<Vec<f64>, String>>()
+                });
+            match loaded {
+                Ok(ratings) if ratings.len() == opts.players => Some(ratings),
+                Ok(ratings) => {
+                    println!(
+                        "{} has {} ratings, but there are {} players",
+                        path,
+                        ratings.len(),
+                        opts.players
+                    );
+                    return;
+                }
+                Err(error) => {
+                    println!("Failed to load {}: {}", path, error);
+                    return;
+                }
+            }
+        }
+        None => roster.ratings(),
+    };
+    // A cached schedule doesn't know who met before, how strong players are or where they must sit
+    let use_cache =
+        opts.avoid_repeats_from.is_empty() && ratings.is_none() && constraints.is_empty();
+    if use_cache {
         let search_paths = [
             &ideal_path,
-            &std::path::PathBuf::from(format!(
-                "cache/ideal/{}_players_{}_tables",
-                opts.players, opts.tables
-            )),
+            &std::path::PathBuf::from(format!("cache/ideal/{}", cache_name)),
         ];
         for path in search_paths.iter() {
             println!("Attempting to loading cache from {}", path.display());
@@ -142,89 +723,863 @@ fn main() {
                 let mut contents = String::new();
                 let _ = file.read_to_string(&mut contents);
                 if let Ok(schedule) = serde_json::from_str::<schedule::SerdeSchedule>(&contents) {
-                    let mut output = String::new();
-                    display_schedule(&mut output, &schedule);
-                    println!("Found ideal from cache: \n{}", output);
+                    use schedule::ScheduleStructure;
+                    if schedule.get_player_count() != opts.players
+                        || schedule.get_tables() != opts.tables
+                        || schedule.get_rounds() != rounds
+                        || schedule.get_teams() != teams
+                    {
+                        println!(
+                            "Cached schedule is for a different number of players, tables, rounds or teams"
+                        );
+                        continue;
+                    }
+                    let claim = new_schedule().ideal_claim();
+                    let report = schedule.validate(&claim);
+                    if !report.is_valid() {
+                        println!("Cached schedule is invalid:");
+                        for violation in report.violations.iter() {
+                            println!("    {}", violation);
+                        }
+                        continue;
+                    }
+                    println!("Found ideal from cache:");
+                    if let Err(error) = write_schedule(common, &schedule) {
+                        println!("{}", error);
+                    }
                     return;
                 }
             }
         }
     }
-    let (tx, rx) = std::sync::mpsc::channel::<schedule::Schedule>();
-    let operations = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
-    let random_starts = std::sync::Arc::new(std::sync::atomic::AtomicU64::new(0));
-    println!("Spawning {} threads", num_cpus::get());
-    for _ in 0..num_cpus::get() {
-        let tx = tx.clone();
-        let operations = std::sync::Arc::clone(&operations);
-        let random_starts = std::sync::Arc::clone(&random_starts);
-        let players = opts.players;
-        let tables = opts.tables;
-        std::thread::spawn(move || {
-            let mut schedule_generator =
-                schedule::Generator::new(rand::thread_rng(), players, tables);
-
-            tx.send(schedule_generator.best.clone()).unwrap();
-            loop {
-                let old_score = schedule_generator.best.get_score();
-                let mut local_operations: u64 = 0;
-                let mut local_random_starts: u64 = 0;
-                for _ in 0..PROCESS_LOOP_COUNT {
-                    let (ops, rs) = schedule_generator.process();
-                    local_operations += ops as u64;
-                    local_random_starts += rs as u64;
+    let seed = common.seed.unwrap_or_else(|| {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|duration| duration.as_nanos() as u64)
+            .unwrap_or(0)
+    });
+    let threads = common.threads.unwrap_or_else(num_cpus::get).max(1);
+    println!("Spawning {} threads with seed {}", threads, seed);
+    let original = match opts.start_from.as_ref().map(|path| load_matches(path)) {
+        Some(Ok(original)) => Some(original),
+        Some(Err(error)) => {
+            println!("{}", error);
+            return;
+        }
+        None if use_cache => cached_entry(&best_known, opts.players, opts.tables, rounds, teams)
+            .map(|entry| {
+                println!(
+                    "Starting from the best known schedule, which scored {} with objective {} and was found by seed {} with strategy {} at Unix time {}",
+                    entry.score, entry.objective, entry.seed, entry.strategy, entry.timestamp
+                );
+                matches_of(&entry.schedule)
+            }),
+        None => None,
+    };
+    let mut pair_history = history::PairHistory::new(opts.players);
+    for path in opts.avoid_repeats_from.iter() {
+        match load_matches(path) {
+            Ok(matches) => {
+                let matches: Vec<Vec<Vec<usize>>> = matches
+                    .into_iter()
+                    .map(|round| {
+                        round
+                            .into_iter()
+                            .map(|players| {
+                                players
+                                    .into_iter()
+                                    .filter(|&player| player < opts.players)
+                                    .collect()
+                            })
+                            .collect()
+                    })
+                    .collect();
+                pair_history.add_event(&matches, history::MEETING_WEIGHT);
+            }
+            Err(error) => {
+                println!("{}", error);
+                return;
+            }
+        }
+    }
+    let generators = parallel::worker_rngs::<rand_xorshift::XorShiftRng>(seed, threads)
+        .into_iter()
+        .map(|rng| {
+            let mut schedule_generator = match &original {
+                Some(original) => schedule::Generator::warm_start(rng, new_schedule(), original),
+                None if teams > 1 => schedule::Generator::with_schedule(rng, new_schedule()),
+                None => schedule::Generator::new(rng, opts.players, opts.tables, rounds),
+            };
+            schedule_generator.set_objective(objective.clone());
+            schedule_generator.set_pair_history(&pair_history);
+            if let Some(ratings) = &ratings {
+                schedule_generator.set_ratings(ratings);
+            }
+            if !constraints.is_empty() {
+                schedule_generator.set_constraints(&constraints);
+            }
+            schedule_generator.set_strategy(strategy::parse(&opts.strategy).unwrap());
+            schedule_generator.set_neighbourhood(neighbourhood);
+            schedule_generator
+        })
+        .collect();
+    let mut generators = parallel::Parallel::new(generators, PROCESS_LOOP_COUNT);
+    if let Some(interval) = opts.migrate_every {
+        generators.set_migration(parallel::Migration {
+            interval,
+            ..parallel::Migration::default()
+        });
+    }
+    let criteria = run::StopCriteria {
+        time_limit: opts.time_limit.map(|seconds| seconds * 1000.0),
+        max_evaluations: opts.max_evaluations,
+        target_score: opts.target_score,
+        stagnation: opts.stagnation,
+        stop_at_ideal: true,
+        progress_interval: Some(1000.0),
+    };
+    let show_progress = |schedule: &schedule::Schedule,
+                         stats: run::RunStats,
+                         diversity: Option<f64>,
+                         with_schedule: bool| {
+        let mut output = String::new();
+        display_performance(
+            &mut output,
+            stats.evaluations,
+            stats.random_starts,
+            (stats.elapsed * 1_000_000.0) as u128,
+        );
+        if let Some(diversity) = diversity {
+            output.push_str(&format!("Island diversity: {:.1} pairs\n", diversity));
+        }
+        output.push_str(&format!(
+            "Best score found: {} / proven upper bound {}\n",
+            schedule.get_score(),
+            schedule.score_bound()
+        ));
+        if !constraints.is_empty() {
+            output.push_str(&format!(
+                "Broken constraints: {}\n",
+                schedule.constraint_violations()
+            ));
+        }
+        if with_schedule {
+            display_schedule(&mut output, schedule, &names);
+        }
+        println!("{}", output);
+    };
+    show_progress(
+        &generators.best,
+        run::RunStats::default(),
+        opts.migrate_every.map(|_| generators.diversity()),
+        true,
+    );
+    // Saves the best schedule if it beats the cached one, so long searches aren't lost when they are stopped
+    let save_best_known = |schedule: &schedule::Schedule| {
+        if !use_cache {
+            return;
+        }
+        let entry = best_known::BestKnown {
+            schedule: schedule.to_serde_schedule(),
+            score: schedule.get_score(),
+            objective: opts.objective.clone(),
+            seed,
+            strategy: opts.strategy.clone(),
+            timestamp: unix_time(),
+        };
+        if let Err(error) = best_known.offer(&cache_name, &entry) {
+            println!("{}", error);
+        }
+    };
+    let mut saved_score = generators.best_score;
+    // Only stops between epochs, so the schedules found don't depend on how fast the threads ran unless there is a time limit
+    let (ending, stats) = if opts.tui {
+        let performance = |stats: run::RunStats| {
+            let mut output = String::new();
+            display_performance(
+                &mut output,
+                stats.evaluations,
+                stats.random_starts,
+                (stats.elapsed * 1_000_000.0) as u128,
+            );
+            output
+        };
+        let grid = |schedule: &schedule::Schedule| {
+            let mut output = String::new();
+            display_schedule(&mut output, schedule, &names);
+            output
+        };
+        let save = |schedule: &schedule::Schedule| {
+            save_best_known(schedule);
+            let mut saved = Vec::new();
+            if use_cache {
+                saved.push("the cache".to_string());
+            }
+            if let Some(path) = &common.output {
+                if let Err(error) = write_schedule(common, &schedule.to_serde_schedule()) {
+                    return error;
                 }
+                saved.push(path.clone());
+            }
+            if saved.is_empty() {
+                "Nowhere to save to, use --output".to_string()
+            } else {
+                format!("Saved to {}", saved.join(" and "))
+            }
+        };
+        let view = tui::View {
+            performance: &performance,
+            grid: &grid,
+            save: &save,
+        };
+        match tui::run(&mut generators, &criteria, &opts.strategy, &view) {
+            Ok(result) => result,
+            Err(error) => {
+                println!("Failed to run the terminal UI: {}", error);
+                return;
+            }
+        }
+    } else {
+        let clock = run::std_clock();
+        let (reason, stats) = generators.run_until(&criteria, &clock, |event| {
+            if let run::RunEvent::Progress {
+                best,
+                score,
+                stats,
+                diversity,
+            } = event
+            {
+                show_progress(best, stats, diversity, true);
+                if score > saved_score {
+                    save_best_known(best);
+                    saved_score = score;
+                }
+            }
+        });
+        (tui::Ending::Stopped(reason), stats)
+    };
+    let schedule = &generators.best;
+    save_best_known(schedule);
+    match ending {
+        tui::Ending::Stopped(run::StopReason::Ideal) => println!("\n\nFound ideal schedule\n"),
+        tui::Ending::Stopped(reason) => println!("\n\nStopped because the search {}\n", reason),
+        tui::Ending::Quit => println!("\n\nStopped by the user\n"),
+    }
+    show_progress(
+        schedule,
+        stats,
+        opts.migrate_every.map(|_| generators.diversity()),
+        false,
+    );
+    if let Err(error) = write_schedule(common, &schedule.to_serde_schedule()) {
+        println!("{}", error);
+    }
+    if let Some(bundle_path) = &opts.export_cache {
+        let written = serde_json::to_string(&best_known.export())
+            .map_err(|error| error.to_string())
+            .and_then(|contents| {
+                std::fs::write(bundle_path, contents).map_err(|error| error.to_string())
+            });
+        if let Err(error) = written {
+            println!("Failed to export the cache to {}: {}", bundle_path, error);
+        }
+    }
+    if schedule.is_ideal() {
+        let serde_schedule = schedule.to_serde_schedule();
+
+        if let Ok(string_form) = serde_json::to_string(&serde_schedule) {
+            if let Ok(mut file) = std::fs::File::create(ideal_path) {
+                file.write_all(string_form.as_bytes()).unwrap();
+            }
+        }
+    }
+}
 
-                operations.fetch_add(local_operations, std::sync::atomic::Ordering::SeqCst);
-                random_starts.fetch_add(local_random_starts, std::sync::atomic::Ordering::SeqCst);
+#[cfg(feature = "cli")]
+fn solve(opts: &Solve) {
+    let common = &opts.common;
+    let rounds = opts.rounds.unwrap_or(opts.tables);
+    if opts.tables < 2 || opts.players < 2 * opts.tables {
+        println!("There must be at least two tables, with at least two players at each");
+        return;
+    }
+    if rounds == 0 || rounds > opts.tables {
+        println!("There must be between 1 and {} rounds", opts.tables);
+        return;
+    }
+    let threads = common.threads.unwrap_or_else(num_cpus::get).max(1);
+    println!("Searching on {} threads", threads);
+    let start = std::time::Instant::now();
+    let should_stop = || {
+        opts.time_limit
+            .is_some_and(|seconds| start.elapsed().as_secs_f64() >= seconds)
+    };
+    let (outcome, stats) = exact::solve(
+        &exact::table_sizes(opts.players, opts.tables),
+        rounds,
+        threads,
+        &should_stop,
+    );
+    let mut output = String::new();
+    output.push_str("Explored ");
+    output
+        .write_formatted(&stats.get_nodes(), &Locale::en)
+        .unwrap();
+    output.push_str(&format!(
+        " nodes in {:.1} seconds, reaching a depth of {} players placed",
+        start.elapsed().as_secs_f64(),
+        stats.get_max_depth()
+    ));
+    println!("{}", output);
+    match outcome {
+        exact::Outcome::Found(matches) => {
+            let mut schedule =
+                schedule::Schedule::from_vec(opts.players, opts.tables, rounds, &matches);
+            schedule.generate_score();
+            let entry = best_known::BestKnown {
+                schedule: schedule.to_serde_schedule(),
+                score: schedule.get_score(),
+                objective: "default".to_string(),
+                seed: 0,
+                strategy: "exact".to_string(),
+                timestamp: unix_time(),
+            };
+            let name = best_known::name(opts.players, opts.tables, rounds, 1);
+            if let Err(error) = best_known_cache().offer(&name, &entry) {
+                println!("{}", error);
+            }
+            println!("Found a schedule where nobody meets anyone twice or sits at the same table twice\n");
+            if let Err(error) = write_schedule(common, &entry.schedule) {
+                println!("{}", error);
+            }
+        }
+        exact::Outcome::Impossible => {
+            println!("No schedule exists where nobody meets anyone twice or sits at the same table twice")
+        }
+        exact::Outcome::Stopped => println!("Reached the time limit before the search finished"),
+    }
+}
 
-                if schedule_generator.best.get_score() > old_score {
-                    tx.send(schedule_generator.best.clone()).unwrap();
+#[cfg(feature = "cli")]
+fn verify(opts: &Verify) {
+    use schedule::ScheduleStructure;
+    let schedule = match load_schedule(&opts.schedule) {
+        Ok(schedule) => schedule,
+        Err(error) => {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+    };
+    let mut claim = schedule_util::validate::Claim {
+        max_repeat_pairings: opts.max_meetings,
+        ..schedule_util::validate::Claim::default()
+    };
+    // The ideal scores are only known for schedules that fit a Schedule
+    if opts.ideal && schedule.validate(&claim).is_valid() {
+        let ideal = schedule.to_schedule().ideal_claim();
+        claim.unique_opponents = ideal.unique_opponents;
+        claim.unique_games_played = ideal.unique_games_played;
+    }
+    let report = schedule.validate(&claim);
+    match opts.common.format {
+        Format::Rendered(schedule_util::format::Format::Json) => println!(
+            "{}",
+            serde_json::json!({
+                "valid": report.is_valid(),
+                "violations": report
+                    .violations
+                    .iter()
+                    .map(|violation| violation.to_string())
+                    .collect::<Vec<String>>(),
+                "unique_opponents": report.unique_opponents,
+                "min_unique_opponents": report.min_unique_opponents,
+                "unique_games_played": report.unique_games_played,
+                "repeat_pairings": report.repeat_pairings.len(),
+            })
+        ),
+        _ => {
+            println!(
+                "{} is {}",
+                opts.schedule,
+                if report.is_valid() {
+                    "valid"
+                } else {
+                    "invalid"
                 }
+            );
+            for violation in report.violations.iter() {
+                println!("    {}", violation);
             }
-        });
+            println!(
+                "Total unique opponents/teammates played with: {}",
+                report.unique_opponents
+            );
+            println!(
+                "Minimum number of unique opponents/teammates played with: {}",
+                report.min_unique_opponents
+            );
+            println!("Total unique games played: {}", report.unique_games_played);
+            println!(
+                "Pairs of players who meet more than once: {}",
+                report.repeat_pairings.len()
+            );
+        }
     }
-    let best_schedule = std::sync::Arc::new(std::sync::Mutex::new(rx.recv().unwrap()));
+    if !report.is_valid() {
+        std::process::exit(1);
+    }
+}
+
+/** Load a schedule for show and export, which need it to be valid to score it*/
+#[cfg(feature = "cli")]
+fn load_valid_schedule(path: &str) -> Result<schedule::SerdeSchedule, String> {
+    let schedule = load_schedule(path)?;
+    let report = schedule.validate(&schedule_util::validate::Claim::default());
+    match report.violations.first() {
+        Some(violation) => Err(format!("{} is invalid: {}", path, violation)),
+        None => Ok(schedule),
+    }
+}
+
+#[cfg(feature = "cli")]
+fn show(common: &Common, path: &str) {
+    if let Err(error) =
+        load_valid_schedule(path).and_then(|schedule| write_schedule(common, &schedule))
     {
-        let best_schedule = std::sync::Arc::clone(&best_schedule);
-        let mut best_score = best_schedule.lock().unwrap().get_score();
-        std::thread::spawn(move || loop {
-            let schedule = rx.recv().unwrap();
-            if schedule.get_score() > best_score {
-                *best_schedule.lock().unwrap() = schedule;
-                best_score = best_schedule.lock().unwrap().get_score();
-            }
+        println!("{}", error);
+    }
+}
+
+#[cfg(feature = "cli")]
+fn export(opts: &Export) {
+    let common = &opts.common;
+    if common.output.is_some() || common.format != Format::Text {
+        println!("export takes the format and file as arguments, instead of --format and --output");
+        return;
+    }
+    let written = load_valid_schedule(&opts.schedule)
+        .and_then(|schedule| load_roster(common).map(|roster| (schedule, roster.names())))
+        .and_then(|(schedule, names)| {
+            std::fs::write(&opts.file, schedule.render(opts.target, &names))
+                .map_err(|error| format!("Failed to write {}: {}", opts.file, error))
         });
+    match written {
+        Ok(()) => println!("Wrote {}", opts.file),
+        Err(error) => println!("{}", error),
     }
-    let instant = std::time::Instant::now();
-    loop {
-        if let Ok(schedule) = best_schedule.lock() {
-            if schedule.is_ideal() {
-                println!("\n\nFound ideal schedule\n");
-            }
+}
+
+#[cfg(feature = "cli")]
+fn itinerary(opts: &Itinerary) {
+    use schedule::ScheduleStructure;
+    let common = &opts.common;
+    let loaded = load_valid_schedule(&opts.schedule)
+        .and_then(|schedule| load_roster(common).map(|roster| (schedule, roster.names())));
+    let (schedule, names) = match loaded {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let players: Vec<usize> = match &opts.player {
+        Some(player) => match names.iter().position(|name| name == player) {
+            Some(number) => vec![number],
+            None => match player.parse::<usize>() {
+                Ok(number) if number < schedule.get_player_count() => vec![number],
+                _ => {
+                    println!("There is no player {}", player);
+                    return;
+                }
+            },
+        },
+        None => (0..schedule.get_player_count()).collect(),
+    };
+    let text = match common.format {
+        Format::Text => {
+            let full_schedule = schedule.to_schedule();
+            let matches = matches_of(&schedule);
+            let layout = schedule_util::format::Layout {
+                teams: schedule.get_teams(),
+                names: &names,
+                ..schedule_util::format::Layout::new(schedule.get_player_count(), &matches)
+            };
             let mut output = String::new();
-            display_performance(
-                &mut output,
-                operations.load(std::sync::atomic::Ordering::Relaxed),
-                random_starts.load(std::sync::atomic::Ordering::Relaxed),
-                instant.elapsed().as_nanos(),
+            for &player in players.iter() {
+                output.push_str(&format!("{}\n", layout.label(player)));
+                for (round, seat) in full_schedule.itinerary(player).iter().enumerate() {
+                    output.push_str(&format!(
+                        "    Round {}: table {} with {}\n",
+                        round + 1,
+                        seat.table + 1,
+                        layout.meets(player, seat)
+                    ));
+                }
+            }
+            output
+        }
+        Format::Rendered(format) => schedule.render_itineraries(format, &names, &players),
+    };
+    match &common.output {
+        Some(path) => {
+            if let Err(error) = std::fs::write(path, text) {
+                println!("Failed to write {}: {}", path, error);
+            }
+        }
+        None => print!("{}", text),
+    }
+}
+
+/** A heatmap cell showing a count, coloured from green for once to red for three or more times when colour is on, and a dot for never*/
+#[cfg(feature = "cli")]
+fn heat_cell(output: &mut String, count: u32, width: usize, colour: bool) {
+    let text = if count == 0 {
+        ".".to_string()
+    } else {
+        count.to_string()
+    };
+    let background = match count {
+        0 => None,
+        1 => Some(42),
+        2 => Some(43),
+        _ => Some(41),
+    };
+    match background {
+        Some(background) if colour => output.push_str(&format!(
+            "\x1b[30;{}m{:>width$}\x1b[0m",
+            background,
+            text,
+            width = width
+        )),
+        _ => output.push_str(&format!("{:>width$}", text, width = width)),
+    }
+}
+
+/** A grid with a labelled row for each player, and columns numbered from first_column, showing the count in each cell*/
+#[cfg(feature = "cli")]
+fn display_heatmap(
+    output: &mut String,
+    labels: &[String],
+    rows: &[Vec<u32>],
+    first_column: usize,
+    colour: bool,
+) {
+    let label_width = labels.iter().map(|label| label.len()).max().unwrap_or(0);
+    let columns = rows.first().map_or(0, |row| row.len());
+    let width = (first_column + columns).to_string().len() + 1;
+    output.push_str(&" ".repeat(label_width));
+    for column in first_column..first_column + columns {
+        output.push_str(&format!("{:>width$}", column, width = width));
+    }
+    output.push('\n');
+    for (label, row) in labels.iter().zip(rows.iter()) {
+        output.push_str(&format!("{:<width$}", label, width = label_width));
+        for &count in row.iter() {
+            heat_cell(output, count, width, colour);
+        }
+        output.push('\n');
+    }
+}
+
+#[cfg(feature = "cli")]
+fn analyse(opts: &Analyse) {
+    use schedule::ScheduleStructure;
+    use std::io::IsTerminal;
+    let common = &opts.common;
+    let loaded = load_valid_schedule(&opts.schedule)
+        .and_then(|schedule| load_roster(common).map(|roster| (schedule, roster.names())));
+    let (schedule, names) = match loaded {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    let analysis = analysis::Analysis::new(&schedule);
+    let matches = matches_of(&schedule);
+    let layout = schedule_util::format::Layout {
+        names: &names,
+        ..schedule_util::format::Layout::new(schedule.get_player_count(), &matches)
+    };
+    let text = match common.format {
+        Format::Rendered(schedule_util::format::Format::Json) => {
+            let report = serde_json::json!({
+                "unique_opponents": (0..analysis.player_count())
+                    .map(|player| analysis.unique_opponents(player))
+                    .collect::<Vec<usize>>(),
+                "unique_opponent_distribution": analysis.unique_opponent_distribution(),
+                "repeat_pairs": analysis.repeat_pairs(),
+                "most_meetings": analysis.most_meetings(),
+                "never_met": analysis.never_met(),
+                "meetings": analysis.meetings,
+                "table_visits": analysis.table_visits,
+            });
+            report.to_string() + "\n"
+        }
+        Format::Text => {
+            let colour = common.output.is_none()
+                && std::env::var_os("NO_COLOR").is_none()
+                && std::io::stdout().is_terminal();
+            let mut output = String::from("Players meeting each number of different players:\n");
+            for (opponents, &players) in analysis.unique_opponent_distribution().iter().enumerate()
+            {
+                if players > 0 {
+                    output.push_str(&format!(
+                        "    {} different players: {} players\n",
+                        opponents, players
+                    ));
+                }
+            }
+            output.push_str(&format!(
+                "Pairs meeting more than once: {}, and the most any pair meets is {} times\n",
+                analysis.repeat_pairs(),
+                analysis.most_meetings()
+            ));
+            let never_met = analysis.never_met();
+            output.push_str(&format!("Pairs who never meet: {}\n", never_met.len()));
+            for &(player1, player2) in never_met.iter() {
+                output.push_str(&format!(
+                    "    {} and {}\n",
+                    layout.label(player1),
+                    layout.label(player2)
+                ));
+            }
+            let labels: Vec<String> = (0..analysis.player_count())
+                .map(|player| match names.get(player) {
+                    Some(name) => format!("{} {} ", player, name),
+                    None => format!("{} ", player),
+                })
+                .collect();
+            output.push_str(
+                "\nTimes each pair of players meets, with a column for each player number:\n",
             );
-            display_schedule(&mut output, &*schedule);
-            println!("{}", output);
-            if schedule.is_ideal() {
-                let serde_schedule = schedule.to_serde_schedule();
-
-                if let Ok(string_form) = serde_json::to_string(&serde_schedule) {
-                    if let Ok(mut file) = std::fs::File::create(ideal_path) {
-                        file.write_all(string_form.as_bytes()).unwrap();
+            display_heatmap(&mut output, &labels, &analysis.meetings, 0, colour);
+            output.push_str("\nTimes each player sits at each table:\n");
+            display_heatmap(&mut output, &labels, &analysis.table_visits, 1, colour);
+            output
+        }
+        Format::Rendered(_) => {
+            println!("The analysis can be written as text or json");
+            return;
+        }
+    };
+    match &common.output {
+        Some(path) => {
+            if let Err(error) = std::fs::write(path, text) {
+                println!("Failed to write {}: {}", path, error);
+            }
+        }
+        None => print!("{}", text),
+    }
+}
+
+#[cfg(feature = "cli")]
+fn cache(opts: &Cache) {
+    let best_known = best_known_cache();
+    match &opts.action {
+        CacheAction::List => match opts.common.format {
+            Format::Rendered(schedule_util::format::Format::Json) => {
+                write_bundle(&best_known.export(), opts.common.output.as_deref())
+            }
+            _ => {
+                for name in best_known.names() {
+                    match best_known.load(&name) {
+                        Some(entry) => println!(
+                            "{}: scored {} with objective {}, found by seed {} with strategy {} at Unix time {}",
+                            name, entry.score, entry.objective, entry.seed, entry.strategy, entry.timestamp
+                        ),
+                        None => println!("{}: unreadable", name),
                     }
                 }
-                break;
             }
-        } else {
-            panic!("Failed");
+        },
+        CacheAction::Prune { older_than } => {
+            let now = unix_time();
+            let pruned = best_known.prune(|_, entry| {
+                older_than
+                    .is_none_or(|days| now.saturating_sub(entry.timestamp) <= days * 24 * 60 * 60)
+            });
+            match pruned {
+                Ok(removed) => {
+                    for name in removed.iter() {
+                        println!("Removed {}", name);
+                    }
+                    println!("Removed {} entries", removed.len());
+                }
+                Err(error) => println!("{}", error),
+            }
+        }
+        CacheAction::Import { bundle } => {
+            let imported = std::fs::read_to_string(bundle)
+                .map_err(|error| error.to_string())
+                .and_then(|contents| {
+                    serde_json::from_str::<best_known::Bundle>(&contents)
+                        .map_err(|error| error.to_string())
+                })
+                .and_then(|bundle| best_known.import(&bundle));
+            match imported {
+                Ok(count) => println!("Imported {} better schedules from {}", count, bundle),
+                Err(error) => println!("Failed to import {}: {}", bundle, error),
+            }
+        }
+        CacheAction::Export { bundle } => write_bundle(&best_known.export(), Some(bundle)),
+    }
+}
+
+#[cfg(feature = "cli")]
+fn batch(opts: &Batch) {
+    let common = &opts.common;
+    let objective = match objective::parse(&opts.objective) {
+        Ok(objective) => objective,
+        Err(error) => {
+            println!("{}", error);
+            return;
+        }
+    };
+    if let Err(error) = strategy::parse(&opts.strategy) {
+        println!("{}", error);
+        return;
+    }
+    if common.format == Format::Rendered(schedule_util::format::Format::Html) {
+        println!("The summary can be written as text, csv, json or markdown");
+        return;
+    }
+    let sizes = batch::sizes(opts.players, opts.tables, opts.rounds);
+    let seed = common.seed.unwrap_or_else(unix_time);
+    let threads = common.threads.unwrap_or_else(num_cpus::get).max(1);
+    println!(
+        "Searching {} sizes for up to {} seconds each on {} threads with seed {}",
+        sizes.len(),
+        opts.time_limit,
+        threads,
+        seed
+    );
+    let best_known = best_known_cache();
+    let criteria = run::StopCriteria {
+        time_limit: Some(opts.time_limit * 1000.0),
+        stop_at_ideal: true,
+        ..run::StopCriteria::default()
+    };
+    let mut summaries = Vec::with_capacity(sizes.len());
+    for size in sizes {
+        let start = std::time::Instant::now();
+        let cached = cached_entry(&best_known, size.players, size.tables, size.rounds, 1)
+            .map(|entry| matches_of(&entry.schedule));
+        let mut schedule = schedule::Schedule::new(size.players, size.tables, size.rounds);
+        schedule.set_objective(objective.clone());
+        if let Some(matches) = &cached {
+            schedule.import_vec(matches);
+        }
+        // Sizes which already have an ideal schedule in the cache aren't searched again
+        if cached.is_none() || !schedule.is_ideal() {
+            let generators = parallel::worker_rngs::<rand_xorshift::XorShiftRng>(seed, threads)
+                .into_iter()
+                .map(|rng| {
+                    let mut schedule_generator = match &cached {
+                        Some(matches) => schedule::Generator::warm_start(
+                            rng,
+                            schedule::Schedule::new(size.players, size.tables, size.rounds),
+                            matches,
+                        ),
+                        None => {
+                            schedule::Generator::new(rng, size.players, size.tables, size.rounds)
+                        }
+                    };
+                    schedule_generator.set_objective(objective.clone());
+                    schedule_generator.set_strategy(strategy::parse(&opts.strategy).unwrap());
+                    schedule_generator
+                })
+                .collect();
+            let mut generators = parallel::Parallel::new(generators, PROCESS_LOOP_COUNT);
+            generators.run_until(&criteria, &run::std_clock(), |_| {});
+            schedule = generators.best;
+            let entry = best_known::BestKnown {
+                schedule: schedule.to_serde_schedule(),
+                score: schedule.get_score(),
+                objective: opts.objective.clone(),
+                seed,
+                strategy: opts.strategy.clone(),
+                timestamp: unix_time(),
+            };
+            let name = best_known::name(size.players, size.tables, size.rounds, 1);
+            if let Err(error) = best_known.offer(&name, &entry) {
+                println!("{}", error);
+            }
         }
-        std::thread::sleep(std::time::Duration::from_millis(1000));
+        let summary = batch::Summary {
+            players: size.players,
+            tables: size.tables,
+            rounds: size.rounds,
+            score: schedule.get_score(),
+            bound: schedule.score_bound(),
+            ideal: schedule.is_ideal(),
+            optimal: schedule.get_score() >= schedule.score_bound(),
+            seconds: start.elapsed().as_secs_f64(),
+        };
+        println!(
+            "{} players, {} tables, {} rounds: scored {} / proven upper bound {}{}",
+            summary.players,
+            summary.tables,
+            summary.rounds,
+            summary.score,
+            summary.bound,
+            if summary.ideal { ", ideal" } else { "" }
+        );
+        summaries.push(summary);
+    }
+    let text = match common.format {
+        Format::Rendered(schedule_util::format::Format::Csv) => batch::csv(&summaries),
+        Format::Rendered(schedule_util::format::Format::Json) => {
+            match serde_json::to_string_pretty(&summaries) {
+                Ok(json) => json + "\n",
+                Err(error) => {
+                    println!("{}", error);
+                    return;
+                }
+            }
+        }
+        _ => batch::markdown(&summaries),
+    };
+    match &common.output {
+        Some(path) => {
+            if let Err(error) = std::fs::write(path, text) {
+                println!("Failed to write {}: {}", path, error);
+            }
+        }
+        None => print!("\n{}", text),
+    }
+}
+
+/** Write a bundle of best known schedules to the file, or print it if there is none*/
+#[cfg(feature = "cli")]
+fn write_bundle(bundle: &best_known::Bundle, path: Option<&str>) {
+    let written = serde_json::to_string(bundle)
+        .map_err(|error| error.to_string())
+        .and_then(|contents| match path {
+            Some(path) => std::fs::write(path, contents).map_err(|error| error.to_string()),
+            None => {
+                println!("{}", contents);
+                Ok(())
+            }
+        });
+    if let Err(error) = written {
+        println!("Failed to export the cache: {}", error);
+    }
+}
+
+#[cfg(feature = "cli")]
+fn main() {
+    let opts: Opts = Opts::parse();
+    match &opts.command {
+        Command::Generate(opts) => generate(opts),
+        Command::Solve(opts) => solve(opts),
+        Command::Verify(opts) => verify(opts),
+        Command::Show(opts) => show(&opts.common, &opts.schedule),
+        Command::Export(opts) => export(opts),
+        Command::Itinerary(opts) => itinerary(opts),
+        Command::Analyse(opts) => analyse(opts),
+        Command::Cache(opts) => cache(opts),
+        Command::Batch(opts) => batch(opts),
     }
 }
 
@@ -1,7 +1,9 @@
 use seed::prelude::*;
 
+use crate::schedule::ScheduleStructure;
 use crate::{
-    alert, database, generate_schedule_page, player_select_box, style_control, view_schedule, Msg,
+    alert, database, generate_schedule_page, player_select_box, style_control, view_played_rounds,
+    view_schedule, Msg,
 };
 
 #[derive(Default)]
@@ -72,6 +74,32 @@ St::FlexGrow=> "1";];
                         td![event.date],
                         td![format!("{:} players", event.players.len())],
                         td![button![style.button_style(), "Change Name"]],
+                        td![button![
+                            style.button_style(),
+                            ev(Ev::Click, move |_| Msg::MEEditEvent(id)),
+                            "Change Players or Tables"
+                        ]],
+                        td![select![
+                            style.button_style(),
+                            input_ev(Ev::Input, move |round| Msg::MEReplanEvent(id, round)),
+                            {
+                                let mut round_list: Vec<Node<Msg>> =
+                                    Vec::with_capacity(event.total_rounds());
+                                round_list.push(option![
+                                    style.option_style(),
+                                    attrs! {At::Value => ""},
+                                    "Re-plan from round..."
+                                ]);
+                                for round in 2..=event.total_rounds() {
+                                    round_list.push(option![
+                                        style.option_style(),
+                                        attrs! {At::Value => round},
+                                        format!("Re-plan from round {}", round)
+                                    ]);
+                                }
+                                round_list
+                            }
+                        ]],
                         td![button![
                             style.button_style(),
                             ev(Ev::Click, move |_| Msg::MEDelete(id)),
@@ -91,6 +119,15 @@ St::FlexGrow=> "1";];
                             ]
                         }]
                     ]);
+                    if let Some(filter_by_player) = model.filter_by_player {
+                        node_list.push(tr![td![
+                            attrs! {At::ColSpan => 10},
+                            div![
+                                &box_style,
+                                view_itinerary(event, filter_by_player, database)
+                            ]
+                        ]]);
+                    }
                     node_list.push(tr![td![
                         attrs! {At::ColSpan => 10},
                         div![
@@ -99,16 +136,29 @@ St::FlexGrow=> "1";];
                             div![
                                 &box_style,
                                 if model.expanded_schedules.contains(&id) {
-                                    view_schedule(
-                                        &event.schedule,
-                                        &event.players,
-                                        database,
-                                        if event.matches.is_empty() {
-                                            None
+                                    div![
+                                        if event.played_rounds.is_empty() {
+                                            span![]
                                         } else {
-                                            Some(&event.matches)
+                                            view_played_rounds(
+                                                &event.played_rounds,
+                                                &event.played_matches,
+                                                database,
+                                            )
                                         },
-                                    )
+                                        view_schedule(
+                                            &event.schedule,
+                                            &event.players,
+                                            database,
+                                            if event.matches.is_empty() {
+                                                None
+                                            } else {
+                                                Some(&event.matches)
+                                            },
+                                            event.played_rounds.len(),
+                                        ),
+                                        view_fairness(event, database)
+                                    ]
                                 } else {
                                     div![]
                                 }
@@ -122,6 +172,167 @@ St::FlexGrow=> "1";];
     ]
 }
 
+/** Show where a player sits in each round of an event and who they meet, including rounds played before it was re-planned*/
+fn view_itinerary(event: &database::Event, id: u32, database: &database::Database) -> Node<Msg> {
+    let name = |id: u32| {
+        database.get_player(id).map_or_else(
+            || format!("Unknown player ID: {}", id),
+            |player| player.name.clone(),
+        )
+    };
+    let mut rows: Vec<Node<Msg>> = Vec::with_capacity(event.total_rounds() + 1);
+    rows.push(tr![th!["Round"], th!["Table"], th!["Meets"]]);
+    for (round, tables) in event.played_rounds.iter().enumerate() {
+        if let Some((table, players)) = tables
+            .iter()
+            .enumerate()
+            .find(|(_, players)| players.contains(&id))
+        {
+            rows.push(tr![
+                td![format!("{} (played)", round + 1)],
+                td![(table + 1).to_string()],
+                td![players
+                    .iter()
+                    .filter(|&&other| other != id)
+                    .map(|&other| name(other))
+                    .collect::<Vec<String>>()
+                    .join(", ")]
+            ]);
+        }
+    }
+    if let Some(player_number) = event.players.iter().position(|&player| player == id) {
+        let schedule = event.schedule.to_schedule();
+        for (round, seat) in schedule.itinerary(player_number).iter().enumerate() {
+            let meets = if schedule.get_teams() > 1 {
+                let names: Vec<String> = seat
+                    .players
+                    .iter()
+                    .map(|&other| name(event.players[other]))
+                    .collect();
+                crate::schedule::format_pairing(&crate::schedule::split_into_teams(
+                    &names,
+                    schedule.get_teams(),
+                ))
+            } else {
+                seat.players
+                    .iter()
+                    .filter(|&&other| other != player_number)
+                    .map(|&other| name(event.players[other]))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            };
+            rows.push(tr![
+                td![(event.played_rounds.len() + round + 1).to_string()],
+                td![(seat.table + 1).to_string()],
+                td![meets]
+            ]);
+        }
+    }
+    div![
+        h3![format!("Itinerary for {}", name(id))],
+        table![style![St::BorderSpacing => "5px 10px"; ], rows]
+    ]
+}
+
+/** Background colour of a cell counting how often something happened, from green for once to red for three or more times*/
+fn heat_colour(count: u32) -> &'static str {
+    match count {
+        0 => "transparent",
+        1 => "#9d9",
+        2 => "#ed8",
+        _ => "#e88",
+    }
+}
+
+/** A grid with a row for each player named in labels, and columns headed by headings, coloured by the count in each cell*/
+fn view_heat_grid(labels: &[String], headings: &[String], rows: &[Vec<u32>]) -> Node<Msg> {
+    let cell_style = style![St::Width => "1.5em"; St::TextAlign => "center"];
+    let mut grid: Vec<Node<Msg>> = Vec::with_capacity(rows.len() + 1);
+    let mut heading: Vec<Node<Msg>> = Vec::with_capacity(headings.len() + 1);
+    heading.push(td![]);
+    for column in headings {
+        heading.push(th![&cell_style, column]);
+    }
+    grid.push(tr![heading]);
+    for (label, row) in labels.iter().zip(rows.iter()) {
+        let mut cells: Vec<Node<Msg>> = Vec::with_capacity(row.len() + 1);
+        cells.push(th![style![St::TextAlign => "left"], label]);
+        for &count in row {
+            cells.push(td![
+                &cell_style,
+                style![St::BackgroundColor => heat_colour(count)],
+                if count == 0 {
+                    "".to_string()
+                } else {
+                    count.to_string()
+                }
+            ]);
+        }
+        grid.push(tr![cells]);
+    }
+    table![style![St::BorderSpacing => "2px"; ], grid]
+}
+
+/** Show how fair the planned rounds of an event are, with coloured grids of how often each pair meets and how often each player sits at each table*/
+fn view_fairness(event: &database::Event, database: &database::Database) -> Node<Msg> {
+    let analysis = crate::analysis::Analysis::new(&event.schedule);
+    let names: Vec<String> = event
+        .players
+        .iter()
+        .map(|&id| {
+            database.get_player(id).map_or_else(
+                || format!("Unknown player ID: {}", id),
+                |player| player.name.clone(),
+            )
+        })
+        .collect();
+    let labels: Vec<String> = names
+        .iter()
+        .enumerate()
+        .map(|(player, name)| format!("{} {}", player + 1, name))
+        .collect();
+    let player_numbers: Vec<String> = (1..=names.len()).map(|player| player.to_string()).collect();
+    let table_numbers: Vec<String> = (1..=event.schedule.get_tables())
+        .map(|table| table.to_string())
+        .collect();
+    let never_met = analysis.never_met();
+    div![
+        h3!["Fairness"],
+        p![format!(
+            "Different players met: {}",
+            analysis
+                .unique_opponent_distribution()
+                .iter()
+                .enumerate()
+                .filter(|(_, &players)| players > 0)
+                .map(|(opponents, players)| format!("{} players meet {}", players, opponents))
+                .collect::<Vec<String>>()
+                .join(", ")
+        )],
+        p![format!(
+            "Pairs meeting more than once: {}, pairs who never meet: {}",
+            analysis.repeat_pairs(),
+            never_met.len()
+        )],
+        if never_met.is_empty() {
+            p![]
+        } else {
+            p![format!(
+                "Never meet: {}",
+                never_met
+                    .iter()
+                    .map(|&(player1, player2)| format!("{} and {}", names[player1], names[player2]))
+                    .collect::<Vec<String>>()
+                    .join(", ")
+            )]
+        },
+        h4!["Times each pair meets"],
+        view_heat_grid(&labels, &player_numbers, &analysis.meetings),
+        h4!["Times each player sits at each table"],
+        view_heat_grid(&labels, &table_numbers, &analysis.table_visits),
+    ]
+}
+
 pub enum CreateEventStages {
     Details,
     GenerateSchedule,
@@ -134,6 +345,20 @@ pub struct CreateEvent {
     add_player_select_box: Option<u32>,
     add_group_select_box: Option<u32>,
     tables: Option<usize>,
+    /** None means one round per table*/
+    rounds: Option<usize>,
+    /** Number of teams at each table, 1 when players don't play in teams*/
+    teams: usize,
+    /** Previous events whose pairings shouldn't be repeated*/
+    avoid_repeats_from: std::collections::HashSet<u32>,
+    /** Previous events count half as much for every this many days before the event, None means they all count the same*/
+    repeat_half_life: Option<u32>,
+    /** How strongly tables of even strength, from ratings of past match results, are preferred over variety. 0 ignores ratings*/
+    table_balance: u64,
+    /** The id of the saved event being changed, None when creating a new event*/
+    editing: Option<u32>,
+    /** Number of rounds of the saved event which have been played, these are kept and only the rounds after them are re-planned*/
+    completed_rounds: usize,
     pub stage: CreateEventStages,
 }
 
@@ -146,6 +371,13 @@ impl Default for CreateEvent {
             add_player_select_box: None,
             add_group_select_box: None,
             tables: None,
+            rounds: None,
+            teams: 1,
+            avoid_repeats_from: std::collections::HashSet::new(),
+            repeat_half_life: None,
+            table_balance: 0,
+            editing: None,
+            completed_rounds: 0,
             stage: CreateEventStages::Details,
         }
     }
@@ -158,6 +390,33 @@ impl CreateEvent {
     pub fn set_event_date(&mut self, date: String) {
         self.event_date = date
     }
+    /** Load a saved event to change its details, the new schedule will start from the saved one*/
+    pub fn edit_event(&mut self, id: u32, database: &database::Database) {
+        if let Some(event) = database.get_event(id) {
+            self.event_name = event.name.clone();
+            self.event_date = event.date.clone();
+            self.players = event.players.iter().copied().collect();
+            self.tables = Some(event.tables);
+            self.rounds = Some(event.total_rounds());
+            self.teams = event.schedule.get_teams();
+            self.editing = Some(id);
+            self.completed_rounds = event.played_rounds.len();
+            self.stage = CreateEventStages::Details;
+        } else {
+            alert("Event does not exist");
+        }
+    }
+    /** Load a saved event part way through to change its players or tables, keeping the rounds before round as they were played*/
+    pub fn replan_event(&mut self, id: u32, round: usize, database: &database::Database) {
+        self.edit_event(id, database);
+        if self.editing == Some(id) {
+            self.completed_rounds = round.saturating_sub(1);
+        }
+    }
+    pub fn stop_editing(&mut self) {
+        self.editing = None;
+        self.completed_rounds = 0;
+    }
     pub fn back_to_details(&mut self) {
         self.stage = CreateEventStages::Details;
     }
@@ -172,24 +431,47 @@ impl CreateEvent {
                 alert("Event name is empty")
             } else if self.event_date.is_empty() {
                 alert("Event date is empty")
-            } else if self.players.len() > 64 {
+            } else if self.players.len() < tables * 2 {
                 alert(&format!(
-                    "Has {} players, which is above the maximum of 64",
+                    "Has {} players, which is below minimium of 2 per table",
                     self.players.len()
                 ));
-            } else if self.players.len() < tables * 2 {
+            } else if self.players.len() < tables * self.teams {
                 alert(&format!(
-                    "Has {} players, which is below minimium of 2 per table",
+                    "Has {} players, which is below minimium of one per team at every table",
                     self.players.len()
                 ));
+            } else if self.rounds.unwrap_or(tables) <= self.completed_rounds {
+                alert(&format!(
+                    "{} rounds have already been played, so there are no rounds left to plan",
+                    self.completed_rounds
+                ));
             } else {
                 self.stage = CreateEventStages::GenerateSchedule;
                 let players: Vec<u32> = self.players.iter().copied().collect();
+                let avoid_repeats_from: Vec<u32> = self
+                    .avoid_repeats_from
+                    .iter()
+                    .copied()
+                    .filter(|&id| Some(id) != self.editing)
+                    .collect();
+                let pair_history = database.pair_history(
+                    &avoid_repeats_from,
+                    &players,
+                    &self.event_date,
+                    self.repeat_half_life,
+                );
                 generate_schedule_model.apply_parameters(
                     players,
                     tables,
+                    self.rounds.unwrap_or(tables) - self.completed_rounds,
+                    self.teams,
                     &self.event_name,
                     &self.event_date,
+                    self.editing,
+                    self.completed_rounds,
+                    &pair_history,
+                    self.table_balance,
                     &database,
                 )
             }
@@ -254,6 +536,32 @@ impl CreateEvent {
             self.tables = None;
         }
     }
+    pub fn set_rounds(&mut self, rounds: String) {
+        match rounds.parse::<usize>() {
+            Ok(round_count) if round_count > 0 => self.rounds = Some(round_count),
+            _ => self.rounds = None,
+        }
+    }
+    pub fn set_teams(&mut self, teams: String) {
+        match teams.parse::<usize>() {
+            Ok(team_count) if team_count > 0 => self.teams = team_count,
+            _ => self.teams = 1,
+        }
+    }
+    pub fn toggle_avoid_repeats_from(&mut self, id: u32) {
+        if !self.avoid_repeats_from.remove(&id) {
+            self.avoid_repeats_from.insert(id);
+        }
+    }
+    pub fn set_repeat_half_life(&mut self, days: String) {
+        match days.parse::<u32>() {
+            Ok(days) if days > 0 => self.repeat_half_life = Some(days),
+            _ => self.repeat_half_life = None,
+        }
+    }
+    pub fn set_table_balance(&mut self, table_balance: String) {
+        self.table_balance = table_balance.parse::<u64>().unwrap_or(0);
+    }
     pub fn back(&mut self, generate_schedule_model: &mut generate_schedule_page::GenerateSchedule) {
         generate_schedule_model.stop();
         self.stage = CreateEventStages::Details;
@@ -263,6 +571,7 @@ impl CreateEvent {
         generate_schedule_model: &generate_schedule_page::GenerateSchedule,
     ) -> String {
         match self.stage {
+            CreateEventStages::Details if self.editing.is_some() => "Change Event",
             CreateEventStages::Details => "Create Event",
             CreateEventStages::GenerateSchedule => {
                 if generate_schedule_model.found_ideal {
@@ -276,6 +585,65 @@ impl CreateEvent {
     }
 }
 
+fn view_avoid_repeats(
+    model: &CreateEvent,
+    database: &database::Database,
+    style: &style_control::StyleControl,
+) -> Node<Msg> {
+    let mut events: Vec<(&u32, &database::Event)> = database
+        .get_events()
+        .into_iter()
+        .filter(|&(&id, _)| Some(id) != model.editing)
+        .collect();
+    events.sort_by(|(_, event1), (_, event2)| event2.date.cmp(&event1.date));
+    div![
+        h3!["Avoid repeating pairings"],
+        p!["Players who were at a table together at the selected events are seated apart where possible"],
+        events
+            .into_iter()
+            .map(|(&id, event)| {
+                p![
+                    input![
+                        attrs! {At::Type => "checkbox"},
+                        if model.avoid_repeats_from.contains(&id) {
+                            attrs! {At::Checked => "checked"}
+                        } else {
+                            attrs! {}
+                        },
+                        simple_ev(Ev::Click, Msg::CEToggleAvoidRepeats(id))
+                    ],
+                    span![format!("{} ({})", event.name, event.date)]
+                ]
+            })
+            .collect::<Vec<Node<Msg>>>(),
+        span!["Older events count: "],
+        select![
+            style.button_style(),
+            input_ev(Ev::Input, Msg::CESetRepeatHalfLife),
+            [
+                (0, "The same as recent events"),
+                (7, "Half as much for every week"),
+                (28, "Half as much for every 4 weeks"),
+                (91, "Half as much for every 3 months"),
+            ]
+            .iter()
+            .map(|&(days, text)| {
+                option![
+                    style.option_style(),
+                    attrs! {At::Value => days},
+                    if model.repeat_half_life.unwrap_or(0) == days {
+                        attrs! {At::Selected => "selected"}
+                    } else {
+                        attrs! {}
+                    },
+                    text
+                ]
+            })
+            .collect::<Vec<Node<Msg>>>()
+        ],
+    ]
+}
+
 fn view_create_event_details(
     model: &CreateEvent,
     database: &database::Database,
@@ -292,6 +660,26 @@ St::FlexGrow=> "1";];
             &box_style,
             style![St::FlexGrow=> "1"; St::Width => "min-content"],
             h2!["Event Details"],
+            if let Some(id) = model.editing {
+                p![
+                    format!("Changing event {}, the new schedule will start from its saved schedule. ", id),
+                    if model.completed_rounds > 0 {
+                        format!(
+                            "Rounds 1 to {} have been played and are kept as they are, the rounds after them are re-planned. ",
+                            model.completed_rounds
+                        )
+                    } else {
+                        String::new()
+                    },
+                    button![
+                        style.button_style(),
+                        simple_ev(Ev::Click, Msg::CEStopEditing),
+                        "Create a new event instead"
+                    ]
+                ]
+            } else {
+                span![]
+            },
             table![
                 tr![
                     td!["Event Name: "],
@@ -329,10 +717,101 @@ St::FlexGrow=> "1";];
                             table_size_list
                         }
                     ]],
+                ],
+                tr![
+                    td!["Number of rounds: "],
+                    td![select![
+                        style.button_style(),
+                        input_ev(Ev::Input, Msg::CESetRounds),
+                        {
+                            let mut round_count_list: Vec<Node<Msg>> = Vec::with_capacity(32);
+                            round_count_list.push(option![
+                                style.option_style(),
+                                attrs! {At::Value => ""},
+                                "Same as number of board games"
+                            ]);
+                            for round_count in 1..32 {
+                                round_count_list.push(option![
+                                    style.option_style(),
+                                    attrs! {At::Value => round_count},
+                                    if Some(round_count) == model.rounds {
+                                        attrs! {At::Selected => "selected"}
+                                    } else {
+                                        attrs! {}
+                                    },
+                                    format!("{}", round_count)
+                                ]);
+                            }
+                            round_count_list
+                        }
+                    ]],
+                ],
+                tr![
+                    td!["Teams at each board game: "],
+                    td![select![
+                        style.button_style(),
+                        input_ev(Ev::Input, Msg::CESetTeams),
+                        {
+                            let mut team_count_list: Vec<Node<Msg>> = Vec::with_capacity(4);
+                            team_count_list.push(option![
+                                style.option_style(),
+                                attrs! {At::Value => 1},
+                                "Everyone plays against each other"
+                            ]);
+                            for team_count in 2..5 {
+                                team_count_list.push(option![
+                                    style.option_style(),
+                                    attrs! {At::Value => team_count},
+                                    if team_count == model.teams {
+                                        attrs! {At::Selected => "selected"}
+                                    } else {
+                                        attrs! {}
+                                    },
+                                    format!("{} teams", team_count)
+                                ]);
+                            }
+                            team_count_list
+                        }
+                    ]],
                 ]],
             p![
-                "The number of board games will also be the number of rounds, so \
-                each player will get an opportunity to play each of the games once"
+                "By default the number of rounds is the number of board games, so \
+                each player will get an opportunity to play each of the games once. \
+                With fewer rounds players won't get to play every game, and with more rounds some games will be played again"
+            ],
+            p![
+                "With teams, such as 2 teams for doubles, the players at each board game are split into teams. \
+                The schedule then tries to give everyone both new partners and new opponents"
+            ],
+            view_avoid_repeats(model, database, style),
+            h3!["Balance tables by skill"],
+            p![
+                "Players are rated from the results of previous events, and tables are given a similar average rating. \
+                Balancing more strongly means players are more likely to meet the same opponents or play the same games again. ",
+                select![
+                    style.button_style(),
+                    input_ev(Ev::Input, Msg::CESetTableBalance),
+                    [
+                        (0, "Ignore skill"),
+                        (1, "Balance a little"),
+                        (4, "Balance moderately"),
+                        (16, "Balance strongly"),
+                    ]
+                    .iter()
+                    .map(|&(table_balance, text)| {
+                        option![
+                            style.option_style(),
+                            attrs! {At::Value => table_balance},
+                            if model.table_balance == table_balance {
+                                attrs! {At::Selected => "selected"}
+                            } else {
+                                attrs! {}
+                            },
+                            text
+                        ]
+                    })
+                    .collect::<Vec<Node<Msg>>>()
+                ]
             ],
             p![
                 "Steps:",
@@ -364,16 +843,14 @@ St::FlexGrow=> "1";];
                     li![
                         "Add the players. Players can be added to the database via the Manage Players page. ",
                         span![
-                            if model.players.len() <= 64 && (if let Some(tables) = model.tables {model.players.len() >= tables * 2} else {true}){
+                            if if let Some(tables) = model.tables {model.players.len() >= tables * 2} else {true} {
                                 style![St::Color => "green"]
                             } else {
                                 style![St::Color => "red"]
                             },
                             model.players.len().to_string(),
                             " players",
-                            if model.players.len() > 64 {
-                                " - Above maximum of 64"
-                            } else if if let Some(tables) = model.tables {
+                            if if let Some(tables) = model.tables {
                                 model.players.len() < tables * 2
                             } else {
                                 false
@@ -0,0 +1,351 @@
+use history::MEETING_WEIGHT;
+use std::sync::Arc;
+
+const UNIQUE_GAMES_MULTIPLIER: u64 = 4;
+/* Results in the program favouring schedules with higher total unique games played, during
+testing this seemed to result in overall better (higher total unique games played and higher total unique opponents) generated schedules.
+*/
+/** How many rating points a game is from average strength for it to cost as much as one fewer unique opponent, at a table balance weight of 1*/
+const RATING_POINTS_PER_OPPONENT: u64 = 100;
+
+/** Cached measurements of a schedule which an objective turns into a score.
+All of these are kept up to date incrementally by Schedule, so scoring is cheap enough to do for every evaluated move */
+pub struct Metrics<'a> {
+    pub player_count: usize,
+    /**Sum of how many unique opponents each player has*/
+    pub unique_opponents: u32,
+    /**Lowest number of unique opponents of any player*/
+    pub min_unique_opponents: u32,
+    /**Total unique games played*/
+    pub unique_games_played: u32,
+    /**Calculated max possible total unique opponents*/
+    pub ideal_unique_opponents: u32,
+    /**Calculated max possible total unique games played*/
+    pub ideal_unique_games: u32,
+    /**Sum of how many unique partners each player has, 0 when players don't play in teams*/
+    pub unique_partners: u32,
+    /**Calculated max possible total unique partners, 0 when players don't play in teams*/
+    pub ideal_unique_partners: u32,
+    /**Sum of how much each player met their opponents at previous events, 0 when there is no pair history*/
+    pub repeated_pairs: u32,
+    /**Sum of how far the total rating of the players in each game is from average, 0 when there are no ratings*/
+    pub table_imbalance: u32,
+    /**How many unique opponents each player has, indexed by player*/
+    pub player_opponents: &'a [u16],
+    /**How many players are at the same table in the same round as in the schedule this one was started from, 0 if there is none*/
+    pub unchanged_positions: u32,
+}
+
+/** Turns the metrics of a schedule into a score, where a higher score is a better schedule */
+pub trait Objective: Send + Sync {
+    fn score(&self, metrics: &Metrics) -> u64;
+}
+
+/** Weighted sum of the unique opponents, minimum unique opponents, unique games played and unique partners,
+minus weighted penalties for seating players together who met at previous events and for tables of uneven strength.
+Each term is first scaled so that they are comparable to each other regardless of player and table counts,
+a pair meeting again straight after a previous event costs as much as one fewer unique opponent for each of them */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Weighted {
+    pub unique_opponents: u64,
+    pub min_unique_opponents: u64,
+    pub unique_games_played: u64,
+    pub unique_partners: u64,
+    pub repeated_pairs: u64,
+    pub table_balance: u64,
+}
+
+impl Default for Weighted {
+    fn default() -> Self {
+        Self {
+            unique_opponents: 1,
+            min_unique_opponents: 1,
+            unique_games_played: UNIQUE_GAMES_MULTIPLIER,
+            unique_partners: 1,
+            repeated_pairs: 1,
+            table_balance: 1,
+        }
+    }
+}
+
+impl Objective for Weighted {
+    fn score(&self, metrics: &Metrics) -> u64 {
+        let ideal_unique_games = u64::from(metrics.ideal_unique_games);
+        (u64::from(metrics.unique_opponents) * ideal_unique_games * self.unique_opponents
+            + u64::from(metrics.min_unique_opponents)
+                * ideal_unique_games
+                * (metrics.player_count as u64)
+                * self.min_unique_opponents
+            + u64::from(metrics.unique_games_played)
+                * u64::from(metrics.ideal_unique_opponents)
+                * self.unique_games_played
+            + u64::from(metrics.unique_partners) * ideal_unique_games * self.unique_partners)
+            .saturating_sub(
+                u64::from(metrics.repeated_pairs) * ideal_unique_games * self.repeated_pairs
+                    / u64::from(MEETING_WEIGHT)
+                    + u64::from(metrics.table_imbalance) * ideal_unique_games * self.table_balance
+                        / RATING_POINTS_PER_OPPONENT,
+            )
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Metric {
+    UniqueOpponents,
+    MinUniqueOpponents,
+    UniqueGamesPlayed,
+}
+
+impl Metric {
+    fn get(self, metrics: &Metrics) -> u64 {
+        u64::from(match self {
+            Metric::UniqueOpponents => metrics.unique_opponents,
+            Metric::MinUniqueOpponents => metrics.min_unique_opponents,
+            Metric::UniqueGamesPlayed => metrics.unique_games_played,
+        })
+    }
+
+    /** Upper bound on the value of the metric, used to pack several metrics into one score*/
+    fn bound(self, metrics: &Metrics) -> u64 {
+        u64::from(match self {
+            Metric::UniqueOpponents => metrics.ideal_unique_opponents,
+            Metric::MinUniqueOpponents => metrics.player_count.saturating_sub(1) as u32,
+            Metric::UniqueGamesPlayed => metrics.ideal_unique_games,
+        })
+    }
+}
+
+/** Only compares a later metric when every earlier metric is equal */
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lexicographic(pub Vec<Metric>);
+
+impl Default for Lexicographic {
+    fn default() -> Self {
+        Lexicographic(vec![
+            Metric::MinUniqueOpponents,
+            Metric::UniqueOpponents,
+            Metric::UniqueGamesPlayed,
+        ])
+    }
+}
+
+impl Objective for Lexicographic {
+    fn score(&self, metrics: &Metrics) -> u64 {
+        self.0.iter().fold(0, |score, metric| {
+            score * (metric.bound(metrics) + 1) + metric.get(metrics).min(metric.bound(metrics))
+        })
+    }
+}
+
+/** Only cares about the worst-off player. Schedules with the same minimum are ranked by how few players are at that minimum */
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct MaxMin;
+
+impl Objective for MaxMin {
+    fn score(&self, metrics: &Metrics) -> u64 {
+        let player_count = metrics.player_count as u64;
+        let min = metrics.min_unique_opponents as u16;
+        let at_min = metrics
+            .player_opponents
+            .iter()
+            .filter(|&&opponents| opponents == min)
+            .count() as u64;
+        u64::from(min) * (player_count + 1) + (player_count - at_min)
+    }
+}
+
+/** The default weighted sum, minus a penalty for how unevenly unique opponents are spread between players */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct VariancePenalised {
+    pub weights: Weighted,
+    pub penalty: u64,
+}
+
+impl Objective for VariancePenalised {
+    fn score(&self, metrics: &Metrics) -> u64 {
+        let player_count = metrics.player_count as u64;
+        let sum = u64::from(metrics.unique_opponents);
+        let sum_of_squares: u64 = metrics
+            .player_opponents
+            .iter()
+            .map(|&opponents| u64::from(opponents) * u64::from(opponents))
+            .sum();
+        // player_count * variance, kept as an integer
+        let spread =
+            (player_count * sum_of_squares).saturating_sub(sum * sum) / player_count.max(1);
+        // Largest possible spread, so the score never goes below zero
+        let max_spread = player_count * player_count.saturating_sub(1).pow(2);
+        self.weights.score(metrics) + self.penalty * max_spread.saturating_sub(spread)
+    }
+}
+
+/** The default weighted sum, plus a reward for every player who is at the same table in the same round as in the original schedule.
+A weight of 1 makes keeping one player's seat worth as much as one more unique opponent */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MinimiseChanges {
+    pub weights: Weighted,
+    pub weight: u64,
+}
+
+impl Objective for MinimiseChanges {
+    fn score(&self, metrics: &Metrics) -> u64 {
+        self.weights.score(metrics)
+            + u64::from(metrics.unchanged_positions)
+                * u64::from(metrics.ideal_unique_games)
+                * self.weight
+    }
+}
+
+/** Parse an objective from the command line. Accepts
+`default`, `weighted:<unique opponents>,<min unique opponents>,<unique games played>[,<unique partners>[,<repeated pairs>[,<table balance>]]]`,
+`lexicographic`, `max-min`, `variance:<penalty>`, and `stable:<weight>`*/
+pub fn parse(text: &str) -> Result<Arc<dyn Objective>, String> {
+    let mut parts = text.splitn(2, ':');
+    let name = parts.next().unwrap_or("");
+    let args = parts.next();
+    let numbers = || -> Result<Vec<u64>, String> {
+        args.unwrap_or("")
+            .split(',')
+            .map(|number| {
+                number
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| format!("Invalid number '{}' in objective '{}'", number, text))
+            })
+            .collect()
+    };
+    match name {
+        "default" => Ok(Arc::new(Weighted::default())),
+        "weighted" => match *numbers()?.as_slice() {
+            [unique_opponents, min_unique_opponents, unique_games_played, ref optional @ ..]
+                if optional.len() <= 3 =>
+            {
+                Ok(Arc::new(Weighted {
+                    unique_opponents,
+                    min_unique_opponents,
+                    unique_games_played,
+                    unique_partners: optional.first().copied().unwrap_or(1),
+                    repeated_pairs: optional.get(1).copied().unwrap_or(1),
+                    table_balance: optional.get(2).copied().unwrap_or(1),
+                }))
+            }
+            _ => Err(format!("Objective '{}' needs 3 to 6 weights", text)),
+        },
+        "lexicographic" => Ok(Arc::new(Lexicographic::default())),
+        "max-min" => Ok(Arc::new(MaxMin)),
+        "variance" => match numbers()?.as_slice() {
+            &[penalty] => Ok(Arc::new(VariancePenalised {
+                weights: Weighted::default(),
+                penalty,
+            })),
+            _ => Err(format!("Objective '{}' needs a penalty", text)),
+        },
+        "stable" => match numbers()?.as_slice() {
+            &[weight] => Ok(Arc::new(MinimiseChanges {
+                weights: Weighted::default(),
+                weight,
+            })),
+            _ => Err(format!("Objective '{}' needs a weight", text)),
+        },
+        _ => Err(format!("Unknown objective '{}'", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metrics(player_opponents: &[u16]) -> Metrics<'_> {
+        Metrics {
+            player_count: player_opponents.len(),
+            unique_opponents: player_opponents.iter().map(|&count| u32::from(count)).sum(),
+            min_unique_opponents: u32::from(*player_opponents.iter().min().unwrap()),
+            unique_games_played: 10,
+            ideal_unique_opponents: 24,
+            ideal_unique_games: 12,
+            unique_partners: 0,
+            ideal_unique_partners: 0,
+            repeated_pairs: 0,
+            table_imbalance: 0,
+            player_opponents,
+            unchanged_positions: 5,
+        }
+    }
+
+    #[test]
+    fn default_matches_original_formula() {
+        let metrics = metrics(&[3, 4, 4, 5, 2, 3]);
+        assert_eq!(
+            Weighted::default().score(&metrics),
+            21 * 12 + 2 * 12 * 6 + 10 * 24 * 4
+        );
+    }
+
+    #[test]
+    fn lexicographic_prefers_higher_minimum() {
+        let objective = Lexicographic::default();
+        assert!(
+            objective.score(&metrics(&[3, 3, 3, 3, 3, 3]))
+                > objective.score(&metrics(&[2, 5, 5, 5, 5, 5]))
+        );
+    }
+
+    #[test]
+    fn max_min_prefers_fewer_players_at_minimum() {
+        let objective = MaxMin;
+        assert!(
+            objective.score(&metrics(&[2, 3, 3, 3])) > objective.score(&metrics(&[2, 2, 5, 5]))
+        );
+        assert!(
+            objective.score(&metrics(&[3, 3, 3, 3])) > objective.score(&metrics(&[2, 5, 5, 5]))
+        );
+    }
+
+    #[test]
+    fn variance_penalises_uneven_spread() {
+        let objective = VariancePenalised {
+            weights: Weighted::default(),
+            penalty: 1000,
+        };
+        assert!(
+            objective.score(&metrics(&[3, 3, 3, 3])) > objective.score(&metrics(&[3, 1, 3, 5]))
+        );
+    }
+
+    #[test]
+    fn parses_objectives() {
+        let metrics = metrics(&[3, 4, 4, 5, 2, 3]);
+        assert_eq!(parse("weighted:1,0,0").unwrap().score(&metrics), 21 * 12);
+        assert!(parse("variance:5").is_ok());
+        assert!(parse("max-min").is_ok());
+        assert_eq!(
+            parse("stable:2").unwrap().score(&metrics),
+            Weighted::default().score(&metrics) + 5 * 12 * 2
+        );
+        let teams = Metrics {
+            unique_partners: 7,
+            ideal_unique_partners: 8,
+            ..metrics
+        };
+        assert_eq!(parse("weighted:0,0,0,3").unwrap().score(&teams), 7 * 12 * 3);
+        let repeats = Metrics {
+            repeated_pairs: 2 * MEETING_WEIGHT,
+            ..metrics
+        };
+        assert_eq!(
+            parse("weighted:1,0,0,0,3").unwrap().score(&repeats),
+            21 * 12 - 2 * 12 * 3
+        );
+        assert!(parse("weighted:1,2").is_err());
+        let uneven = Metrics {
+            table_imbalance: 300,
+            ..metrics
+        };
+        assert_eq!(
+            parse("weighted:1,0,0,0,0,2").unwrap().score(&uneven),
+            21 * 12 - 3 * 12 * 2
+        );
+        assert!(parse("weighted:1,2,3,4,5,6,7").is_err());
+        assert!(parse("unknown").is_err());
+    }
+}
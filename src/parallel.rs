@@ -0,0 +1,288 @@
+use run::{RunEvent, RunStats, StopCriteria, StopReason, Tracker};
+use schedule::{self, Generator, Schedule};
+use strategy::{Restart, Strategy};
+
+/** Derive one rng per worker from a single master seed, so that a run can be repeated exactly */
+pub fn worker_rngs<T: rand::SeedableRng + rand_core::RngCore>(seed: u64, workers: usize) -> Vec<T> {
+    let mut master = T::seed_from_u64(seed);
+    (0..workers)
+        .map(|_| T::from_rng(&mut master).expect("Seeding worker rng from master rng failed"))
+        .collect()
+}
+
+/** How the generators, called islands, cooperate. Every interval epochs each island is sent the best schedule of the previous island in a ring.
+Each island keeps up to elites of the best schedules it has found or been sent,
+and restarts from one of them changed by strength random single-step changes instead of from a random schedule */
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Migration {
+    pub interval: u64,
+    pub elites: usize,
+    pub strength: usize,
+}
+
+impl Default for Migration {
+    fn default() -> Self {
+        Self {
+            interval: 10,
+            elites: 4,
+            strength: 3,
+        }
+    }
+}
+
+/** Runs several Generators on their own threads in lock-step epochs.
+Every generator does the same number of steps each epoch, and the best schedules are merged in worker order once they have all finished,
+so the result only depends on the generators' rngs and the number of epochs, not on thread timing */
+pub struct Parallel<T: rand::Rng + rand_core::RngCore> {
+    generators: Vec<Generator<T>>,
+    /**Number of times each generator is processed per epoch*/
+    epoch_length: usize,
+    /**Number of completed epochs*/
+    epochs: u64,
+    /**The best schedule found by any generator*/
+    pub best: Schedule,
+    /**The score of the best schedule found by any generator*/
+    pub best_score: u64,
+    /**How the generators share schedules, if at all*/
+    migration: Option<Migration>,
+    /**The best schedules each island has found or been sent, with their scores, best first*/
+    elites: Vec<Vec<(u64, Schedule)>>,
+}
+
+impl<T: rand::Rng + rand_core::RngCore + Send> Parallel<T> {
+    /**Create from a non-empty list of generators, which should all be for the same event. Panics if generators is empty*/
+    pub fn new(generators: Vec<Generator<T>>, epoch_length: usize) -> Self {
+        assert!(!generators.is_empty());
+        let mut parallel = Self {
+            best: generators[0].best.clone(),
+            best_score: generators[0].best_score,
+            generators,
+            epoch_length,
+            epochs: 0,
+            migration: None,
+            elites: Vec::new(),
+        };
+        parallel.merge();
+        parallel
+    }
+
+    /** Get the generators, in worker order*/
+    pub fn get_generators(&self) -> &[Generator<T>] {
+        &self.generators
+    }
+
+    /** Change the local search method of every generator, each continuing from its current schedule*/
+    pub fn set_strategy<F: Fn() -> Box<dyn Strategy>>(&mut self, strategy: F) {
+        for generator in self.generators.iter_mut() {
+            generator.set_strategy(strategy());
+        }
+    }
+
+    /** Make the generators cooperate as islands. Without this they run independently, restarting from random schedules*/
+    pub fn set_migration(&mut self, migration: Migration) {
+        self.migration = Some(migration);
+        self.elites = vec![Vec::new(); self.generators.len()];
+    }
+
+    /** Average distance of each island's best schedule from the overall best schedule, a measure of how differently the islands are searching*/
+    pub fn diversity(&self) -> f64 {
+        self.generators
+            .iter()
+            .map(|generator| f64::from(generator.best.distance(&self.best)))
+            .sum::<f64>()
+            / self.generators.len() as f64
+    }
+
+    /** Get the number of completed epochs*/
+    pub fn get_epochs(&self) -> u64 {
+        self.epochs
+    }
+
+    /**Process every generator for one epoch, each on its own thread, then merge their best schedules.
+    Returns (number of evaluated schedules, number of random starts) summed over every generator*/
+    pub fn run_epoch(&mut self) -> (u64, u64) {
+        let (evaluations, random_starts, _) = self.epoch();
+        (evaluations, random_starts)
+    }
+
+    /**Keep running epochs until one of the criteria is reached, only stopping between epochs. clock gives the time in milliseconds, such as run::std_clock().
+    observer is told about every better schedule found, and about progress every criteria.progress_interval milliseconds.
+    Returns why the run stopped and the work done*/
+    pub fn run_until<O: FnMut(RunEvent)>(
+        &mut self,
+        criteria: &StopCriteria,
+        clock: &dyn Fn() -> f64,
+        mut observer: O,
+    ) -> (StopReason, RunStats) {
+        let mut tracker = Tracker::new(criteria, clock);
+        loop {
+            if let Some(reason) = tracker.stop_reason(&self.best, self.best_score) {
+                return (reason, tracker.stats());
+            }
+            let (evaluations, random_starts, improved) = self.epoch();
+            tracker.record(evaluations, random_starts);
+            if improved {
+                observer(RunEvent::NewBest {
+                    best: &self.best,
+                    score: self.best_score,
+                    stats: tracker.improved(),
+                });
+            }
+            if let Some(stats) = tracker.progress() {
+                observer(RunEvent::Progress {
+                    best: &self.best,
+                    score: self.best_score,
+                    stats,
+                    diversity: self.migration.map(|_| self.diversity()),
+                });
+            }
+        }
+    }
+
+    /**Same as run_epoch, but also returns whether a better schedule was found*/
+    fn epoch(&mut self) -> (u64, u64, bool) {
+        let epoch_length = self.epoch_length;
+        let totals: Vec<(u64, u64)> = std::thread::scope(|scope| {
+            let handles: Vec<_> = self
+                .generators
+                .iter_mut()
+                .map(|generator| {
+                    scope.spawn(move || {
+                        let mut operations: u64 = 0;
+                        let mut random_starts: u64 = 0;
+                        for _ in 0..epoch_length {
+                            let (ops, rs) = generator.process();
+                            operations += u64::from(ops);
+                            random_starts += u64::from(rs);
+                        }
+                        (operations, random_starts)
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().expect("Generator thread panicked"))
+                .collect()
+        });
+        self.epochs += 1;
+        let improved = self.merge();
+        if let Some(migration) = self.migration {
+            if self.epochs.is_multiple_of(migration.interval.max(1)) {
+                self.migrate(migration);
+            }
+        }
+        let (evaluations, random_starts) = totals
+            .into_iter()
+            .fold((0, 0), |(ops, rs), (o, r)| (ops + o, rs + r));
+        (evaluations, random_starts, improved)
+    }
+
+    /**Send each island the best schedule of the previous island, and let islands restart from their elites*/
+    fn migrate(&mut self, migration: Migration) {
+        let island_count = self.generators.len();
+        for island in 0..island_count {
+            let neighbour = &self.generators[(island + island_count - 1) % island_count];
+            let own = &self.generators[island];
+            let elites = &mut self.elites[island];
+            for &(score, schedule) in &[
+                (own.best_score, &own.best),
+                (neighbour.best_score, &neighbour.best),
+            ] {
+                if elites.iter().all(|(elite_score, elite)| {
+                    *elite_score != score || elite.distance(schedule) != 0
+                }) {
+                    elites.push((score, schedule.clone()));
+                }
+            }
+            // Stable sort, so ties stay in the order they were added
+            elites.sort_by(|(score1, _), (score2, _)| score2.cmp(score1));
+            elites.truncate(migration.elites.max(1));
+        }
+        for (generator, elites) in self.generators.iter_mut().zip(self.elites.iter()) {
+            generator.set_restart(Restart::Perturb {
+                elites: elites
+                    .iter()
+                    .map(|(_, schedule)| schedule.clone())
+                    .collect(),
+                strength: migration.strength,
+            });
+        }
+    }
+
+    /**Keep the best schedule of any generator, checking them in worker order so ties are resolved the same way every run.
+    Returns whether the best schedule changed*/
+    fn merge(&mut self) -> bool {
+        let mut improved = false;
+        for generator in &self.generators {
+            if schedule::is_better(
+                generator.best_score,
+                &generator.best,
+                self.best_score,
+                &self.best,
+            ) {
+                self.best_score = generator.best_score;
+                self.best = generator.best.clone();
+                improved = true;
+            }
+        }
+        improved
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(seed: u64, workers: usize, epochs: usize) -> Vec<Vec<usize>> {
+        let generators = worker_rngs::<rand_xorshift::XorShiftRng>(seed, workers)
+            .into_iter()
+            .map(|rng| Generator::new(rng, 17, 4, 5))
+            .collect();
+        let mut parallel = Parallel::new(generators, 50);
+        for _ in 0..epochs {
+            parallel.run_epoch();
+        }
+        (0..5)
+            .flat_map(|round| (0..4).map(move |table| (round, table)))
+            .map(|(round, table)| parallel.best.get_players_from_game(round, table))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_and_workers_gives_same_schedule() {
+        assert_eq!(run(7, 3, 20), run(7, 3, 20));
+    }
+
+    #[test]
+    fn islands_are_deterministic_and_keep_best() {
+        let run = || {
+            let generators = worker_rngs::<rand_xorshift::XorShiftRng>(11, 3)
+                .into_iter()
+                .map(|rng| Generator::new(rng, 14, 3, 4))
+                .collect();
+            let mut parallel = Parallel::new(generators, 50);
+            parallel.set_migration(Migration {
+                interval: 2,
+                elites: 2,
+                strength: 2,
+            });
+            let mut best_score = parallel.best_score;
+            for _ in 0..20 {
+                parallel.run_epoch();
+                assert!(parallel.best_score >= best_score);
+                assert_eq!(parallel.best_score, parallel.best.clone().generate_score());
+                best_score = parallel.best_score;
+            }
+            assert!(parallel.elites.iter().all(|elites| elites.len() <= 2));
+            (parallel.best_score, parallel.diversity())
+        };
+        assert_eq!(run(), run());
+    }
+
+    #[test]
+    fn workers_get_different_rngs() {
+        use rand::RngCore;
+        let mut rngs = worker_rngs::<rand_xorshift::XorShiftRng>(7, 2);
+        assert_ne!(rngs[0].next_u64(), rngs[1].next_u64());
+    }
+}
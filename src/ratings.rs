@@ -0,0 +1,63 @@
+/** Rating of a player before they have any results*/
+pub const INITIAL_RATING: f64 = 1500.0;
+/** How far a single comparison between two players can move their ratings*/
+const K_FACTOR: f64 = 32.0;
+
+/** Elo ratings from match results in the order they were played, indexed by player.
+Each result is the players at a table with their scores, and every player is compared with everyone else at the table,
+with a higher score winning and an equal score drawing. The change from a table is split between the comparisons, so bigger tables don't move ratings further*/
+pub fn elo_ratings(player_count: usize, results: &[Vec<(usize, usize)>]) -> Vec<f64> {
+    let mut ratings = vec![INITIAL_RATING; player_count];
+    for result in results {
+        if result.len() < 2 {
+            continue;
+        }
+        let k = K_FACTOR / (result.len() - 1) as f64;
+        let changes: Vec<f64> = result
+            .iter()
+            .map(|&(player, score)| {
+                result
+                    .iter()
+                    .filter(|&&(other, _)| other != player)
+                    .map(|&(other, other_score)| {
+                        let expected =
+                            1.0 / (1.0 + 10_f64.powf((ratings[other] - ratings[player]) / 400.0));
+                        let actual = match score.cmp(&other_score) {
+                            std::cmp::Ordering::Greater => 1.0,
+                            std::cmp::Ordering::Equal => 0.5,
+                            std::cmp::Ordering::Less => 0.0,
+                        };
+                        k * (actual - expected)
+                    })
+                    .sum()
+            })
+            .collect();
+        for (&(player, _), change) in result.iter().zip(changes) {
+            ratings[player] += change;
+        }
+    }
+    ratings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn winners_gain_what_losers_lose() {
+        let ratings = elo_ratings(4, &[vec![(0, 10), (1, 5), (2, 5)], vec![(3, 1)]]);
+        assert!(ratings[0] > INITIAL_RATING);
+        assert!(ratings[1] < INITIAL_RATING);
+        assert!((ratings[1] - ratings[2]).abs() < 1e-9);
+        assert!((ratings[3] - INITIAL_RATING).abs() < 1e-9);
+        assert!((ratings.iter().sum::<f64>() - 4.0 * INITIAL_RATING).abs() < 1e-9);
+    }
+
+    #[test]
+    fn repeated_wins_keep_raising_rating() {
+        let once = elo_ratings(2, &[vec![(0, 2), (1, 1)]]);
+        let twice = elo_ratings(2, &[vec![(0, 2), (1, 1)], vec![(0, 2), (1, 1)]]);
+        assert!(twice[0] > once[0]);
+        assert!(twice[0] - once[0] < once[0] - INITIAL_RATING);
+    }
+}
@@ -0,0 +1,277 @@
+use ratings::INITIAL_RATING;
+use schedule::Constraints;
+use std::collections::BTreeMap;
+
+/** Someone who may play at an event*/
+#[derive(Clone, Debug, PartialEq)]
+pub struct Member {
+    pub name: String,
+    /**Whether they play at this event. Members who don't aren't given a player number*/
+    pub available: bool,
+    /**The table they must sit at in every round, counting from 0*/
+    pub table: Option<usize>,
+    /**Names of the members they must never share a table with*/
+    pub avoid: Vec<String>,
+    pub rating: Option<f64>,
+    /**Every other column of a CSV roster, by its heading*/
+    pub attributes: BTreeMap<String, String>,
+}
+
+impl Member {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            available: true,
+            table: None,
+            avoid: Vec::new(),
+            rating: None,
+            attributes: BTreeMap::new(),
+        }
+    }
+}
+
+/** The members who may play at an event, so schedules can be made without the web app's database.
+Available members are numbered from 0 in the order they are listed, and those are their player numbers in schedules*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Roster {
+    pub members: Vec<Member>,
+}
+
+/** Split a line of CSV into fields, where fields in double quotes can hold commas and "" is a quote*/
+fn csv_fields(line: &str) -> Vec<String> {
+    let mut fields = vec![String::new()];
+    let mut quoted = false;
+    let mut characters = line.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '"' if quoted && characters.peek() == Some(&'"') => {
+                characters.next();
+                fields.last_mut().unwrap().push('"');
+            }
+            '"' => quoted = !quoted,
+            ',' if !quoted => fields.push(String::new()),
+            character => fields.last_mut().unwrap().push(character),
+        }
+    }
+    fields
+        .into_iter()
+        .map(|field| field.trim().to_string())
+        .collect()
+}
+
+fn parse_available(text: &str) -> Result<bool, String> {
+    match text.to_lowercase().as_str() {
+        "" | "yes" | "y" | "true" | "1" => Ok(true),
+        "no" | "n" | "false" | "0" => Ok(false),
+        _ => Err(format!("Expected yes or no for available, got '{}'", text)),
+    }
+}
+
+impl Roster {
+    /** Parse a roster, which is either the name of each member on its own line, or CSV with a header row.
+    The CSV needs a name column, and can have an available column of yes or no, a table column with the table the member must sit at counting from 1,
+    an avoid column with the names of members they must never share a table with separated by semicolons, and a rating column.
+    Any other columns are kept as attributes. Blank lines are ignored*/
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut lines = text
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+        let header = match lines.next() {
+            Some((_, line)) => csv_fields(line),
+            None => return Ok(Self::default()),
+        };
+        let column = |heading: &str| {
+            header
+                .iter()
+                .position(|field| field.eq_ignore_ascii_case(heading))
+        };
+        let name_column = match column("name") {
+            Some(name_column) => name_column,
+            None => {
+                return Ok(Self {
+                    members: text
+                        .lines()
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(|name| Member::new(name.to_string()))
+                        .collect(),
+                })
+            }
+        };
+        let mut members: Vec<Member> = Vec::new();
+        for (line_number, line) in lines {
+            let fields = csv_fields(line);
+            let field = |heading: &str| {
+                column(heading)
+                    .and_then(|index| fields.get(index))
+                    .map_or("", String::as_str)
+            };
+            let error = |error: String| format!("Line {}: {}", line_number + 1, error);
+            let name = fields.get(name_column).cloned().unwrap_or_default();
+            if name.is_empty() {
+                return Err(error("Every member needs a name".to_string()));
+            }
+            if members.iter().any(|member| member.name == name) {
+                return Err(error(format!("{} is listed twice", name)));
+            }
+            let table = match field("table") {
+                "" => None,
+                table => match table.parse::<usize>() {
+                    Ok(table) if table > 0 => Some(table - 1),
+                    _ => {
+                        return Err(error(format!(
+                            "Expected a table number from 1, got '{}'",
+                            table
+                        )))
+                    }
+                },
+            };
+            let rating = match field("rating") {
+                "" => None,
+                rating => Some(rating.parse::<f64>().map_err(|parse_error| {
+                    error(format!("Invalid rating '{}': {}", rating, parse_error))
+                })?),
+            };
+            members.push(Member {
+                available: parse_available(field("available")).map_err(error)?,
+                table,
+                avoid: field("avoid")
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|other| !other.is_empty())
+                    .map(str::to_string)
+                    .collect(),
+                rating,
+                attributes: header
+                    .iter()
+                    .zip(fields.iter())
+                    .filter(|(heading, _)| {
+                        !["name", "available", "table", "avoid", "rating"]
+                            .iter()
+                            .any(|known| heading.eq_ignore_ascii_case(known))
+                    })
+                    .map(|(heading, value)| (heading.clone(), value.clone()))
+                    .collect(),
+                name,
+            });
+        }
+        Ok(Self { members })
+    }
+
+    /** The members playing at this event, in player number order*/
+    pub fn players(&self) -> Vec<&Member> {
+        self.members
+            .iter()
+            .filter(|member| member.available)
+            .collect()
+    }
+
+    /** Names of the members playing at this event, indexed by player number*/
+    pub fn names(&self) -> Vec<String> {
+        self.players()
+            .iter()
+            .map(|member| member.name.clone())
+            .collect()
+    }
+
+    /** Ratings of the players indexed by player number, with INITIAL_RATING for players without one. None if nobody has a rating*/
+    pub fn ratings(&self) -> Option<Vec<f64>> {
+        let players = self.players();
+        if players.iter().all(|member| member.rating.is_none()) {
+            return None;
+        }
+        Some(
+            players
+                .iter()
+                .map(|member| member.rating.unwrap_or(INITIAL_RATING))
+                .collect(),
+        )
+    }
+
+    /** The tables players are pinned to and the pairs who must be kept apart, for a schedule with the number of tables.
+    Pairs with a member who isn't playing are ignored, but names nobody on the roster has and tables which don't exist are errors*/
+    pub fn constraints(&self, tables: usize) -> Result<Constraints, String> {
+        let players = self.players();
+        let mut constraints = Constraints::default();
+        for (player, member) in players.iter().enumerate() {
+            if let Some(table) = member.table {
+                if table >= tables {
+                    return Err(format!(
+                        "{} is pinned to table {}, but there are only {} tables",
+                        member.name,
+                        table + 1,
+                        tables
+                    ));
+                }
+            }
+            constraints.pinned.push(member.table);
+            for other in member.avoid.iter() {
+                if !self.members.iter().any(|member| &member.name == other) {
+                    return Err(format!(
+                        "{} avoids {}, who isn't on the roster",
+                        member.name, other
+                    ));
+                }
+                if let Some(other_player) = players.iter().position(|member| &member.name == other)
+                {
+                    if other_player != player {
+                        constraints.forbidden.push((player, other_player));
+                    }
+                }
+            }
+        }
+        if constraints.pinned.iter().all(Option::is_none) {
+            constraints.pinned.clear();
+        }
+        Ok(constraints)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_lists_are_names() {
+        let roster = Roster::parse("Ann\n\n  Bo \nCy, Jr\n").unwrap();
+        assert_eq!(roster.names(), vec!["Ann", "Bo", "Cy, Jr"]);
+        assert_eq!(roster.ratings(), None);
+        assert!(roster.constraints(2).unwrap().is_empty());
+    }
+
+    #[test]
+    fn csv_rosters_have_constraints_and_attributes() {
+        let roster = Roster::parse(concat!(
+            "Name,Available,Table,Avoid,Rating,Club\n",
+            "Ann,yes,2,Bo;Dee,1600,North\n",
+            "Bo,,,,,South\n",
+            "\"Cy, Jr\",no,1,Ann,,\n",
+            "Dee,1,,,1400,\"The \"\"East\"\"\"\n",
+        ))
+        .unwrap();
+        assert_eq!(roster.members.len(), 4);
+        assert_eq!(roster.names(), vec!["Ann", "Bo", "Dee"]);
+        assert_eq!(roster.members[2].name, "Cy, Jr");
+        assert_eq!(roster.members[3].attributes["Club"], "The \"East\"");
+        assert_eq!(roster.ratings(), Some(vec![1600.0, INITIAL_RATING, 1400.0]));
+        assert_eq!(
+            roster.constraints(2),
+            Ok(Constraints {
+                pinned: vec![Some(1), None, None],
+                forbidden: vec![(0, 1), (0, 2)],
+            })
+        );
+        assert!(roster.constraints(1).is_err());
+    }
+
+    #[test]
+    fn invalid_rosters_are_rejected() {
+        assert!(Roster::parse("name,table\nAnn,0\n").is_err());
+        assert!(Roster::parse("name,available\nAnn,maybe\n").is_err());
+        assert!(Roster::parse("name\nAnn\nAnn\n").is_err());
+        assert!(Roster::parse("name,rating\nAnn,high\n").is_err());
+        let roster = Roster::parse("name,avoid\nAnn,Zed\n").unwrap();
+        assert!(roster.constraints(2).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+use schedule::Schedule;
+
+/** When Generator::run_until and Parallel::run_until stop. Every limit that is set applies, and the run stops at the first one reached.
+With no limits set the run only stops at an ideal schedule, if stop_at_ideal is set*/
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct StopCriteria {
+    /**Stop after this many milliseconds*/
+    pub time_limit: Option<f64>,
+    /**Stop after evaluating this many schedules*/
+    pub max_evaluations: Option<u64>,
+    /**Stop once the best score is at least this*/
+    pub target_score: Option<u64>,
+    /**Stop after evaluating this many schedules without finding a better one*/
+    pub stagnation: Option<u64>,
+    /**Stop once the best schedule reaches the ideal scores, or its score reaches Schedule::score_bound, since nothing better is possible*/
+    pub stop_at_ideal: bool,
+    /**How often the observer is sent RunEvent::Progress, in milliseconds. None means never*/
+    pub progress_interval: Option<f64>,
+}
+
+/** Which of the StopCriteria ended a run*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum StopReason {
+    Ideal,
+    TargetScore,
+    TimeLimit,
+    EvaluationBudget,
+    Stagnation,
+}
+
+impl std::fmt::Display for StopReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            StopReason::Ideal => "found an ideal schedule",
+            StopReason::TargetScore => "reached the target score",
+            StopReason::TimeLimit => "reached the time limit",
+            StopReason::EvaluationBudget => "evaluated the maximum number of schedules",
+            StopReason::Stagnation => "stopped finding better schedules",
+        })
+    }
+}
+
+/** The work done so far in a run*/
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct RunStats {
+    pub evaluations: u64,
+    pub random_starts: u64,
+    /**Milliseconds since the run started*/
+    pub elapsed: f64,
+}
+
+/** What an observer of a run is told about*/
+pub enum RunEvent<'a> {
+    /**A better schedule was found*/
+    NewBest {
+        best: &'a Schedule,
+        score: u64,
+        stats: RunStats,
+    },
+    /**Sent every StopCriteria::progress_interval milliseconds.
+    diversity is how differently the islands are searching, see Parallel::diversity, and is only given for Parallel runs with migration*/
+    Progress {
+        best: &'a Schedule,
+        score: u64,
+        stats: RunStats,
+        diversity: Option<f64>,
+    },
+}
+
+/** A clock in milliseconds, for where std::time works. In the browser use performance.now() instead*/
+pub fn std_clock() -> impl Fn() -> f64 {
+    let start = std::time::Instant::now();
+    move || start.elapsed().as_secs_f64() * 1000.0
+}
+
+/** Keeps track of a run against its StopCriteria, shared by Generator, Parallel and anything else which runs a search step by step*/
+pub struct Tracker<'a> {
+    criteria: &'a StopCriteria,
+    clock: &'a dyn Fn() -> f64,
+    start: f64,
+    stats: RunStats,
+    /**Evaluations when the last better schedule was found*/
+    last_improvement: u64,
+    last_progress: f64,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(criteria: &'a StopCriteria, clock: &'a dyn Fn() -> f64) -> Self {
+        let start = clock();
+        Self {
+            criteria,
+            clock,
+            start,
+            stats: RunStats::default(),
+            last_improvement: 0,
+            last_progress: 0.0,
+        }
+    }
+
+    /**Add the work done since the last call, and read the clock*/
+    pub fn record(&mut self, evaluations: u64, random_starts: u64) {
+        self.stats.evaluations += evaluations;
+        self.stats.random_starts += random_starts;
+        self.stats.elapsed = (self.clock)() - self.start;
+    }
+
+    /**Note that a better schedule was found, returning the stats for the observer*/
+    pub fn improved(&mut self) -> RunStats {
+        self.last_improvement = self.stats.evaluations;
+        self.stats
+    }
+
+    /**Whether the observer is due a progress event, returning the stats for it*/
+    pub fn progress(&mut self) -> Option<RunStats> {
+        let interval = self.criteria.progress_interval?;
+        if self.stats.elapsed - self.last_progress >= interval {
+            self.last_progress = self.stats.elapsed;
+            Some(self.stats)
+        } else {
+            None
+        }
+    }
+
+    pub const fn stats(&self) -> RunStats {
+        self.stats
+    }
+
+    /**Which of the criteria has been reached, if any*/
+    pub fn stop_reason(&self, best: &Schedule, best_score: u64) -> Option<StopReason> {
+        let criteria = self.criteria;
+        if criteria.stop_at_ideal && (best.is_ideal() || best_score >= best.score_bound()) {
+            Some(StopReason::Ideal)
+        } else if criteria
+            .target_score
+            .is_some_and(|target| best_score >= target)
+        {
+            Some(StopReason::TargetScore)
+        } else if criteria
+            .time_limit
+            .is_some_and(|limit| self.stats.elapsed >= limit)
+        {
+            Some(StopReason::TimeLimit)
+        } else if criteria
+            .max_evaluations
+            .is_some_and(|limit| self.stats.evaluations >= limit)
+        {
+            Some(StopReason::EvaluationBudget)
+        } else if criteria
+            .stagnation
+            .is_some_and(|limit| self.stats.evaluations - self.last_improvement >= limit)
+        {
+            Some(StopReason::Stagnation)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parallel::{worker_rngs, Parallel};
+    use rand::SeedableRng;
+    use schedule::Generator;
+    use std::cell::Cell;
+
+    fn generator(seed: u64) -> Generator<rand_xorshift::XorShiftRng> {
+        Generator::new(rand_xorshift::XorShiftRng::seed_from_u64(seed), 20, 4, 4)
+    }
+
+    #[test]
+    fn stops_at_evaluation_budget() {
+        let criteria = StopCriteria {
+            max_evaluations: Some(5000),
+            ..StopCriteria::default()
+        };
+        let clock = std_clock();
+        let (reason, stats) = generator(1).run_until(&criteria, &clock, |_| {});
+        assert_eq!(reason, StopReason::EvaluationBudget);
+        assert!(stats.evaluations >= 5000);
+    }
+
+    #[test]
+    fn stops_at_time_limit_of_clock() {
+        let time = Cell::new(0.0);
+        let clock = || {
+            time.set(time.get() + 1.0);
+            time.get()
+        };
+        let criteria = StopCriteria {
+            time_limit: Some(50.0),
+            progress_interval: Some(10.0),
+            ..StopCriteria::default()
+        };
+        let mut progress_events = 0;
+        let (reason, stats) = generator(2).run_until(&criteria, &clock, |event| {
+            if let RunEvent::Progress { .. } = event {
+                progress_events += 1;
+            }
+        });
+        assert_eq!(reason, StopReason::TimeLimit);
+        assert!(stats.elapsed >= 50.0);
+        assert_eq!(progress_events, 5);
+    }
+
+    #[test]
+    fn reports_each_better_schedule() {
+        let criteria = StopCriteria {
+            stagnation: Some(20_000),
+            stop_at_ideal: true,
+            ..StopCriteria::default()
+        };
+        let clock = std_clock();
+        let mut scores = Vec::new();
+        let mut generator = generator(3);
+        let initial = generator.best_score;
+        let (reason, _) = generator.run_until(&criteria, &clock, |event| {
+            if let RunEvent::NewBest { score, .. } = event {
+                scores.push(score);
+            }
+        });
+        assert!(reason == StopReason::Stagnation || reason == StopReason::Ideal);
+        assert!(scores.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert!(scores.iter().all(|&score| score >= initial));
+        assert_eq!(
+            scores.last().copied().unwrap_or(initial),
+            generator.best_score
+        );
+    }
+
+    #[test]
+    fn parallel_stops_at_target_score() {
+        let generators = worker_rngs::<rand_xorshift::XorShiftRng>(4, 2)
+            .into_iter()
+            .map(|rng| Generator::new(rng, 20, 4, 4))
+            .collect();
+        let mut parallel = Parallel::new(generators, 50);
+        let target = parallel.best_score + 1;
+        let criteria = StopCriteria {
+            target_score: Some(target),
+            max_evaluations: Some(10_000_000),
+            ..StopCriteria::default()
+        };
+        let clock = std_clock();
+        let (reason, _) = parallel.run_until(&criteria, &clock, |_| {});
+        assert_eq!(reason, StopReason::TargetScore);
+        assert!(parallel.best_score >= target);
+    }
+}
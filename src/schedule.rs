@@ -1,12 +1,12 @@
+use bounds;
+use history::PairHistory;
+use objective::{self, Metrics, Objective};
 use rand::seq::SliceRandom;
+use run::{RunEvent, RunStats, StopCriteria, StopReason, Tracker};
+use schedule_util::{format, validate};
 use std::ops::IndexMut;
-
-const UNIQUE_GAMES_MULTIPLIER: u32 = 4;
-/* Results in the program favouring schedules with higher total unique games played, during
-testing this seemed to result in overall better (higher total unique games played and higher total unique opponents) generated schedules.
-*/
-
-const MAX_PLAYERS: usize = 64;
+use std::sync::Arc;
+use strategy::{self, Restart, Strategy};
 
 /** Structure for storing a schedule, and performing operations on it
 */
@@ -14,19 +14,68 @@ const MAX_PLAYERS: usize = 64;
 pub trait ScheduleStructure {
     fn get_player_count(&self) -> usize;
     fn get_tables(&self) -> usize;
+    fn get_rounds(&self) -> usize;
 
     /** Get a vector of all players in the game at specified round and table
      */
     fn get_players_from_game(&self, round: usize, table: usize) -> Vec<usize>;
 
+    /** Get the number of teams at each table, 1 when players don't play in teams*/
+    fn get_teams(&self) -> usize {
+        1
+    }
+
+    /** Get the players in each team of the game at specified round and table, the whole table is one team when players don't play in teams
+     */
+    fn get_teams_from_game(&self, round: usize, table: usize) -> Vec<Vec<usize>> {
+        split_into_teams(&self.get_players_from_game(round, table), self.get_teams())
+    }
+
     fn to_schedule(&self) -> Schedule;
 }
+
+/** Split the players at a table into teams in order, with the first players in the first team. Earlier teams get the extra players when they can't be even*/
+pub fn split_into_teams<T: Clone>(players: &[T], teams: usize) -> Vec<Vec<T>> {
+    let teams = teams.max(1);
+    let mut split: Vec<Vec<T>> = vec![Vec::new(); teams];
+    for (position, player) in players.iter().enumerate() {
+        split[position * teams / players.len()].push(player.clone());
+    }
+    split
+}
+
+/** Show a game played in teams as "A+B vs C+D"*/
+pub fn format_pairing<T: std::fmt::Display>(teams: &[Vec<T>]) -> String {
+    teams
+        .iter()
+        .map(|team| {
+            team.iter()
+                .map(|player| player.to_string())
+                .collect::<Vec<String>>()
+                .join("+")
+        })
+        .collect::<Vec<String>>()
+        .join(" vs ")
+}
 #[derive(serde::Serialize, serde::Deserialize, Clone)]
 pub struct SerdeSchedule {
     player_count: usize,
     tables: usize,
+    /**Missing from schedules saved before rounds could differ from tables, in which case it is 0*/
+    #[serde(default)]
+    rounds: usize,
+    /**Minimum and maximum players at each table, empty for schedules with balanced tables*/
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    capacities: Vec<(usize, usize)>,
+    /**Number of teams at each table, the players at each table are listed team by team. 0 for schedules without teams*/
+    #[serde(default, skip_serializing_if = "is_zero")]
+    teams: usize,
     matches: Vec<Vec<Vec<usize>>>,
 }
+
+fn is_zero(value: &usize) -> bool {
+    *value == 0
+}
 impl ScheduleStructure for SerdeSchedule {
     fn get_player_count(&self) -> usize {
         self.player_count
@@ -34,16 +83,303 @@ impl ScheduleStructure for SerdeSchedule {
     fn get_tables(&self) -> usize {
         self.tables
     }
+    fn get_rounds(&self) -> usize {
+        if self.rounds == 0 {
+            self.matches.len()
+        } else {
+            self.rounds
+        }
+    }
     fn get_players_from_game(&self, round: usize, table: usize) -> Vec<usize> {
         self.matches[round][table].clone()
     }
+    fn get_teams(&self) -> usize {
+        self.teams.max(1)
+    }
 
     fn to_schedule(&self) -> Schedule {
-        Schedule::from_vec(self.player_count, self.tables, &self.matches)
+        let mut schedule = if self.capacities.is_empty() {
+            Schedule::new(self.player_count, self.tables, self.get_rounds())
+        } else {
+            Schedule::with_capacities(self.player_count, &self.capacities, self.get_rounds())
+        };
+        schedule.set_teams(self.get_teams());
+        schedule.import_vec(&self.matches);
+        schedule
+    }
+}
+impl SerdeSchedule {
+    /** Check that the shape is one a Schedule can hold, that every player is at exactly one table each round, that table sizes are within capacity,
+    and that the schedule reaches the claimed scores. Should be called before to_schedule on untrusted data, which is safe once there are no violations*/
+    pub fn validate(&self, claim: &validate::Claim) -> validate::Report {
+        let spec = if self.capacities.is_empty() {
+            validate::Spec::balanced(self.player_count, self.tables, self.get_rounds())
+        } else {
+            validate::Spec {
+                player_count: self.player_count,
+                rounds: self.get_rounds(),
+                capacities: self.capacities.clone(),
+            }
+        };
+        let mut violations = validate::check_spec(&spec, self.get_teams());
+        if spec.capacities.len() != self.tables {
+            violations.push(validate::Violation::CapacityCount {
+                expected: self.tables,
+                found: spec.capacities.len(),
+            });
+        }
+        let mut report = validate::validate(&spec, &self.matches, claim);
+        violations.append(&mut report.violations);
+        report.violations = violations;
+        report
+    }
+
+    /** Render the schedule in one of the shared formats, showing players by name where names has one for them.
+    JSON without names is the schedule as it is saved, so it can be loaded again*/
+    pub fn render(&self, format: format::Format, names: &[String]) -> String {
+        if format == format::Format::Json && names.is_empty() {
+            if let Ok(json) = serde_json::to_string_pretty(self) {
+                return json;
+            }
+        }
+        format::render(
+            format,
+            &format::Layout {
+                teams: self.get_teams(),
+                names,
+                ..format::Layout::new(self.player_count, &self.matches)
+            },
+        )
+    }
+
+    /** Render where the players sit every round in one of the shared formats, showing players by name where names has one for them.
+    HTML is a card for each player*/
+    pub fn render_itineraries(
+        &self,
+        format: format::Format,
+        names: &[String],
+        players: &[usize],
+    ) -> String {
+        let schedule = self.to_schedule();
+        format::render_itineraries(
+            format,
+            &format::Layout {
+                teams: self.get_teams(),
+                names,
+                ..format::Layout::new(self.player_count, &self.matches)
+            },
+            &players
+                .iter()
+                .map(|&player| (player, schedule.itinerary(player)))
+                .collect::<Vec<(usize, Vec<format::Seat>)>>(),
+        )
+    }
+}
+
+/** Most players that Move::SwapGroups can swap from each table*/
+pub const MAX_GROUP_SIZE: usize = 4;
+
+/**A change which only affects the players at a few tables. Every move except ExchangeRounds only changes a single round*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Move {
+    /**Swap player1 at table1 with player2 at table2*/
+    Swap {
+        player1: usize,
+        table1: usize,
+        player2: usize,
+        table2: usize,
+    },
+    /**Move a player to a different table, changing the size of both tables*/
+    Relocate {
+        player: usize,
+        from: usize,
+        to: usize,
+    },
+    /**Swap player1 and player2, who are in different teams at the same table, between their teams*/
+    SwapTeams {
+        player1: usize,
+        player2: usize,
+        table: usize,
+    },
+    /**Move player1 from table1 to table2, player2 from table2 to table3, and player3 from table3 to table1*/
+    Cycle {
+        player1: usize,
+        table1: usize,
+        player2: usize,
+        table2: usize,
+        player3: usize,
+        table3: usize,
+    },
+    /**Swap the first size players of group1 at table1 with the first size players of group2 at table2*/
+    SwapGroups {
+        group1: [usize; MAX_GROUP_SIZE],
+        table1: usize,
+        group2: [usize; MAX_GROUP_SIZE],
+        table2: usize,
+        size: usize,
+    },
+    /**Move a player from one table to another in this round, and back the other way in round2, so they play the same tables in a different order.
+    Changes the size of the tables in both rounds*/
+    ExchangeRounds {
+        player: usize,
+        from: usize,
+        to: usize,
+        round2: usize,
+    },
+}
+
+impl Move {
+    /**The move which undoes this move*/
+    pub const fn inverse(self) -> Self {
+        match self {
+            Move::Swap { .. } | Move::SwapTeams { .. } | Move::SwapGroups { .. } => self,
+            Move::Relocate { player, from, to } => Move::Relocate {
+                player,
+                from: to,
+                to: from,
+            },
+            Move::Cycle {
+                player1,
+                table1,
+                player2,
+                table2,
+                player3,
+                table3,
+            } => Move::Cycle {
+                player1,
+                table1: table2,
+                player2: player3,
+                table2: table1,
+                player3: player2,
+                table3,
+            },
+            Move::ExchangeRounds {
+                player,
+                from,
+                to,
+                round2,
+            } => Move::ExchangeRounds {
+                player,
+                from: to,
+                to: from,
+                round2,
+            },
+        }
+    }
+
+    /**The players who change table*/
+    pub fn players(self) -> Vec<usize> {
+        match self {
+            Move::Swap {
+                player1, player2, ..
+            }
+            | Move::SwapTeams {
+                player1, player2, ..
+            } => vec![player1, player2],
+            Move::Relocate { player, .. } | Move::ExchangeRounds { player, .. } => vec![player],
+            Move::Cycle {
+                player1,
+                player2,
+                player3,
+                ..
+            } => vec![player1, player2, player3],
+            Move::SwapGroups {
+                group1,
+                group2,
+                size,
+                ..
+            } => group1[..size]
+                .iter()
+                .chain(&group2[..size])
+                .copied()
+                .collect(),
+        }
+    }
+}
+
+/** Players who must sit at a certain table in every round, and pairs of players who must never share a table.
+Schedules which break fewer constraints always score higher than schedules which break more, whatever the objective*/
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Constraints {
+    /**The table each player must sit at, indexed by player. Players past the end or with None can sit anywhere*/
+    pub pinned: Vec<Option<usize>>,
+    /**Pairs of players who must never be at the same table*/
+    pub forbidden: Vec<(usize, usize)>,
+}
+
+impl Constraints {
+    pub fn is_empty(&self) -> bool {
+        self.pinned.iter().all(Option::is_none) && self.forbidden.is_empty()
+    }
+}
+
+/** Which moves Schedule::moves offers besides swapping two players and relocating one player to a table with space, which are always offered.
+The extra moves can find improvements that a single swap can't, but there are many more of them to evaluate. The default is no extra moves*/
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Neighbourhood {
+    /**Move three players around three tables, see Move::Cycle*/
+    pub cycles: bool,
+    /**Swap groups of this many players between two tables, see Move::SwapGroups. 0 for none, otherwise from 2 to MAX_GROUP_SIZE*/
+    pub group_size: usize,
+    /**Exchange which tables a player is at in two rounds, see Move::ExchangeRounds. Never offered when playing in teams*/
+    pub round_exchanges: bool,
+}
+
+impl Neighbourhood {
+    /** Parse the extra moves from the command line, separated by commas. Accepts `cycles`, `groups:<size>` and `exchanges`*/
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut neighbourhood = Self::default();
+        for part in text.split(',').map(str::trim) {
+            let mut parts = part.splitn(2, ':');
+            match (parts.next().unwrap_or(""), parts.next()) {
+                ("cycles", None) => neighbourhood.cycles = true,
+                ("exchanges", None) => neighbourhood.round_exchanges = true,
+                ("groups", Some(size)) => {
+                    neighbourhood.group_size = match size.trim().parse() {
+                        Ok(size) if (2..=MAX_GROUP_SIZE).contains(&size) => size,
+                        _ => {
+                            return Err(format!(
+                                "Group size '{}' should be from 2 to {}",
+                                size, MAX_GROUP_SIZE
+                            ))
+                        }
+                    }
+                }
+                _ => return Err(format!("Unknown move '{}'", part)),
+            }
+        }
+        Ok(neighbourhood)
+    }
+}
+
+/**Every way of choosing size of the players, keeping their order, each padded to MAX_GROUP_SIZE*/
+fn groups_of(players: &[usize], size: usize) -> Vec<[usize; MAX_GROUP_SIZE]> {
+    let mut groups = Vec::new();
+    if size == 0 || size > players.len() {
+        return groups;
+    }
+    let mut indices: Vec<usize> = (0..size).collect();
+    loop {
+        let mut group = [0; MAX_GROUP_SIZE];
+        for (slot, &index) in group.iter_mut().zip(&indices) {
+            *slot = players[index];
+        }
+        groups.push(group);
+        // Advance the last index which can still move right, and put the ones after it straight after it
+        match (0..size)
+            .rev()
+            .find(|&i| indices[i] < i + players.len() - size)
+        {
+            Some(i) => {
+                indices[i] += 1;
+                for j in i + 1..size {
+                    indices[j] = indices[j - 1] + 1;
+                }
+            }
+            None => return groups,
+        }
     }
 }
-#[cfg(feature = "default")]
-impl SerdeSchedule {}
 
 #[derive(Clone)]
 pub struct Schedule {
@@ -51,21 +387,76 @@ pub struct Schedule {
     player_count: usize,
     /**The number of tables, since one table per game, same as number of games*/
     tables: usize,
-    /**Stores each individual match, uses round * self.tables + table*/
+    /**The number of rounds, each player plays one game per round*/
+    rounds: usize,
+    /**Minimum and maximum number of players at each table*/
+    capacities: Vec<(usize, usize)>,
+    /**Whether capacities are the floor and ceiling of player_count / tables for every table*/
+    balanced: bool,
+    /**The number of u64 words used to store the players in a single match*/
+    words: usize,
+    /**Stores each individual match as a bitset of players, uses (round * self.tables + table) * self.words + word*/
     matches: Vec<u64>,
-    /**Store where each player is for a given round, uses player * self.tables + round_number*/
-    player_positions: Vec<u16>,
+    /**Store where each player is for a given round, as round * self.tables + table, uses player * self.rounds + round_number*/
+    player_positions: Vec<u32>,
     /**Cache of how many unique opponents each player has*/
-    player_opponent_cache: Vec<u8>,
+    player_opponent_cache: Vec<u16>,
     /**Cache of total unique games played*/
     unique_games_played_cache: u32,
     /**Cache of sum of how many unique opponents each player has*/
     unique_opponent_sum_cache: u32,
     unique_opponent_min_cache: u32,
+    /**Where each player was in the schedule this one was started from, in the same form as player_positions.
+    u32::MAX where the player or round didn't exist, empty if there is no original*/
+    original_positions: Arc<Vec<u32>>,
+    /**Cache of how many players are at the same table in the same round as in the original*/
+    unchanged_positions_cache: u32,
+    /**Opponents each player met in rounds that were already played, as a bitset per player, uses player * self.words + word. Empty if there are none*/
+    history_opponents: Arc<Vec<u64>>,
+    /**Players who played each table in rounds that were already played, as a bitset per table, uses table * self.words + word. Empty if there are none*/
+    history_games: Arc<Vec<u64>>,
+    /**Number of teams at each table, 1 when players don't play in teams*/
+    teams: usize,
+    /**Stores the players in each team as a bitset, uses ((round * self.tables + table) * self.teams + team) * self.words + word. Empty when teams is 1*/
+    team_matches: Vec<u64>,
+    /**Which team each player is in at their table, uses player * self.rounds + round. Empty when teams is 1*/
+    player_teams: Vec<u8>,
+    /**Cache of how many unique partners each player has, empty when teams is 1*/
+    player_partner_cache: Vec<u16>,
+    /**Cache of sum of how many unique partners each player has*/
+    unique_partner_sum_cache: u32,
+    /**How much each pair of players met at previous events, uses player1 * self.player_count + player2. Empty if there are none*/
+    pair_history: Arc<Vec<u32>>,
+    /**Cache of the sum of the pair history with each player's opponents, empty if there is no pair history*/
+    player_repeat_cache: Vec<u32>,
+    /**Cache of the sum of the pair history with each player's opponents, over every player*/
+    repeated_pairs_cache: u32,
+    /**Rating of each player minus the average rating, used to balance the strength of tables. Empty if there are no ratings*/
+    ratings: Arc<Vec<i32>>,
+    /**Cache of how far the total rating of the players in each game is from average, uses round * self.tables + table. Empty if there are no ratings*/
+    game_imbalance_cache: Vec<u32>,
+    /**Cache of the sum of how far each game is from average*/
+    table_imbalance_cache: u32,
+    /**Table each player must sit at, indexed by player. Empty if no player is pinned to a table*/
+    pinned: Arc<Vec<Option<usize>>>,
+    /**Whether each pair of players must never share a table, uses player1 * self.player_count + player2. Empty if there are no such pairs*/
+    forbidden: Arc<Vec<bool>>,
+    /**Most constraints a schedule can break, 0 if there are no constraints*/
+    max_violations: u32,
+    /**Cache of how many constraints each game breaks, uses round * self.tables + table. Empty if there are no constraints*/
+    game_violation_cache: Vec<u32>,
+    /**Cache of the sum of how many constraints each game breaks*/
+    constraint_violations_cache: u32,
     /**Calculated max possible total unique games played*/
     pub ideal_unique_games: u32,
     /**Calculated max possible total unique opponents*/
     pub ideal_unique_opponents: u32,
+    /**Calculated max possible total unique partners, 0 when teams is 1*/
+    pub ideal_unique_partners: u32,
+    /**Turns the cached results into a score*/
+    objective: Arc<dyn Objective>,
+    /**Which moves are offered besides swaps and relocations*/
+    neighbourhood: Neighbourhood,
 }
 
 impl ScheduleStructure for Schedule {
@@ -75,22 +466,17 @@ impl ScheduleStructure for Schedule {
     fn get_tables(&self) -> usize {
         self.tables
     }
+    fn get_rounds(&self) -> usize {
+        self.rounds
+    }
     fn get_players_from_game(&self, round: usize, table: usize) -> Vec<usize> {
-        debug_assert!(round < self.get_tables());
-        debug_assert!(table < self.get_tables());
-        let game = self.get(round, table);
-        let game_size = game.count_ones() as usize;
-        let mut players: Vec<usize> = Vec::with_capacity(game_size);
-        for player in 0..self.get_player_count() {
-            let player_number = 1 << player;
-            if game & player_number != 0 {
-                players.push(player);
-                if players.len() >= game_size {
-                    return players;
-                }
-            }
-        }
-        players
+        Schedule::get_players_from_game(self, round, table)
+    }
+    fn get_teams(&self) -> usize {
+        self.teams
+    }
+    fn get_teams_from_game(&self, round: usize, table: usize) -> Vec<Vec<usize>> {
+        Schedule::get_teams_from_game(self, round, table)
     }
 
     fn to_schedule(&self) -> Schedule {
@@ -99,73 +485,151 @@ impl ScheduleStructure for Schedule {
 }
 
 impl Schedule {
-    /**Create a new Schedule object with specified player count and table count. Panics if player count >= 64, or table count <= 2.
+    /**Create a new Schedule object with specified player count, table count and round count, with players spread as evenly as possible between tables.
+    Panics if player count < 2 * table count, table count <= 2, or round count is 0.
     Contains blank schedule, so either generate_random, normal_fill, or import_vec will need to be called before it can be used */
-    pub fn new(player_count: usize, tables: usize) -> Self {
-        assert!(player_count <= MAX_PLAYERS);
+    pub fn new(player_count: usize, tables: usize, rounds: usize) -> Self {
         assert!(player_count >= 2 * tables);
         assert!(tables >= 2); // Cannot swap two different tables, if there are less than two tables.
-        let mut matches: Vec<u64> = Vec::with_capacity(tables * tables);
-        for _ in 0..(tables * tables) {
-            matches.push(0);
-        }
-        let mut player_positions: Vec<u16> = Vec::with_capacity(player_count * tables);
-        for _ in 0..(player_count * tables) {
-            player_positions.push(0);
-        }
-        let mut player_opponent_cache: Vec<u8> = Vec::with_capacity(player_count);
-        for _ in 0..player_count {
-            player_opponent_cache.push(0);
-        }
-        let ideal_unique_opponents = (player_count
-            * tables
-            * (1.max(if player_count % tables == 0 {
-                player_count / tables
-            } else {
-                player_count / tables + 1
-            }) - 1)) as u32;
-        debug_assert!(if player_count <= tables {
-            ideal_unique_opponents == 0
+        let min = player_count / tables;
+        let max = if player_count.is_multiple_of(tables) {
+            min
         } else {
-            ideal_unique_opponents > 0
-        }); // Basic sanity check on above algorithm
-        Self {
+            min + 1
+        };
+        let mut new = Self::with_capacities(player_count, &vec![(min, max); tables], rounds);
+        new.balanced = true;
+        new
+    }
+
+    /**Create a new Schedule object with specified player count, (minimum, maximum) players at each table, and round count.
+    Panics if there are less than 2 tables, a table could have less than 2 players, the player count can't fit the capacities, or round count is 0.
+    Contains blank schedule, so either generate_random, normal_fill, or import_vec will need to be called before it can be used */
+    pub fn with_capacities(
+        player_count: usize,
+        capacities: &[(usize, usize)],
+        rounds: usize,
+    ) -> Self {
+        let tables = capacities.len();
+        assert!(tables >= 2); // Cannot swap two different tables, if there are less than two tables.
+        assert!(rounds >= 1);
+        assert!(capacities.iter().all(|&(min, max)| 2 <= min && min <= max));
+        assert!(capacities.iter().map(|&(min, _)| min).sum::<usize>() <= player_count);
+        assert!(capacities.iter().map(|&(_, max)| max).sum::<usize>() >= player_count);
+        let words = Self::words_for(player_count);
+        let mut new = Self {
             player_count,
             tables,
-            matches,
-            player_positions,
-            player_opponent_cache,
+            rounds,
+            capacities: capacities.to_vec(),
+            balanced: false,
+            words,
+            matches: vec![0; rounds * tables * words],
+            player_positions: vec![0; player_count * rounds],
+            player_opponent_cache: vec![0; player_count],
             unique_games_played_cache: 0,
             unique_opponent_sum_cache: 0,
             unique_opponent_min_cache: 0,
-            ideal_unique_games: (player_count * tables) as u32,
-            ideal_unique_opponents,
+            original_positions: Arc::new(Vec::new()),
+            unchanged_positions_cache: 0,
+            history_opponents: Arc::new(Vec::new()),
+            history_games: Arc::new(Vec::new()),
+            teams: 1,
+            team_matches: Vec::new(),
+            player_teams: Vec::new(),
+            player_partner_cache: Vec::new(),
+            unique_partner_sum_cache: 0,
+            pair_history: Arc::new(Vec::new()),
+            player_repeat_cache: Vec::new(),
+            repeated_pairs_cache: 0,
+            ratings: Arc::new(Vec::new()),
+            game_imbalance_cache: Vec::new(),
+            table_imbalance_cache: 0,
+            pinned: Arc::new(Vec::new()),
+            forbidden: Arc::new(Vec::new()),
+            max_violations: 0,
+            game_violation_cache: Vec::new(),
+            constraint_violations_cache: 0,
+            ideal_unique_games: 0,
+            ideal_unique_opponents: 0,
+            ideal_unique_partners: 0,
+            objective: Arc::new(objective::Weighted::default()),
+            neighbourhood: Neighbourhood::default(),
+        };
+        new.calculate_ideals();
+        new
+    }
+
+    /**Number of u64 words needed to store a bit for every player*/
+    const fn words_for(player_count: usize) -> usize {
+        if player_count == 0 {
+            1
+        } else {
+            player_count.div_ceil(64)
         }
     }
+
+    /**Where the player sits in each round, from player_positions, with everyone at their table listed team by team*/
+    pub fn itinerary(&self, player: usize) -> Vec<format::Seat> {
+        (0..self.rounds)
+            .map(|round| {
+                let table =
+                    self.player_positions[player * self.rounds + round] as usize % self.tables;
+                format::Seat {
+                    table,
+                    players: self.get_teams_from_game(round, table).concat(),
+                }
+            })
+            .collect()
+    }
+
+    /**The itinerary of every player, indexed by player*/
+    pub fn itineraries(&self) -> Vec<Vec<format::Seat>> {
+        (0..self.player_count)
+            .map(|player| self.itinerary(player))
+            .collect()
+    }
+
     pub fn to_serde_schedule(&self) -> SerdeSchedule {
-        let mut matches: Vec<Vec<Vec<usize>>> = Vec::with_capacity(self.tables);
-        for round_number in 0..self.tables {
+        let mut matches: Vec<Vec<Vec<usize>>> = Vec::with_capacity(self.rounds);
+        for round_number in 0..self.rounds {
             let mut round: Vec<Vec<usize>> = Vec::with_capacity(self.tables);
             for table_number in 0..self.tables {
-                round.push(self.get_players_from_game(round_number, table_number));
+                // List the players team by team, so that the teams can be recovered
+                round.push(
+                    self.get_teams_from_game(round_number, table_number)
+                        .concat(),
+                );
             }
             matches.push(round);
         }
         SerdeSchedule {
             player_count: self.player_count,
             tables: self.tables,
+            rounds: self.rounds,
+            capacities: if self.balanced {
+                Vec::new()
+            } else {
+                self.capacities.clone()
+            },
+            teams: if self.teams > 1 { self.teams } else { 0 },
             matches: matches,
         }
     }
 
-    /**Create a new Schedule object with the specified player count,  table count, and schedule which is in the following form:
+    /**Create a new Schedule object with the specified player count, table count, round count, and schedule which is in the following form:
     Player number as usize,
     Players in a game as Vec of players, stored as Vec<usize>,
     Games in a round as Vec of Players in a games, stored as Vec<Vec<usize>>
     Rounds in an event as Vec of Games in a Round, stored as Vec<Vec<Vec<usize>>>
      */
-    pub fn from_vec(player_count: usize, tables: usize, data: &[Vec<Vec<usize>>]) -> Self {
-        let mut new = Self::new(player_count, tables);
+    pub fn from_vec(
+        player_count: usize,
+        tables: usize,
+        rounds: usize,
+        data: &[Vec<Vec<usize>>],
+    ) -> Self {
+        let mut new = Self::new(player_count, tables, rounds);
         new.import_vec(&data);
         new
     }
@@ -177,30 +641,34 @@ impl Schedule {
     Rounds in an event as Vec of Games in a Round, stored as Vec<Vec<Vec<usize>>>
      */
     pub fn import_vec(&mut self, data: &[Vec<Vec<usize>>]) {
-        self.matches = Vec::with_capacity(self.tables * self.tables);
-        for _ in 0..(self.tables * self.tables) {
-            self.matches.push(0);
-        }
-        self.player_positions = Vec::with_capacity(self.player_count * self.tables);
-        for _ in 0..(self.player_count * self.tables) {
-            self.player_positions.push(0);
-        }
-        self.player_opponent_cache = Vec::with_capacity(self.player_count);
-        for _ in 0..self.player_count {
-            self.player_opponent_cache.push(0);
+        self.matches = vec![0; self.rounds * self.tables * self.words];
+        self.player_positions = vec![0; self.player_count * self.rounds];
+        self.player_opponent_cache = vec![0; self.player_count];
+        if self.teams > 1 {
+            self.team_matches = vec![0; self.rounds * self.tables * self.teams * self.words];
+            self.player_teams = vec![0; self.player_count * self.rounds];
         }
         for (round_number, round) in data.iter().enumerate() {
             for (table_number, table) in round.iter().enumerate() {
-                for player in table.iter() {
-                    *self.get_mut(round_number, table_number) |= 1_u64 << player;
-                    self.player_positions[player * self.tables + round_number] =
-                        (round_number * self.tables + table_number) as u16;
+                for (position, &player) in table.iter().enumerate() {
+                    self.toggle_player(round_number, table_number, player);
+                    self.player_positions[player * self.rounds + round_number] =
+                        (round_number * self.tables + table_number) as u32;
+                    if self.teams > 1 {
+                        // Same split as split_into_teams
+                        let team = position * self.teams / table.len();
+                        self.player_teams[player * self.rounds + round_number] = team as u8;
+                        self.toggle_team(round_number * self.tables + table_number, team, player);
+                    }
                 }
             }
         }
         self.find_unique_opponents(); // Fill cache of unique opponents with correct data
         self.find_unique_games_played(); // Fill cache of unique games played with correct data
         self.find_min_unique_opponents();
+        self.find_unchanged_positions();
+        self.find_table_imbalance();
+        self.find_constraint_violations();
     }
 
     /**
@@ -209,15 +677,20 @@ impl Schedule {
     pub fn generate_random<T: rand::Rng + rand_core::RngCore>(&mut self, rng: &mut T) {
         let mut player_list: Vec<usize> = (0..self.player_count).collect();
         let mut game: Vec<Vec<Vec<usize>>> = Vec::new();
-        for _round in 0..self.tables {
-            let offset: usize = rng.gen();
+        for _round in 0..self.rounds {
             player_list.shuffle(rng);
-            let mut round: Vec<Vec<usize>> = Vec::new();
-            for _ in 0..self.tables {
-                round.push(Vec::new());
+            // Start with every table at its minimum, then hand out the remaining players to random tables with space
+            let mut sizes: Vec<usize> = self.capacities.iter().map(|&(min, _)| min).collect();
+            for _ in 0..(self.player_count - sizes.iter().sum::<usize>()) {
+                let open: Vec<usize> = (0..self.tables)
+                    .filter(|&table| sizes[table] < self.capacities[table].1)
+                    .collect();
+                sizes[*open.choose(rng).unwrap()] += 1;
             }
-            for (i, player) in player_list.iter().enumerate() {
-                round[(i + offset) % self.tables].push(*player);
+            let mut round: Vec<Vec<usize>> = Vec::new();
+            let mut players = player_list.iter().copied();
+            for size in sizes {
+                round.push(players.by_ref().take(size).collect());
             }
             game.push(round);
         }
@@ -228,343 +701,1527 @@ impl Schedule {
     they play, and if player_count > tables, then everyone plays every game once.
     */
     pub fn normal_fill(&mut self) {
-        let player_list: Vec<usize> = (0..self.player_count).collect();
+        // Table sizes for the first round, rotated with the players in later rounds when capacities allow
+        let mut base_sizes: Vec<usize> = self.capacities.iter().map(|&(min, _)| min).collect();
+        let mut table = 0;
+        while base_sizes.iter().sum::<usize>() < self.player_count {
+            if base_sizes[table] < self.capacities[table].1 {
+                base_sizes[table] += 1;
+            }
+            table = (table + 1) % self.tables;
+        }
         let mut game: Vec<Vec<Vec<usize>>> = Vec::new();
-        for (offset, _round) in (0..self.tables).enumerate() {
+        for (offset, _round) in (0..self.rounds).enumerate() {
+            let rotated: Vec<usize> = (0..self.tables)
+                .map(|table| base_sizes[(table + self.tables - offset % self.tables) % self.tables])
+                .collect();
+            let sizes = if rotated
+                .iter()
+                .zip(self.capacities.iter())
+                .all(|(&size, &(min, max))| min <= size && size <= max)
+            {
+                rotated
+            } else {
+                base_sizes.clone()
+            };
             let mut round: Vec<Vec<usize>> = Vec::new();
             for _ in 0..self.tables {
                 round.push(Vec::new());
             }
-            for (i, player) in player_list.iter().enumerate() {
-                round[(i + offset) % self.tables].push(*player);
+            for player in 0..self.player_count {
+                // Move on to the next table with space if the preferred one is full
+                let mut table = (player + offset) % self.tables;
+                while round[table].len() >= sizes[table] {
+                    table = (table + 1) % self.tables;
+                }
+                round[table].push(player);
             }
             game.push(round);
         }
         self.import_vec(&game);
     }
 
-    fn get(&self, round: usize, table: usize) -> u64 {
-        self.matches[round * self.tables + table]
+    /**Fill the schedule so that it changes as little as possible from original, a schedule in the same form as import_vec takes
+    but which can have different dimensions. Players, tables and rounds that don't exist in this schedule are dropped,
+    players without a seat are added to the emptiest tables, and rounds original doesn't have come from normal_fill.
+    Afterwards original is used to count unchanged positions*/
+    pub fn fill_from(&mut self, original: &[Vec<Vec<usize>>]) {
+        self.normal_fill();
+        let mut game: Vec<Vec<Vec<usize>>> = (0..self.rounds)
+            .map(|round| {
+                (0..self.tables)
+                    .map(|table| self.get_players_from_game(round, table))
+                    .collect()
+            })
+            .collect();
+        for (round, original_round) in original.iter().enumerate().take(self.rounds) {
+            let mut seated = vec![false; self.player_count];
+            let mut tables: Vec<Vec<usize>> = vec![Vec::new(); self.tables];
+            for (table, players) in original_round.iter().enumerate().take(self.tables) {
+                for &player in players {
+                    if player < self.player_count
+                        && !seated[player]
+                        && tables[table].len() < self.capacities[table].1
+                    {
+                        seated[player] = true;
+                        tables[table].push(player);
+                    }
+                }
+            }
+            for player in (0..self.player_count).filter(|&player| !seated[player]) {
+                // Seat at the table furthest below its minimum, or with the fewest players
+                let table = (0..self.tables)
+                    .filter(|&table| tables[table].len() < self.capacities[table].1)
+                    .min_by_key(|&table| {
+                        (
+                            tables[table].len() as isize - self.capacities[table].0 as isize,
+                            tables[table].len(),
+                        )
+                    })
+                    .unwrap();
+                tables[table].push(player);
+            }
+            // Top up tables below their minimum from the tables with the most players to spare
+            while let Some(short) =
+                (0..self.tables).find(|&table| tables[table].len() < self.capacities[table].0)
+            {
+                let donor = (0..self.tables)
+                    .max_by_key(|&table| {
+                        tables[table].len() as isize - self.capacities[table].0 as isize
+                    })
+                    .unwrap();
+                let player = tables[donor].pop().unwrap();
+                tables[short].push(player);
+            }
+            game[round] = tables;
+        }
+        self.import_vec(&game);
+        self.set_original(original);
+    }
+
+    /**Count unchanged positions against original, a schedule in the same form as import_vec takes but which can have different dimensions*/
+    pub fn set_original(&mut self, original: &[Vec<Vec<usize>>]) {
+        let mut positions = vec![u32::MAX; self.player_count * self.rounds];
+        for (round, original_round) in original.iter().enumerate().take(self.rounds) {
+            for (table, players) in original_round.iter().enumerate().take(self.tables) {
+                for &player in players.iter().filter(|&&player| player < self.player_count) {
+                    positions[player * self.rounds + round] = (round * self.tables + table) as u32;
+                }
+            }
+        }
+        self.original_positions = Arc::new(positions);
+        self.find_unchanged_positions();
+    }
+
+    /**Count how many players are at the same table in the same round as in the original. Uses mut self to allow updating cache*/
+    pub fn find_unchanged_positions(&mut self) -> u32 {
+        self.unchanged_positions_cache = self
+            .original_positions
+            .iter()
+            .zip(self.player_positions.iter())
+            .filter(|(original, current)| original == current)
+            .count() as u32;
+        self.unchanged_positions_cache
     }
 
-    fn get_mut(&mut self, round: usize, table: usize) -> &mut u64 {
-        self.matches.index_mut(round * self.tables + table)
+    /**Get cached count of players at the same table in the same round as in the original, 0 if there is no original*/
+    pub fn unchanged_positions(&self) -> u32 {
+        self.unchanged_positions_cache
     }
 
-    /**Count the total number of unique games played by all players. Uses mut self to allow updating cache
-     */
-    #[inline(never)]
-    pub fn find_unique_games_played(&mut self) -> u32 {
-        assert!(self.matches.len() >= self.tables * self.tables);
-        // Check that the unchecked_get won't read a value off the end of the array
-        let mut total: u32 = 0;
-        for table1 in (0..(self.tables / 2 * 2)).step_by(2) {
-            let table2 = table1 + 1;
-            let mut total_table: u128 = 0;
-            for round in 0..self.tables {
-                let base = round * self.tables;
-                total_table |= unsafe {
-                    // Treat pairs of u64 as u128 to enable faster bitwise AND
-                    std::mem::transmute::<[u64; 2], u128>([
-                        *self.matches.get_unchecked(base + table1), // Using get_unchecked since it gave ~10% improvement
-                        *self.matches.get_unchecked(base + table2),
-                    ])
-                };
+    /**Update the unchanged positions cache for a player moving between games*/
+    fn track_unchanged(&mut self, player: usize, round: usize, from: u32, to: u32) {
+        if let Some(&original) = self.original_positions.get(player * self.rounds + round) {
+            if original == from {
+                self.unchanged_positions_cache -= 1;
             }
-            total += total_table.count_ones();
-        }
-        if self.tables % 2 == 1 {
-            // Deal with the number of tables not being an even number
-            let mut total_table = 0;
-            for round in 0..self.tables {
-                total_table |= self.get(round, self.tables - 1);
+            if original == to {
+                self.unchanged_positions_cache += 1;
             }
-            total += total_table.count_ones();
         }
-        debug_assert!(
-            // Less optimised version used to check that above code is correct
-            total == {
-                let mut total2: u32 = 0;
-                for table in 0..self.tables {
-                    let mut total_table = 0;
-                    for round in 0..self.tables {
-                        total_table |= self.get(round, table);
+    }
+
+    /**Count rounds that were already played as history, such as the completed rounds of an event that is being re-planned.
+    played is in the same form as import_vec takes, with players numbered as in this schedule. Players past the player count and tables past the last are ignored.
+    Opponents met and games played in the history don't count as new in this schedule, and the ideal scores are changed to match.
+    Call before filling the schedule, filling updates the caches*/
+    pub fn set_history(&mut self, played: &[Vec<Vec<usize>>]) {
+        let words = self.words;
+        let mut opponents: Vec<u64> = vec![0; self.player_count * words];
+        let mut games: Vec<u64> = vec![0; self.tables * words];
+        for round in played {
+            for (table, players) in round.iter().enumerate() {
+                let mut game = vec![0; words];
+                for &player in players.iter().filter(|&&player| player < self.player_count) {
+                    game[player / 64] |= 1 << (player % 64);
+                }
+                for &player in players.iter().filter(|&&player| player < self.player_count) {
+                    for (word, &bits) in game.iter().enumerate() {
+                        opponents[player * words + word] |= bits;
+                    }
+                }
+                if table < self.tables {
+                    for (word, &bits) in game.iter().enumerate() {
+                        games[table * words + word] |= bits;
                     }
-                    total2 += total_table.count_ones();
                 }
-                total2
             }
-        );
-        self.unique_games_played_cache = total;
-        total
+        }
+        self.history_opponents = Arc::new(opponents);
+        self.history_games = Arc::new(games);
+        self.calculate_ideals();
     }
-    /** Use cached results to get total number of unique games played */
-    pub const fn unique_games_played(&self) -> u32 {
-        self.unique_games_played_cache
+
+    /**Penalise seating players together who met at previous events, by how much they met in history.
+    Opponents from history set via set_history aren't affected, since those are counted as already met.
+    Call before filling the schedule, filling updates the caches*/
+    pub fn set_pair_history(&mut self, history: &PairHistory) {
+        assert_eq!(history.get_player_count(), self.player_count);
+        if history.is_empty() {
+            self.pair_history = Arc::new(Vec::new());
+            self.player_repeat_cache = Vec::new();
+        } else {
+            self.pair_history = Arc::new(history.weights().to_vec());
+            self.player_repeat_cache = vec![0; self.player_count];
+        }
+        self.repeated_pairs_cache = 0;
     }
-    /** Count the number of opponents specified player has been in a match with.
-     */
-    fn player_unique_opponents(&mut self, player: usize) -> u8 {
-        // Take a bitwise OR on all games specified player was in, and then count the ones to get total unique players
-        let count = self.player_positions[player * self.tables..player * self.tables + self.tables]
+
+    /**Balance the strength of the tables using a rating for each player, such as from ratings::elo_ratings.
+    Each game is penalised by how far the total rating of its players is from what average players would have, so tables get a similar average strength.
+    Call before filling the schedule, filling updates the caches*/
+    pub fn set_ratings(&mut self, ratings: &[f64]) {
+        assert_eq!(ratings.len(), self.player_count);
+        let average = ratings.iter().sum::<f64>() / self.player_count as f64;
+        let ratings: Vec<i32> = ratings
             .iter()
-            .map(|&index| self.matches[index as usize])
-            .fold(0, |acc, round| acc | round)
-            .count_ones() as u8
-            - 1;
-        self.player_opponent_cache[player] = count;
-        count
-    }
-    /** Count the number of opponents each player has been in a match with
-     */
-    pub fn find_unique_opponents(&mut self) -> u32 {
-        let mut total: u32 = 0;
-        for player in 0..self.player_count {
-            total += u32::from(self.player_unique_opponents(player));
+            .map(|rating| (rating - average).round() as i32)
+            .collect();
+        if ratings.iter().all(|&rating| rating == 0) {
+            self.ratings = Arc::new(Vec::new());
+            self.game_imbalance_cache = Vec::new();
+        } else {
+            self.ratings = Arc::new(ratings);
+            self.game_imbalance_cache = vec![0; self.rounds * self.tables];
         }
-        self.unique_opponent_sum_cache = total;
-        total
+        self.table_imbalance_cache = 0;
     }
-    /** Take the sum of the cached number of opponents each player has, and cache the result*/
-    fn sum_unique_opponent(&mut self) {
-        self.unique_opponent_sum_cache = self
-            .player_opponent_cache
+
+    /**How far the total rating of the players in the game, which is round * self.tables + table, is from average*/
+    fn game_imbalance(&self, game: usize) -> u32 {
+        let mut total: i32 = 0;
+        for (word_number, &word) in self.matches[game * self.words..][..self.words]
             .iter()
-            .map(|&val| u32::from(val))
-            .sum::<u32>();
+            .enumerate()
+        {
+            let mut word = word;
+            while word != 0 {
+                let player = word.trailing_zeros();
+                word -= 1 << player;
+                total += self.ratings[word_number * 64 + player as usize];
+            }
+        }
+        total.unsigned_abs()
     }
 
-    /** Use cached results to get total number of unique opponents */
-    pub const fn unique_opponents(&self) -> u32 {
-        self.unique_opponent_sum_cache
+    /**Regenerate the cached imbalance of a game, and the sum cache, does nothing if there are no ratings*/
+    fn update_game_imbalance(&mut self, game: usize) {
+        if !self.ratings.is_empty() {
+            let imbalance = self.game_imbalance(game);
+            self.table_imbalance_cache =
+                self.table_imbalance_cache - self.game_imbalance_cache[game] + imbalance;
+            self.game_imbalance_cache[game] = imbalance;
+        }
     }
 
-    fn find_min_unique_opponents(&mut self) -> u32 {
-        self.unique_opponent_min_cache =
-            *self.player_opponent_cache.iter().min().unwrap_or(&0) as u32;
-        self.unique_opponent_min_cache
+    /**Calculate how far every game is from average strength and cache the results*/
+    pub fn find_table_imbalance(&mut self) -> u32 {
+        self.table_imbalance_cache = 0;
+        if !self.ratings.is_empty() {
+            for game in 0..self.rounds * self.tables {
+                self.game_imbalance_cache[game] = self.game_imbalance(game);
+                self.table_imbalance_cache += self.game_imbalance_cache[game];
+            }
+        }
+        self.table_imbalance_cache
     }
 
-    pub const fn min_unique_opponents(&self) -> u32 {
-        self.unique_opponent_min_cache
+    /**Get the cached sum of how far the total rating of each game is from average, 0 when there are no ratings*/
+    pub const fn table_imbalance(&self) -> u32 {
+        self.table_imbalance_cache
     }
 
-    /** Get the number of tables*/
-    pub const fn get_tables(&self) -> usize {
-        self.tables
+    /**Set the players who must sit at a certain table and the pairs who must never share a table. Panics if a player or table doesn't exist*/
+    pub fn set_constraints(&mut self, constraints: &Constraints) {
+        assert!(constraints.pinned.len() <= self.player_count);
+        assert!(constraints
+            .pinned
+            .iter()
+            .flatten()
+            .all(|&table| table < self.tables));
+        self.max_violations = 0;
+        if constraints.pinned.iter().any(Option::is_some) {
+            let mut pinned = constraints.pinned.clone();
+            pinned.resize(self.player_count, None);
+            self.max_violations += (pinned.iter().flatten().count() * self.rounds) as u32;
+            self.pinned = Arc::new(pinned);
+        } else {
+            self.pinned = Arc::new(Vec::new());
+        }
+        if constraints.forbidden.is_empty() {
+            self.forbidden = Arc::new(Vec::new());
+        } else {
+            let mut forbidden = vec![false; self.player_count * self.player_count];
+            for &(player1, player2) in constraints.forbidden.iter() {
+                assert!(player1 < self.player_count && player2 < self.player_count);
+                forbidden[player1 * self.player_count + player2] = true;
+                forbidden[player2 * self.player_count + player1] = true;
+            }
+            // Each pair only counts once however many times it was listed
+            let pairs = forbidden.iter().filter(|&&forbidden| forbidden).count() / 2;
+            self.max_violations += (pairs * self.rounds) as u32;
+            self.forbidden = Arc::new(forbidden);
+        }
+        self.game_violation_cache = if self.max_violations == 0 {
+            Vec::new()
+        } else {
+            vec![0; self.rounds * self.tables]
+        };
+        self.constraint_violations_cache = 0;
     }
-    /** Get the number of players */
-    pub const fn get_player_count(&self) -> usize {
-        self.player_count
+
+    /**How many constraints the game, which is round * self.tables + table, breaks*/
+    fn game_violations(&self, game: usize) -> u32 {
+        let players = self.get_players_from_game(game / self.tables, game % self.tables);
+        let mut violations = 0;
+        for (position, &player1) in players.iter().enumerate() {
+            if self
+                .pinned
+                .get(player1)
+                .is_some_and(|&pinned| pinned.is_some_and(|table| table != game % self.tables))
+            {
+                violations += 1;
+            }
+            if !self.forbidden.is_empty() {
+                for &player2 in &players[position + 1..] {
+                    if self.forbidden[player1 * self.player_count + player2] {
+                        violations += 1;
+                    }
+                }
+            }
+        }
+        violations
     }
 
-    /** Get a vector of all players in the game at specified round and table
-     */
-    pub fn get_players_from_game(&self, round: usize, table: usize) -> Vec<usize> {
-        debug_assert!(round < self.tables);
-        debug_assert!(table < self.tables);
-        let mut game = self.get(round, table);
-        let game_size = game.count_ones() as usize;
-        let mut players: Vec<usize> = Vec::with_capacity(game_size);
-        while game != 0 {
-            let player = game.trailing_zeros();
-            game -= 1 << player;
-            players.push(player as usize);
+    /**Regenerate the cached constraints broken by a game, and the sum cache, does nothing if there are no constraints*/
+    fn update_game_constraints(&mut self, game: usize) {
+        if self.max_violations != 0 {
+            let violations = self.game_violations(game);
+            self.constraint_violations_cache =
+                self.constraint_violations_cache - self.game_violation_cache[game] + violations;
+            self.game_violation_cache[game] = violations;
         }
-        players
     }
-    /** Calculate score and cache results*/
-    pub fn generate_score(&mut self) -> u32 {
-        self.find_unique_opponents() * self.ideal_unique_games
-            + self.find_min_unique_opponents()
-                * self.ideal_unique_games
-                * (self.player_count as u32)
-            + self.find_unique_games_played()
-                * self.ideal_unique_opponents
-                * UNIQUE_GAMES_MULTIPLIER
+
+    /**Calculate how many constraints every game breaks and cache the results*/
+    pub fn find_constraint_violations(&mut self) -> u32 {
+        self.constraint_violations_cache = 0;
+        if self.max_violations != 0 {
+            for game in 0..self.rounds * self.tables {
+                self.game_violation_cache[game] = self.game_violations(game);
+                self.constraint_violations_cache += self.game_violation_cache[game];
+            }
+        }
+        self.constraint_violations_cache
     }
 
-    /** Get score using cached results */
-    pub const fn get_score(&self) -> u32 {
-        self.unique_opponents() * self.ideal_unique_games
-            + self.min_unique_opponents() * self.ideal_unique_games * (self.player_count as u32)
-            + self.unique_games_played() * self.ideal_unique_opponents * UNIQUE_GAMES_MULTIPLIER
+    /**Use cached results to get how many constraints the schedule breaks, counting each pinned player at the wrong table and each forbidden pair at the same table once a round*/
+    pub const fn constraint_violations(&self) -> u32 {
+        self.constraint_violations_cache
     }
 
-    /**Find which pair of players being swapped maximises the score.
-    Returns (best found score, total unique games played).
-     If apply is true then it applies the found optimal, otherwise self should be unchanged*/
-    pub fn improve_table(
-        &mut self,
-        old_score: u32,
-        round: usize,
-        table1: usize,
-        table2: usize,
-        apply: bool,
-    ) -> (u32, u32, u32) {
-        let mut score = old_score;
-        debug_assert!(score == self.get_score());
-        debug_assert!(score == self.generate_score()); // check that cache is updated
-        let old_unique_games_played = self.unique_games_played();
-        let mut unique_games_played = old_unique_games_played;
+    /**Split the players at each table into teams, such as two teams of two for doubles games.
+    Unique partners and unique opponents are then counted separately, with only players in other teams counting as opponents.
+    Panics if a table could have fewer players than teams. Call before filling the schedule, the players given for each table are split with split_into_teams*/
+    pub fn set_teams(&mut self, teams: usize) {
+        assert!(teams >= 1 && teams <= usize::from(u8::MAX));
+        assert!(self.capacities.iter().all(|&(min, _)| min >= teams));
+        self.teams = teams;
+        if teams > 1 {
+            self.team_matches = vec![0; self.rounds * self.tables * teams * self.words];
+            self.player_teams = vec![0; self.player_count * self.rounds];
+            self.player_partner_cache = vec![0; self.player_count];
+        } else {
+            self.team_matches = Vec::new();
+            self.player_teams = Vec::new();
+            self.player_partner_cache = Vec::new();
+        }
+        self.unique_partner_sum_cache = 0;
+        self.calculate_ideals();
+    }
 
-        let original_t1 = self.get(round, table1);
-        let original_t2 = self.get(round, table2);
+    /** Get the number of teams at each table, 1 when players don't play in teams*/
+    pub const fn get_teams(&self) -> usize {
+        self.teams
+    }
 
-        let mut best_t1 = original_t1;
-        let mut best_t2 = original_t2;
-        let mut best_player1 = 0;
-        let mut best_player2 = 0;
+    /**Recalculate the ideal scores from the capacities, teams and history. These are upper bounds which no schedule can do better than, see bounds*/
+    fn calculate_ideals(&mut self) {
+        let words = self.words;
+        let largest_table = self
+            .capacities
+            .iter()
+            .map(|&(_, max)| max)
+            .max()
+            .unwrap_or(1);
+        // Players in the smallest team at the largest table have the most opponents
+        let opponents_per_round = if self.teams > 1 {
+            largest_table - largest_table / self.teams
+        } else {
+            largest_table - 1
+        };
+        let partners_per_round = largest_table.div_ceil(self.teams) - 1;
+        self.ideal_unique_partners = if self.teams > 1 {
+            (self.player_count * (self.rounds * partners_per_round).min(self.player_count - 1))
+                as u32
+        } else {
+            0
+        };
+        let history_met: Vec<usize> = (0..self.player_count)
+            .map(|player| {
+                (0..words)
+                    .map(|word| self.history_opponents_word(player, word).count_ones() as usize)
+                    .sum::<usize>()
+                    .saturating_sub(1) // A player who played is in their own bitset
+            })
+            .collect();
+        // Each player can meet at most opponents_per_round new opponents per round, and can't meet more than everyone else
+        let opponent_limit: usize = history_met
+            .iter()
+            .map(|met| (met + self.rounds * opponents_per_round).min(self.player_count - 1))
+            .sum();
+        // Opponents are always at the same table, so there can't be more of them than pairs who can meet
+        let pair_limit = (history_met.iter().sum::<usize>() / 2
+            + bounds::most_unique_pairs(self.player_count, &self.capacities, self.rounds))
+        .min(self.player_count * (self.player_count - 1) / 2);
+        self.ideal_unique_opponents = opponent_limit.min(2 * pair_limit) as u32;
+        // Each player can play at most every game once, and each game can be played by at most every player once
+        let player_limit: usize = (0..self.player_count)
+            .map(|player| {
+                let played_tables = (0..self.tables)
+                    .filter(|&table| {
+                        self.history_games_word(table, player / 64) & (1 << (player % 64)) != 0
+                    })
+                    .count();
+                (played_tables + self.rounds).min(self.tables)
+            })
+            .sum();
+        let table_limit: usize = self
+            .capacities
+            .iter()
+            .enumerate()
+            .map(|(table, &(_, max))| {
+                let played_players = (0..words)
+                    .map(|word| self.history_games_word(table, word).count_ones() as usize)
+                    .sum::<usize>();
+                (played_players + self.rounds * max).min(self.player_count)
+            })
+            .sum();
+        self.ideal_unique_games = player_limit.min(table_limit) as u32;
+    }
 
-        let t1_players: Vec<usize> = self.get_players_from_game(round, table1);
-        let t2_players: Vec<usize> = self.get_players_from_game(round, table2);
+    /**Word of the bitset of opponents the player met in the history, 0 if there is no history*/
+    fn history_opponents_word(&self, player: usize, word: usize) -> u64 {
+        self.history_opponents
+            .get(player * self.words + word)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /**Word of the bitset of players who played the table in the history, 0 if there is no history*/
+    fn history_games_word(&self, table: usize, word: usize) -> u64 {
+        self.history_games
+            .get(table * self.words + word)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /**Index of the first word of the specified game in self.matches*/
+    fn game_index(&self, round: usize, table: usize) -> usize {
+        (round * self.tables + table) * self.words
+    }
+
+    /**Get the words storing the players of the specified game*/
+    fn get(&self, round: usize, table: usize) -> &[u64] {
+        let index = self.game_index(round, table);
+        &self.matches[index..index + self.words]
+    }
+
+    /**Add the player to the game if they are not in it, otherwise remove them. Does not update player_positions*/
+    fn toggle_player(&mut self, round: usize, table: usize, player: usize) {
+        let index = self.game_index(round, table) + player / 64;
+        *self.matches.index_mut(index) ^= 1 << (player % 64);
+    }
+
+    /**Flip whether player is in the team of the game, which is round * self.tables + table*/
+    fn toggle_team(&mut self, game: usize, team: usize, player: usize) {
+        let index = (game * self.teams + team) * self.words + player / 64;
+        self.team_matches[index] ^= 1 << (player % 64);
+    }
+
+    /**Swap player1 at table1 with player2 at table2 in the specified round, updating player_positions and the unchanged positions cache but not the other caches*/
+    fn swap_players(
+        &mut self,
+        round: usize,
+        table1: usize,
+        table2: usize,
+        player1: usize,
+        player2: usize,
+    ) {
+        self.toggle_player(round, table1, player1);
+        self.toggle_player(round, table1, player2);
+        self.toggle_player(round, table2, player2);
+        self.toggle_player(round, table2, player1);
+        // Undoing a swap passes the same tables again, so use where the players actually are
+        let position1 = self.player_positions[player1 * self.rounds + round];
+        let position2 = self.player_positions[player2 * self.rounds + round];
+        if self.teams > 1 {
+            // Each player takes the place of the other in their team
+            let index1 = player1 * self.rounds + round;
+            let index2 = player2 * self.rounds + round;
+            let team1 = usize::from(self.player_teams[index1]);
+            let team2 = usize::from(self.player_teams[index2]);
+            self.toggle_team(position1 as usize, team1, player1);
+            self.toggle_team(position2 as usize, team2, player1);
+            self.toggle_team(position2 as usize, team2, player2);
+            self.toggle_team(position1 as usize, team1, player2);
+            self.player_teams.swap(index1, index2);
+        }
+        self.player_positions
+            .swap(player1 * self.rounds + round, player2 * self.rounds + round);
+        self.track_unchanged(player1, round, position1, position2);
+        self.track_unchanged(player2, round, position2, position1);
+    }
+
+    /**Move player from one table to another in the specified round, updating player_positions and the unchanged positions cache but not the other caches*/
+    fn relocate_player(&mut self, round: usize, from: usize, to: usize, player: usize) {
+        self.toggle_player(round, from, player);
+        self.toggle_player(round, to, player);
+        self.player_positions[player * self.rounds + round] = (round * self.tables + to) as u32;
+        self.track_unchanged(
+            player,
+            round,
+            (round * self.tables + from) as u32,
+            (round * self.tables + to) as u32,
+        );
+    }
+
+    /**Swap two players at the same table between their teams in the specified round, updating the teams but not the caches*/
+    fn swap_teams(&mut self, round: usize, player1: usize, player2: usize) {
+        let index1 = player1 * self.rounds + round;
+        let index2 = player2 * self.rounds + round;
+        let game = self.player_positions[index1] as usize;
+        debug_assert_eq!(game, self.player_positions[index2] as usize);
+        let team1 = usize::from(self.player_teams[index1]);
+        let team2 = usize::from(self.player_teams[index2]);
+        self.toggle_team(game, team1, player1);
+        self.toggle_team(game, team2, player1);
+        self.toggle_team(game, team2, player2);
+        self.toggle_team(game, team1, player2);
+        self.player_teams.swap(index1, index2);
+    }
+
+    fn apply_move(&mut self, round: usize, current_move: Move) {
+        match current_move {
+            Move::Swap {
+                player1,
+                table1,
+                player2,
+                table2,
+            } => self.swap_players(round, table1, table2, player1, player2),
+            Move::Relocate { player, from, to } => self.relocate_player(round, from, to, player),
+            Move::SwapTeams {
+                player1, player2, ..
+            } => self.swap_teams(round, player1, player2),
+            Move::Cycle {
+                player1,
+                table1,
+                player2,
+                table2,
+                player3,
+                table3,
+            } => {
+                // Two swaps, so players in teams take each other's places
+                self.swap_players(round, table1, table2, player1, player2);
+                self.swap_players(round, table1, table3, player2, player3);
+            }
+            Move::SwapGroups {
+                group1,
+                table1,
+                group2,
+                table2,
+                size,
+            } => {
+                for (&player1, &player2) in group1[..size].iter().zip(&group2[..size]) {
+                    self.swap_players(round, table1, table2, player1, player2);
+                }
+            }
+            Move::ExchangeRounds {
+                player,
+                from,
+                to,
+                round2,
+            } => {
+                self.relocate_player(round, from, to, player);
+                self.relocate_player(round2, to, from, player);
+            }
+        }
+    }
+
+    /**The games which the move changes, as indices of round * self.tables + table*/
+    fn move_games(&self, round: usize, chosen: Move) -> Vec<usize> {
+        let game = |table: usize| round * self.tables + table;
+        match chosen {
+            Move::Swap { table1, table2, .. } | Move::SwapGroups { table1, table2, .. } => {
+                vec![game(table1), game(table2)]
+            }
+            Move::Relocate { from, to, .. } => vec![game(from), game(to)],
+            Move::SwapTeams { table, .. } => vec![game(table)],
+            Move::Cycle {
+                table1,
+                table2,
+                table3,
+                ..
+            } => vec![game(table1), game(table2), game(table3)],
+            Move::ExchangeRounds {
+                from, to, round2, ..
+            } => vec![
+                game(from),
+                game(to),
+                round2 * self.tables + from,
+                round2 * self.tables + to,
+            ],
+        }
+    }
+
+    /**Count the total number of unique games played by all players. Uses mut self to allow updating cache
+     */
+    #[inline(never)]
+    pub fn find_unique_games_played(&mut self) -> u32 {
+        assert!(self.matches.len() >= self.rounds * self.tables * self.words);
+        // Check that the unchecked_get won't read a value off the end of the array
+        let mut total: u32 = 0;
+        if self.words == 1 {
+            // Fast path for when every match fits in a single u64
+            for table1 in (0..(self.tables / 2 * 2)).step_by(2) {
+                let table2 = table1 + 1;
+                let mut total_table: u128 = unsafe {
+                    std::mem::transmute::<[u64; 2], u128>([
+                        self.history_games_word(table1, 0),
+                        self.history_games_word(table2, 0),
+                    ])
+                };
+                for round in 0..self.rounds {
+                    let base = round * self.tables;
+                    total_table |= unsafe {
+                        // Treat pairs of u64 as u128 to enable faster bitwise AND
+                        std::mem::transmute::<[u64; 2], u128>([
+                            *self.matches.get_unchecked(base + table1), // Using get_unchecked since it gave ~10% improvement
+                            *self.matches.get_unchecked(base + table2),
+                        ])
+                    };
+                }
+                total += total_table.count_ones();
+            }
+            if self.tables % 2 == 1 {
+                // Deal with the number of tables not being an even number
+                let mut total_table = self.history_games_word(self.tables - 1, 0);
+                for round in 0..self.rounds {
+                    total_table |= self.matches[round * self.tables + self.tables - 1];
+                }
+                total += total_table.count_ones();
+            }
+        } else {
+            for table in 0..self.tables {
+                for word in 0..self.words {
+                    let mut total_table = self.history_games_word(table, word);
+                    for round in 0..self.rounds {
+                        total_table |= self.matches[self.game_index(round, table) + word];
+                    }
+                    total += total_table.count_ones();
+                }
+            }
+        }
+        debug_assert!(
+            // Less optimised version used to check that above code is correct
+            total == {
+                let mut total2: u32 = 0;
+                for table in 0..self.tables {
+                    let mut players: Vec<bool> = (0..self.player_count)
+                        .map(|player| {
+                            self.history_games_word(table, player / 64) & (1 << (player % 64)) != 0
+                        })
+                        .collect();
+                    for round in 0..self.rounds {
+                        for player in self.get_players_from_game(round, table) {
+                            players[player] = true;
+                        }
+                    }
+                    total2 += players.iter().filter(|&&played| played).count() as u32;
+                }
+                total2
+            }
+        );
+        self.unique_games_played_cache = total;
+        total
+    }
+    /** Use cached results to get total number of unique games played */
+    pub const fn unique_games_played(&self) -> u32 {
+        self.unique_games_played_cache
+    }
+    /** Count the number of opponents specified player has been in a match with.
+     */
+    fn player_unique_opponents(&mut self, player: usize) -> u16 {
+        if !self.pair_history.is_empty() {
+            self.player_repeated_pairs(player);
+        }
+        if self.teams > 1 {
+            return self.player_unique_team_opponents(player);
+        }
+        // Take a bitwise OR on all games specified player was in, and then count the ones to get total unique players
+        let positions =
+            &self.player_positions[player * self.rounds..player * self.rounds + self.rounds];
+        let count = if self.words == 1 {
+            positions
+                .iter()
+                .map(|&index| self.matches[index as usize])
+                .fold(self.history_opponents_word(player, 0), |acc, round| {
+                    acc | round
+                })
+                .count_ones()
+        } else {
+            (0..self.words)
+                .map(|word| {
+                    positions
+                        .iter()
+                        .map(|&index| self.matches[index as usize * self.words + word])
+                        .fold(self.history_opponents_word(player, word), |acc, round| {
+                            acc | round
+                        })
+                        .count_ones()
+                })
+                .sum()
+        } as u16
+            - 1;
+        self.player_opponent_cache[player] = count;
+        count
+    }
+    /** Sum the pair history of specified player with everyone they are at a table with in this schedule, and cache the result*/
+    fn player_repeated_pairs(&mut self, player: usize) {
+        let positions =
+            &self.player_positions[player * self.rounds..player * self.rounds + self.rounds];
+        let history = &self.pair_history[player * self.player_count..][..self.player_count];
+        let mut total = 0;
+        for word in 0..self.words {
+            let mut met = positions
+                .iter()
+                .map(|&index| self.matches[index as usize * self.words + word])
+                .fold(0, |acc, game| acc | game);
+            if word == player / 64 {
+                met &= !(1 << (player % 64));
+            }
+            while met != 0 {
+                let other = met.trailing_zeros();
+                met -= 1 << other;
+                total += history[word * 64 + other as usize];
+            }
+        }
+        self.player_repeat_cache[player] = total;
+    }
+
+    /** Count the number of opponents, in other teams, and partners, in the same team, specified player has been in a match with when playing in teams.
+    Caches both, and returns the number of opponents*/
+    fn player_unique_team_opponents(&mut self, player: usize) -> u16 {
+        let positions = player * self.rounds..player * self.rounds + self.rounds;
+        let mut opponents = 0;
+        let mut partners = 0;
+        for word in 0..self.words {
+            let mut opponent_bits = self.history_opponents_word(player, word);
+            let mut partner_bits = 0;
+            for index in positions.clone() {
+                let game = self.player_positions[index] as usize;
+                let team = usize::from(self.player_teams[index]);
+                let team_bits = self.team_matches[(game * self.teams + team) * self.words + word];
+                opponent_bits |= self.matches[game * self.words + word] & !team_bits;
+                partner_bits |= team_bits;
+            }
+            if word == player / 64 {
+                opponent_bits &= !(1 << (player % 64));
+                partner_bits &= !(1 << (player % 64));
+            }
+            opponents += opponent_bits.count_ones();
+            partners += partner_bits.count_ones();
+        }
+        self.player_opponent_cache[player] = opponents as u16;
+        self.player_partner_cache[player] = partners as u16;
+        opponents as u16
+    }
+    /** Count the number of opponents each player has been in a match with
+     */
+    pub fn find_unique_opponents(&mut self) -> u32 {
+        let mut total: u32 = 0;
+        for player in 0..self.player_count {
+            total += u32::from(self.player_unique_opponents(player));
+        }
+        self.unique_opponent_sum_cache = total;
+        self.sum_unique_partners();
+        self.sum_repeated_pairs();
+        total
+    }
+    /** Take the sum of the cached number of opponents each player has, and cache the result*/
+    fn sum_unique_opponent(&mut self) {
+        self.unique_opponent_sum_cache = self
+            .player_opponent_cache
+            .iter()
+            .map(|&val| u32::from(val))
+            .sum::<u32>();
+        self.sum_unique_partners();
+        self.sum_repeated_pairs();
+    }
+
+    /** Take the sum of the cached number of partners each player has, and cache the result*/
+    fn sum_unique_partners(&mut self) {
+        self.unique_partner_sum_cache = self
+            .player_partner_cache
+            .iter()
+            .map(|&val| u32::from(val))
+            .sum::<u32>();
+    }
+
+    /** Take the sum of the cached pair history of each player, and cache the result*/
+    fn sum_repeated_pairs(&mut self) {
+        self.repeated_pairs_cache = self.player_repeat_cache.iter().sum();
+    }
+
+    /** Use cached results to get the total pair history of players who are at a table together, counted once for each of the two players.
+    0 when there is no pair history*/
+    pub const fn repeated_pairs(&self) -> u32 {
+        self.repeated_pairs_cache
+    }
+
+    /**Cached pair history of the player with their opponents, 0 when there is no pair history*/
+    fn player_repeated_pairs_cache(&self, player: usize) -> u32 {
+        self.player_repeat_cache.get(player).copied().unwrap_or(0)
+    }
+
+    /** Use cached results to get total number of unique partners, 0 when players don't play in teams */
+    pub const fn unique_partners(&self) -> u32 {
+        self.unique_partner_sum_cache
+    }
+
+    /**Cached number of unique partners of the player, 0 when players don't play in teams*/
+    fn player_partners(&self, player: usize) -> u32 {
+        self.player_partner_cache
+            .get(player)
+            .map_or(0, |&partners| u32::from(partners))
+    }
+
+    /** Use cached results to get total number of unique opponents */
+    pub const fn unique_opponents(&self) -> u32 {
+        self.unique_opponent_sum_cache
+    }
+
+    fn find_min_unique_opponents(&mut self) -> u32 {
+        self.unique_opponent_min_cache =
+            *self.player_opponent_cache.iter().min().unwrap_or(&0) as u32;
+        self.unique_opponent_min_cache
+    }
+
+    pub const fn min_unique_opponents(&self) -> u32 {
+        self.unique_opponent_min_cache
+    }
+
+    /** Get the number of tables*/
+    pub const fn get_tables(&self) -> usize {
+        self.tables
+    }
+    /** Get the number of rounds*/
+    pub const fn get_rounds(&self) -> usize {
+        self.rounds
+    }
+    /** Get the minimum and maximum number of players at each table*/
+    pub fn get_capacities(&self) -> &[(usize, usize)] {
+        &self.capacities
+    }
+    /** Get the number of players */
+    pub const fn get_player_count(&self) -> usize {
+        self.player_count
+    }
+
+    /** Get a vector of all players in the game at specified round and table
+     */
+    pub fn get_players_from_game(&self, round: usize, table: usize) -> Vec<usize> {
+        debug_assert!(round < self.rounds);
+        debug_assert!(table < self.tables);
+        let game = self.get(round, table);
+        let game_size = game.iter().map(|word| word.count_ones()).sum::<u32>() as usize;
+        let mut players: Vec<usize> = Vec::with_capacity(game_size);
+        for (word_number, &word) in game.iter().enumerate() {
+            let mut word = word;
+            while word != 0 {
+                let player = word.trailing_zeros();
+                word -= 1 << player;
+                players.push(word_number * 64 + player as usize);
+            }
+        }
+        players
+    }
+
+    /** Get the players in each team of the game at specified round and table, the whole table is one team when players don't play in teams
+     */
+    pub fn get_teams_from_game(&self, round: usize, table: usize) -> Vec<Vec<usize>> {
+        if self.teams == 1 {
+            return vec![self.get_players_from_game(round, table)];
+        }
+        (0..self.teams)
+            .map(|team| {
+                let index = ((round * self.tables + table) * self.teams + team) * self.words;
+                let mut players = Vec::new();
+                for (word_number, &word) in self.team_matches[index..index + self.words]
+                    .iter()
+                    .enumerate()
+                {
+                    let mut word = word;
+                    while word != 0 {
+                        let player = word.trailing_zeros();
+                        word -= 1 << player;
+                        players.push(word_number * 64 + player as usize);
+                    }
+                }
+                players
+            })
+            .collect()
+    }
+    /** Calculate score and cache results*/
+    pub fn generate_score(&mut self) -> u64 {
+        self.find_unique_opponents();
+        self.find_min_unique_opponents();
+        self.find_unique_games_played();
+        self.find_unchanged_positions();
+        self.find_table_imbalance();
+        self.find_constraint_violations();
+        self.get_score()
+    }
+
+    /** Get score using cached results */
+    pub fn get_score(&self) -> u64 {
+        self.constrained(
+            self.objective.score(&self.metrics()),
+            self.constraint_violations(),
+        )
+    }
+
+    /** Rank schedules which break fewer constraints ahead of those which break more, then by the objective's score.
+    The score is unchanged when there are no constraints*/
+    fn constrained(&self, score: u64, violations: u32) -> u64 {
+        if self.max_violations == 0 {
+            score
+        } else {
+            let bound = self.objective_bound();
+            u64::from(self.max_violations - violations) * (bound + 1) + score.min(bound)
+        }
+    }
+
+    /** Get the cached results which the objective uses to calculate the score */
+    pub fn metrics(&self) -> Metrics<'_> {
+        Metrics {
+            player_count: self.player_count,
+            unique_opponents: self.unique_opponents(),
+            min_unique_opponents: self.min_unique_opponents(),
+            unique_games_played: self.unique_games_played(),
+            ideal_unique_opponents: self.ideal_unique_opponents,
+            ideal_unique_games: self.ideal_unique_games,
+            unique_partners: self.unique_partners(),
+            ideal_unique_partners: self.ideal_unique_partners,
+            repeated_pairs: self.repeated_pairs(),
+            table_imbalance: self.table_imbalance(),
+            player_opponents: &self.player_opponent_cache,
+            unchanged_positions: self.unchanged_positions(),
+        }
+    }
+
+    /** Upper bound on the score of any schedule of this size, from the metrics at their ideal values and without penalties or broken constraints.
+    No schedule can be better once this is reached, though it may not be reachable*/
+    pub fn score_bound(&self) -> u64 {
+        self.constrained(self.objective_bound(), 0)
+    }
+
+    /** Score_bound for the objective alone, without constraints*/
+    fn objective_bound(&self) -> u64 {
+        self.objective.score(&Metrics {
+            player_count: self.player_count,
+            unique_opponents: self.ideal_unique_opponents,
+            // The minimum can't be more than the average
+            min_unique_opponents: self.ideal_unique_opponents / self.player_count as u32,
+            unique_games_played: self.ideal_unique_games,
+            ideal_unique_opponents: self.ideal_unique_opponents,
+            ideal_unique_games: self.ideal_unique_games,
+            unique_partners: self.ideal_unique_partners,
+            ideal_unique_partners: self.ideal_unique_partners,
+            repeated_pairs: 0,
+            table_imbalance: 0,
+            // No players at the minimum and no spread between players, which is at least as good as any real schedule
+            player_opponents: &[],
+            unchanged_positions: if self.original_positions.is_empty() {
+                0
+            } else {
+                (self.player_count * self.rounds) as u32
+            },
+        })
+    }
+
+    /** Change how schedules are scored, the default is objective::Weighted::default() */
+    pub fn set_objective(&mut self, objective: Arc<dyn Objective>) {
+        self.objective = objective;
+    }
+
+    /**Find which of the moves between the two tables maximises the score, see moves for which are considered.
+    Relocations are only considered when both tables stay within their capacities.
+    Returns (best found score, total unique games played, number of evaluated moves).
+     If apply is true then it applies the found optimal, otherwise self should be unchanged*/
+    pub fn improve_table(
+        &mut self,
+        old_score: u64,
+        round: usize,
+        table1: usize,
+        table2: usize,
+        apply: bool,
+    ) -> (u64, u32, u32) {
+        debug_assert!(old_score == self.get_score());
+        let old_unique_games_played = self.unique_games_played();
+        let (best, ops) = self.best_move(round, table1, table2, |_, _| true);
+        match best {
+            Some((best_move, score, unique_games_played))
+                if score > old_score
+                    || (score == old_score && unique_games_played > old_unique_games_played) =>
+            {
+                if apply {
+                    self.make_move(round, best_move);
+                }
+                (score, unique_games_played, ops)
+            }
+            _ => (old_score, old_unique_games_played, ops),
+        }
+    }
+
+    /**Find the move between the two tables in the round which maximises the score, out of the moves that filter allows.
+    The filter is given each move along with the score it would result in. Unlike improve_table, the move may make the schedule worse.
+    Returns the move with the score and total unique games played it results in, and the number of evaluated moves. self is unchanged*/
+    pub fn best_move<F: Fn(Move, u64) -> bool>(
+        &mut self,
+        round: usize,
+        table1: usize,
+        table2: usize,
+        filter: F,
+    ) -> (Option<(Move, u64, u32)>, u32) {
+        debug_assert!(self.get_score() == self.generate_score()); // check that cache is updated
+        let mut best: Option<(Move, u64, u32)> = None;
+        let mut evaluated = 0;
+        for (games, moves) in self.move_groups(round, table1, table2) {
+            self.evaluate_moves(round, &games, &moves, &filter, &mut best);
+            evaluated += moves.len() as u32;
+        }
+        (best, evaluated)
+    }
+
+    /**Evaluate each of the moves in the round, which must only change the players at the games, keeping the best one that filter allows in best.
+    The games are indices of round * self.tables + table. The caches are regenerated afterwards, so self is unchanged*/
+    fn evaluate_moves<F: Fn(Move, u64) -> bool>(
+        &mut self,
+        round: usize,
+        games: &[usize],
+        moves: &[Move],
+        filter: &F,
+        best: &mut Option<(Move, u64, u32)>,
+    ) {
+        let mut affected = vec![false; self.player_count];
+        let mut affected_players: Vec<usize> = Vec::new();
+        for &game in games {
+            for player in self.get_players_from_game(game / self.tables, game % self.tables) {
+                if !affected[player] {
+                    affected[player] = true;
+                    affected_players.push(player);
+                }
+            }
+        }
 
         let (other_unique_games_played_sum, other_unique_games_played_min) = {
             let mut other_unique_games_played_sum: u32 = 0;
             let mut other_unique_games_played_min: u32 = std::u32::MAX;
-            let other_players: u64 = original_t1 | original_t2;
-            debug_assert!(other_players != 0);
-            for player in 0..self.player_count {
-                let player_number = 1 << player;
-                if other_players & player_number == 0 {
-                    debug_assert!(!t1_players.contains(&player));
-                    debug_assert!(!t2_players.contains(&player));
-                    let opponent_count = self.player_opponent_cache[player] as u32;
-                    debug_assert!(opponent_count > 0);
-                    other_unique_games_played_sum += opponent_count;
-                    if opponent_count < other_unique_games_played_min {
-                        other_unique_games_played_min = opponent_count;
-                    }
+            for player in (0..self.player_count).filter(|&player| !affected[player]) {
+                let opponent_count = u32::from(self.player_opponent_cache[player]);
+                debug_assert!(opponent_count > 0);
+                other_unique_games_played_sum += opponent_count;
+                if opponent_count < other_unique_games_played_min {
+                    other_unique_games_played_min = opponent_count;
                 }
             }
             (other_unique_games_played_sum, other_unique_games_played_min)
         };
 
-        let mut new_player_opponent_cache = self.player_opponent_cache.clone();
-        for player1 in &t1_players {
-            let player_number1: u64 = 1_u64 << player1;
-            for player2 in &t2_players {
-                let player_number2: u64 = 1_u64 << player2;
+        let other_partner_sum = self.unique_partners()
+            - affected_players
+                .iter()
+                .map(|&player| self.player_partners(player))
+                .sum::<u32>();
+        let other_repeat_sum = self.repeated_pairs()
+            - affected_players
+                .iter()
+                .map(|&player| self.player_repeated_pairs_cache(player))
+                .sum::<u32>();
+
+        for &current_move in moves {
+            self.apply_move(round, current_move);
+            let mut current_sum = other_unique_games_played_sum;
+            let mut current_min = other_unique_games_played_min;
+            let mut current_partner_sum = other_partner_sum;
+            let mut current_repeat_sum = other_repeat_sum;
+            // Regenerate results for players in the changed games, since they are the only affected players
+            for &p in &affected_players {
+                let opponent_count = u32::from(self.player_unique_opponents(p));
+                current_sum += opponent_count;
+                if opponent_count < current_min {
+                    current_min = opponent_count;
+                }
+                current_partner_sum += self.player_partners(p);
+                current_repeat_sum += self.player_repeated_pairs_cache(p);
+            }
+            self.unique_opponent_sum_cache = current_sum;
+            self.unique_opponent_min_cache = current_min;
+            self.unique_partner_sum_cache = current_partner_sum;
+            self.repeated_pairs_cache = current_repeat_sum;
+            for &game in games {
+                self.update_game_imbalance(game);
+                self.update_game_constraints(game);
+            }
+            let new_unique_games_played = self.find_unique_games_played();
+            let new_score = self.get_score();
+            debug_assert!(new_score == self.generate_score()); // Check that cache still represents most recent data
+            self.apply_move(round, current_move.inverse()); // Move players back to original position
+            let better = match best {
+                Some((_, score, unique_games_played)) => {
+                    new_score > *score
+                        || (new_score == *score && new_unique_games_played > *unique_games_played)
+                }
+                None => true,
+            };
+            if better && filter(current_move, new_score) {
+                *best = Some((current_move, new_score, new_unique_games_played));
+            }
+        }
+        self.update_caches(games);
+    }
+
+    /**Every move between the two tables in the round which keeps every table within its capacity.
+    These are swaps and relocations, plus the moves that the neighbourhood adds. Cycles also use a third table after both of the two tables, so each cycle is only offered for one pair of tables.
+    When playing in teams, relocations are replaced by swaps between the teams at each of the two tables, so team sizes don't change*/
+    pub fn moves(&self, round: usize, table1: usize, table2: usize) -> Vec<Move> {
+        self.move_groups(round, table1, table2)
+            .into_iter()
+            .flat_map(|(_, moves)| moves)
+            .collect()
+    }
+
+    /**The moves offered by moves, grouped by the games they change, which are indices of round * self.tables + table*/
+    fn move_groups(
+        &self,
+        round: usize,
+        table1: usize,
+        table2: usize,
+    ) -> Vec<(Vec<usize>, Vec<Move>)> {
+        let game = |round: usize, table: usize| round * self.tables + table;
+        let t1_players: Vec<usize> = self.get_players_from_game(round, table1);
+        let t2_players: Vec<usize> = self.get_players_from_game(round, table2);
+        let mut moves: Vec<Move> =
+            Vec::with_capacity((t1_players.len() + 1) * (t2_players.len() + 1));
+        for &player1 in &t1_players {
+            for &player2 in &t2_players {
                 // Swap 1 player from each of the two tables
-                *self.get_mut(round, table1) = original_t1 - player_number1 + player_number2;
-                *self.get_mut(round, table2) = original_t2 - player_number2 + player_number1;
-                self.player_positions
-                    .swap(player1 * self.tables + round, player2 * self.tables + round);
-                let mut current_sum = other_unique_games_played_sum;
-                let mut current_min = other_unique_games_played_min;
-                // Regenerate results for players in those two tables, since they are the only affected players
-                for p in t1_players.iter().chain(t2_players.iter()) {
-                    let opponent_count = self.player_unique_opponents(*p) as u32;
-                    current_sum += opponent_count;
-                    if opponent_count < current_min {
-                        current_min = opponent_count;
+                moves.push(Move::Swap {
+                    player1,
+                    table1,
+                    player2,
+                    table2,
+                });
+            }
+        }
+        let size = self.neighbourhood.group_size;
+        if size > 1 && table1 != table2 {
+            for group1 in groups_of(&t1_players, size) {
+                for group2 in groups_of(&t2_players, size) {
+                    moves.push(Move::SwapGroups {
+                        group1,
+                        table1,
+                        group2,
+                        table2,
+                        size,
+                    });
+                }
+            }
+        }
+        if self.teams > 1 {
+            for &(table, players) in &[(table1, &t1_players), (table2, &t2_players)] {
+                for (i, &player1) in players.iter().enumerate() {
+                    for &player2 in &players[i + 1..] {
+                        if self.player_teams[player1 * self.rounds + round]
+                            != self.player_teams[player2 * self.rounds + round]
+                        {
+                            moves.push(Move::SwapTeams {
+                                player1,
+                                player2,
+                                table,
+                            });
+                        }
+                    }
+                }
+            }
+        } else {
+            for &(from, to, from_players, to_size) in &[
+                (table1, table2, &t1_players, t2_players.len()),
+                (table2, table1, &t2_players, t1_players.len()),
+            ] {
+                // Relocate 1 player from one table to the other, if both tables stay within capacity
+                if from_players.len() > self.capacities[from].0 && to_size < self.capacities[to].1 {
+                    for &player in from_players.iter() {
+                        moves.push(Move::Relocate { player, from, to });
+                    }
+                }
+            }
+        }
+        let mut games = vec![game(round, table1)];
+        if table2 != table1 {
+            games.push(game(round, table2));
+        }
+        let mut groups = vec![(games, moves)];
+        if table1 == table2 {
+            return groups;
+        }
+
+        if self.neighbourhood.cycles {
+            for table3 in (table1.max(table2) + 1)..self.tables {
+                let t3_players = self.get_players_from_game(round, table3);
+                let mut cycles =
+                    Vec::with_capacity(2 * t1_players.len() * t2_players.len() * t3_players.len());
+                for &player1 in &t1_players {
+                    for &player2 in &t2_players {
+                        for &player3 in &t3_players {
+                            // Both ways around the three tables
+                            cycles.push(Move::Cycle {
+                                player1,
+                                table1,
+                                player2,
+                                table2,
+                                player3,
+                                table3,
+                            });
+                            cycles.push(Move::Cycle {
+                                player1,
+                                table1,
+                                player2: player3,
+                                table2: table3,
+                                player3: player2,
+                                table3: table2,
+                            });
+                        }
                     }
                 }
-                self.unique_opponent_sum_cache = current_sum;
-                self.unique_opponent_min_cache = current_min;
-                let new_unique_games_played = self.find_unique_games_played();
-                let new_score = self.get_score();
-                debug_assert!(new_score == self.generate_score()); // Check that cache still represents most recent data
-                self.player_positions
-                    .swap(player1 * self.tables + round, player2 * self.tables + round); // Swap players back to original position
-                if new_score > score
-                    || (new_score == score && new_unique_games_played > unique_games_played)
+                groups.push((
+                    vec![
+                        game(round, table1),
+                        game(round, table2),
+                        game(round, table3),
+                    ],
+                    cycles,
+                ));
+            }
+        }
+
+        if self.neighbourhood.round_exchanges && self.teams == 1 {
+            let size = |round: usize, table: usize| {
+                self.get(round, table)
+                    .iter()
+                    .map(|word| word.count_ones() as usize)
+                    .sum::<usize>()
+            };
+            for round2 in (0..self.rounds).filter(|&round2| round2 != round) {
+                let mut exchanges = Vec::new();
+                for &(from, to, from_players) in
+                    &[(table1, table2, &t1_players), (table2, table1, &t2_players)]
                 {
-                    best_t1 = original_t1 - player_number1 + player_number2;
-                    best_t2 = original_t2 - player_number2 + player_number1;
-                    best_player1 = *player1;
-                    best_player2 = *player2;
-                    score = new_score;
-                    unique_games_played = new_unique_games_played;
-                    if apply {
-                        new_player_opponent_cache = self.player_opponent_cache.clone();
+                    // The player leaves from and joins to in this round, and the other way around in round2
+                    if size(round, from) > self.capacities[from].0
+                        && size(round, to) < self.capacities[to].1
+                        && size(round2, to) > self.capacities[to].0
+                        && size(round2, from) < self.capacities[from].1
+                    {
+                        for &player in from_players.iter() {
+                            if self.player_positions[player * self.rounds + round2] as usize
+                                == game(round2, to)
+                            {
+                                exchanges.push(Move::ExchangeRounds {
+                                    player,
+                                    from,
+                                    to,
+                                    round2,
+                                });
+                            }
+                        }
                     }
                 }
+                if !exchanges.is_empty() {
+                    groups.push((
+                        vec![
+                            game(round, table1),
+                            game(round, table2),
+                            game(round2, table1),
+                            game(round2, table2),
+                        ],
+                        exchanges,
+                    ));
+                }
             }
         }
-        self.player_opponent_cache = new_player_opponent_cache;
-        if apply {
-            *self.get_mut(round, table1) = best_t1;
-            *self.get_mut(round, table2) = best_t2;
-            if score > old_score {
-                self.player_positions.swap(
-                    best_player1 * self.tables + round,
-                    best_player2 * self.tables + round,
-                );
+        groups
+    }
+
+    /**Pick a random move between two random tables in a random round, out of those offered by moves. Returns the round and the move*/
+    pub fn random_move<T: rand::Rng + rand_core::RngCore>(&self, rng: &mut T) -> (usize, Move) {
+        loop {
+            let round = rng.gen_range(0..self.rounds);
+            let table1 = rng.gen_range(0..self.tables);
+            let table2 = (table1 + rng.gen_range(1..self.tables)) % self.tables;
+            if let Some(&chosen) = self.moves(round, table1, table2).choose(rng) {
+                return (round, chosen);
+            }
+        }
+    }
+
+    /**Apply the move, and update the caches. Returns the new score*/
+    pub fn make_move(&mut self, round: usize, chosen: Move) -> u64 {
+        self.apply_move(round, chosen);
+        let games = self.move_games(round, chosen);
+        self.update_caches(&games);
+        self.get_score()
+    }
+
+    /**Regenerate caches for the players in the games, which are indices of round * self.tables + table, and the sum caches*/
+    fn update_caches(&mut self, games: &[usize]) {
+        for &game in games {
+            for p in self.get_players_from_game(game / self.tables, game % self.tables) {
+                self.player_unique_opponents(p);
             }
-            self.unique_games_played_cache = unique_games_played;
-        } else {
-            // Restore matches to previous state
-            *self.get_mut(round, table1) = original_t1;
-            *self.get_mut(round, table2) = original_t2;
-            self.unique_games_played_cache = old_unique_games_played;
         }
+        self.find_unique_games_played();
         self.sum_unique_opponent();
         self.find_min_unique_opponents();
-        // Regenerate sum caches
+        for &game in games {
+            self.update_game_imbalance(game);
+            self.update_game_constraints(game);
+        }
         debug_assert!(self.get_score() == self.generate_score()); // Check that cache still represents most recent data
-        (
-            score,
-            unique_games_played,
-            (t1_players.len() * t2_players.len()) as u32,
-        )
     }
+
+    /** Change which moves are offered besides swaps and relocations. Panics if the group size is more than MAX_GROUP_SIZE*/
+    pub fn set_neighbourhood(&mut self, neighbourhood: Neighbourhood) {
+        assert!(neighbourhood.group_size <= MAX_GROUP_SIZE);
+        self.neighbourhood = neighbourhood;
+    }
+
+    pub const fn get_neighbourhood(&self) -> Neighbourhood {
+        self.neighbourhood
+    }
+
+    /** Claim that the schedule reaches the ideal scores, used to check schedules loaded as ideal.
+    Validation counts everyone at a table as an opponent, so there is no claim on opponents when playing in teams*/
+    pub fn ideal_claim(&self) -> validate::Claim {
+        validate::Claim {
+            unique_opponents: if self.teams > 1 {
+                None
+            } else {
+                Some(self.ideal_unique_opponents)
+            },
+            unique_games_played: Some(self.ideal_unique_games),
+            ..validate::Claim::default()
+        }
+    }
+    /**How many times each pair of players meet, indexed by player1 * player_count + player2*/
+    fn meeting_counts(&self) -> Vec<u16> {
+        let mut counts = vec![0; self.player_count * self.player_count];
+        for round in 0..self.rounds {
+            for table in 0..self.tables {
+                let players = self.get_players_from_game(round, table);
+                for &player1 in &players {
+                    for &player2 in &players {
+                        counts[player1 * self.player_count + player2] += 1;
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    /** Number of pairs of players who meet a different number of times in the two schedules, used to measure how different they are.
+    Relabelling tables or reordering rounds doesn't change the distance. Both schedules should have the same player count*/
+    pub fn distance(&self, other: &Schedule) -> u32 {
+        debug_assert_eq!(self.player_count, other.player_count);
+        let counts = self.meeting_counts();
+        let other_counts = other.meeting_counts();
+        let mut distance = 0;
+        for player1 in 0..self.player_count {
+            for player2 in (player1 + 1)..self.player_count {
+                let index = player1 * self.player_count + player2;
+                if counts[index] != other_counts[index] {
+                    distance += 1;
+                }
+            }
+        }
+        distance
+    }
+
     /** Check if the schedule has entirely met all criteria*/
     pub fn is_ideal(&self) -> bool {
         self.unique_opponents() == self.ideal_unique_opponents
             && self.unique_games_played() == self.ideal_unique_games
+            && self.unique_partners() == self.ideal_unique_partners
+            && self.constraint_violations() == 0
     }
 }
 
+/** Whether a schedule should replace the best schedule found so far. Ties in score are broken by total unique games played,
+and a schedule is never kept if it has fewer unique games played than the best, unless it breaks fewer constraints*/
+pub(crate) fn is_better(score: u64, schedule: &Schedule, best_score: u64, best: &Schedule) -> bool {
+    (score > best_score
+        || (score == best_score && schedule.unique_games_played() > best.unique_games_played()))
+        && (schedule.unique_games_played() >= best.unique_games_played()
+            || schedule.constraint_violations() < best.constraint_violations())
+}
+
 /** Wrapper around schedule that handles randomly generating new schedules and using local optimisation to try and find better schedules */
 pub struct Generator<T: rand::Rng + rand_core::RngCore> {
     /**The number of players*/
     player_count: usize,
     /**The number of tables, since one table per game, same as number of games*/
     tables: usize,
+    /**The number of rounds*/
+    rounds: usize,
     /**The best schedule found so far*/
     pub best: Schedule,
     /**The score of the best schedule found so far*/
-    pub best_score: u32,
+    pub best_score: u64,
     current: Schedule,
-    current_score: u32,
-    /**The best scoring round, table1, table2 combination that has been found in the current loop of changes*/
-    next: (usize, usize, usize),
-    /**The score of the best scoring schedule that has been found in the current loop of changes*/
-    next_score: u32,
-    /**The total number of unique games played of the best scoring schedule that has been found in the current loop of changes*/
-    next_unique_games: u32,
+    current_score: u64,
+    /**The local search method used to change the current schedule*/
+    strategy: Box<dyn Strategy>,
+    /**Where the strategy gets a new current schedule from when it restarts*/
+    restart: Restart,
     /**The rng object*/
     rng: T,
-    /** Current round in loop, between 0 and less than the number of tables*/
-    round: usize,
-    /**Current table1 in loop, between 0 and less than table2*/
-    table1: usize,
-    /**Current table1 in loop, between table1 and less than the number of tables*/
-    table2: usize,
 }
 
 impl<T: rand::Rng + rand_core::RngCore> Generator<T> {
-    /**Create a new Generator object with specified player count, table count, and rng. Panics if player count >= 64, or table count <= 2.
-    Initially contains a schedule generated via normal_fill*/
-    pub fn new(rng: T, player_count: usize, tables: usize) -> Self {
-        let mut best = Schedule::new(player_count, tables);
+    /**Create a new Generator object with specified player count, table count, round count, and rng. Panics if player count < 2 * table count, table count <= 2, or round count is 0.
+    Initially contains a schedule generated via normal_fill, and uses hill climbing*/
+    pub fn new(rng: T, player_count: usize, tables: usize, rounds: usize) -> Self {
+        let mut best = Schedule::new(player_count, tables, rounds);
+        best.normal_fill();
+        Self::from_schedule(rng, best)
+    }
+
+    /**Create a new Generator object with specified player count, (minimum, maximum) players at each table, round count, and rng.
+    Panics under the same conditions as Schedule::with_capacities. Initially contains a schedule generated via normal_fill, and uses hill climbing*/
+    pub fn with_capacities(
+        rng: T,
+        player_count: usize,
+        capacities: &[(usize, usize)],
+        rounds: usize,
+    ) -> Self {
+        let mut best = Schedule::with_capacities(player_count, capacities, rounds);
         best.normal_fill();
+        Self::from_schedule(rng, best)
+    }
+
+    /**Create a new Generator object from an empty schedule, such as one which plays in teams. Initially contains the schedule filled via normal_fill, and uses hill climbing*/
+    pub fn with_schedule(rng: T, mut schedule: Schedule) -> Self {
+        schedule.normal_fill();
+        Self::from_schedule(rng, schedule)
+    }
+
+    /**Create a new Generator object which starts from original instead of normal_fill, such as the saved schedule of an event that has been changed.
+    schedule gives the new dimensions, and is filled via Schedule::fill_from so original can have different dimensions.
+    Use an objective such as objective::MinimiseChanges to keep the result close to original*/
+    pub fn warm_start(rng: T, mut schedule: Schedule, original: &[Vec<Vec<usize>>]) -> Self {
+        schedule.fill_from(original);
+        Self::from_schedule(rng, schedule)
+    }
+
+    /**Create a new Generator object to re-plan an event part way through, such as when people leave or turn up.
+    event is the whole schedule of the event, in the same form as import_vec takes, and completed is the number of its rounds that have been played.
+    players gives, for each player in the new schedule, their number in event, or None if they have just joined.
+    The played rounds are frozen and counted via Schedule::set_history, and schedule gives the dimensions of the remaining rounds,
+    which start from the remaining rounds of event like warm_start*/
+    pub fn replan(
+        rng: T,
+        mut schedule: Schedule,
+        event: &[Vec<Vec<usize>>],
+        completed: usize,
+        players: &[Option<usize>],
+    ) -> Self {
+        let completed = completed.min(event.len());
+        let mut new_numbers: Vec<Option<usize>> = Vec::new();
+        for (new, &old) in players.iter().enumerate() {
+            if let Some(old) = old {
+                if old >= new_numbers.len() {
+                    new_numbers.resize(old + 1, None);
+                }
+                new_numbers[old] = Some(new);
+            }
+        }
+        // Renumber the players, dropping those who have left
+        let renumber = |rounds: &[Vec<Vec<usize>>]| -> Vec<Vec<Vec<usize>>> {
+            rounds
+                .iter()
+                .map(|round| {
+                    round
+                        .iter()
+                        .map(|players| {
+                            players
+                                .iter()
+                                .filter_map(|&player| new_numbers.get(player).copied().flatten())
+                                .collect()
+                        })
+                        .collect()
+                })
+                .collect()
+        };
+        schedule.set_history(&renumber(&event[..completed]));
+        Self::warm_start(rng, schedule, &renumber(&event[completed..]))
+    }
+
+    fn from_schedule(rng: T, mut best: Schedule) -> Self {
         let score = best.generate_score();
+        let mut strategy: Box<dyn Strategy> = Box::new(strategy::HillClimbing::default());
+        strategy.start(score);
         Self {
-            player_count,
-            tables,
+            player_count: best.get_player_count(),
+            tables: best.get_tables(),
+            rounds: best.get_rounds(),
             best: best.clone(),
             best_score: score,
             current: best,
             current_score: score,
-            next: (0, 0, 1),
-            next_score: score,
-            next_unique_games: 0,
+            strategy,
+            restart: Restart::default(),
             rng,
-            round: 0,
-            table1: 0,
-            table2: 0,
         }
     }
     /** Get the number of players*/
@@ -575,91 +2232,140 @@ impl<T: rand::Rng + rand_core::RngCore> Generator<T> {
     pub fn get_tables(&self) -> usize {
         self.tables
     }
+    /** Get the number of rounds */
+    pub fn get_rounds(&self) -> usize {
+        self.rounds
+    }
+
+    /** Get the score of the schedule the strategy is currently changing */
+    pub const fn get_current_score(&self) -> u64 {
+        self.current_score
+    }
 
-    /**Find next table pair swap to try and evaluate if improved
+    /** Penalise seating players together who met at previous events, rescoring the current and best schedules.
+    See Schedule::set_pair_history*/
+    pub fn set_pair_history(&mut self, history: &PairHistory) {
+        self.best.set_pair_history(history);
+        self.best_score = self.best.generate_score();
+        self.current.set_pair_history(history);
+        self.current_score = self.current.generate_score();
+        self.strategy.start(self.current_score);
+    }
+
+    /** Balance the strength of the tables using a rating for each player, rescoring the current and best schedules.
+    See Schedule::set_ratings*/
+    pub fn set_ratings(&mut self, ratings: &[f64]) {
+        self.best.set_ratings(ratings);
+        self.best_score = self.best.generate_score();
+        self.current.set_ratings(ratings);
+        self.current_score = self.current.generate_score();
+        self.strategy.start(self.current_score);
+    }
+
+    /** Set the players who must sit at a certain table and the pairs who must never share a table, rescoring the current and best schedules*/
+    pub fn set_constraints(&mut self, constraints: &Constraints) {
+        self.best.set_constraints(constraints);
+        self.best_score = self.best.generate_score();
+        self.current.set_constraints(constraints);
+        self.current_score = self.current.generate_score();
+        self.strategy.start(self.current_score);
+    }
+
+    /** Change how schedules are scored, rescoring the current and best schedules*/
+    pub fn set_objective(&mut self, objective: Arc<dyn Objective>) {
+        self.best.set_objective(objective.clone());
+        self.best_score = self.best.get_score();
+        self.current.set_objective(objective);
+        self.current_score = self.current.get_score();
+        self.strategy.start(self.current_score);
+    }
+
+    /** Change which moves are offered besides swaps and relocations, for the current and best schedules.
+    See Schedule::set_neighbourhood*/
+    pub fn set_neighbourhood(&mut self, neighbourhood: Neighbourhood) {
+        self.best.set_neighbourhood(neighbourhood);
+        self.current.set_neighbourhood(neighbourhood);
+    }
+
+    /** Change the local search method, the search continues from the current schedule*/
+    pub fn set_strategy(&mut self, strategy: Box<dyn Strategy>) {
+        self.strategy = strategy;
+        self.strategy.start(self.current_score);
+    }
+
+    /** Change where the strategy gets a new current schedule from when it restarts, the default is a random schedule*/
+    pub fn set_restart(&mut self, restart: Restart) {
+        self.restart = restart;
+    }
+
+    /**Let the strategy make its next change to the current schedule, and keep it if it is the best found so far
     Return value is (number of evaluated schedules, number of random starts)
     */
     pub fn process(&mut self) -> (u32, u32) {
-        let mut evaluated_schedules = 0;
-        let mut random_starts = 0;
-        self.table2 += 1;
-        if self.table2 >= self.tables {
-            self.table2 = self.table1 + 2;
-            self.table1 += 1;
-            if self.table2 >= self.tables {
-                self.table1 = 0;
-                self.table2 = 1;
-                self.round += 1;
-                if self.round >= self.tables {
-                    // Reached end of iteration of all single-step changes from self.current
-                    self.round = 0;
-                    self.table1 = 0;
-                    self.table2 = 1;
-                    if self.next_score > self.current_score {
-                        // If best single-step change is an improvement, then apply it
-                        let (round, table1, table2) = self.next;
-                        let (_, _, ops) = self.current.improve_table(
-                            self.current_score,
-                            round,
-                            table1,
-                            table2,
-                            true,
-                        );
-                        evaluated_schedules += ops;
-                        self.current_score = self.next_score;
-                        debug_assert_eq!(self.current_score, self.current.generate_score());
-                        if (self.current_score > self.best_score
-                            || (self.current_score == self.best_score
-                                && self.current.unique_games_played()
-                                    > self.best.unique_games_played()))
-                            && self.current.unique_games_played() >= self.best.unique_games_played()
-                        {
-                            self.best_score = self.current_score;
-                            self.best = self.current.clone();
-                        }
-                    } else {
-                        // If best single-step change is not an improvement, then generate a new random schedule
-                        random_starts += 1;
-                        self.current = Schedule::new(self.player_count, self.tables);
-                        self.current.generate_random(&mut self.rng);
-                        self.current_score = self.current.get_score();
-                        evaluated_schedules += 1;
-                        self.next_score = self.current_score;
-                        if (self.current_score > self.best_score
-                            || (self.current_score == self.best_score
-                                && self.current.unique_games_played()
-                                    > self.best.unique_games_played()))
-                            && self.current.unique_games_played() >= self.best.unique_games_played()
-                        {
-                            self.best_score = self.current_score;
-                            self.best = self.current.clone();
-                        }
-                    }
-                }
-            }
-        }
-        let (new_score, new_unique_games_played, ops) = self.current.improve_table(
+        let (evaluations, random_starts, _) = self.step();
+        (evaluations, random_starts)
+    }
+
+    /**Same as process, but also returns whether a better schedule was found*/
+    fn step(&mut self) -> (u32, u32, bool) {
+        let (evaluations, random_starts) = self.strategy.process(
+            &mut self.current,
+            &mut self.current_score,
+            &self.restart,
+            &mut self.rng,
+        );
+        let improved = is_better(
             self.current_score,
-            self.round,
-            self.table1,
-            self.table2,
-            false,
+            &self.current,
+            self.best_score,
+            &self.best,
         );
-        evaluated_schedules += ops;
-        if new_score > self.next_score
-            || (new_score == self.next_score && new_unique_games_played > self.next_unique_games)
-        {
-            self.next = (self.round, self.table1, self.table2);
-            self.next_score = new_score;
-            self.next_unique_games = new_unique_games_played;
+        if improved {
+            self.best_score = self.current_score;
+            self.best = self.current.clone();
+        }
+        (evaluations, random_starts, improved)
+    }
+
+    /**Keep processing until one of the criteria is reached. clock gives the time in milliseconds, such as run::std_clock().
+    observer is told about every better schedule found, and about progress every criteria.progress_interval milliseconds.
+    Returns why the run stopped and the work done*/
+    pub fn run_until<O: FnMut(RunEvent)>(
+        &mut self,
+        criteria: &StopCriteria,
+        clock: &dyn Fn() -> f64,
+        mut observer: O,
+    ) -> (StopReason, RunStats) {
+        let mut tracker = Tracker::new(criteria, clock);
+        loop {
+            if let Some(reason) = tracker.stop_reason(&self.best, self.best_score) {
+                return (reason, tracker.stats());
+            }
+            let (evaluations, random_starts, improved) = self.step();
+            tracker.record(u64::from(evaluations), u64::from(random_starts));
+            if improved {
+                observer(RunEvent::NewBest {
+                    best: &self.best,
+                    score: self.best_score,
+                    stats: tracker.improved(),
+                });
+            }
+            if let Some(stats) = tracker.progress() {
+                observer(RunEvent::Progress {
+                    best: &self.best,
+                    score: self.best_score,
+                    stats,
+                    diversity: None,
+                });
+            }
         }
-        (evaluated_schedules, random_starts)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use history::MEETING_WEIGHT;
     use rand::SeedableRng;
 
     #[test]
@@ -676,7 +2382,7 @@ mod tests {
         for _ in 0..6 {
             game.push(round.clone());
         }
-        let mut schedule = Schedule::from_vec(24, 6, &game);
+        let mut schedule = Schedule::from_vec(24, 6, 6, &game);
         assert_eq!(24, schedule.find_unique_games_played());
     }
 
@@ -694,7 +2400,7 @@ mod tests {
         for _ in 0..6 {
             game.push(round.clone());
         }
-        let mut schedule = Schedule::from_vec(24, 6, &game);
+        let mut schedule = Schedule::from_vec(24, 6, 6, &game);
         assert_eq!(3 * 24, schedule.find_unique_opponents());
     }
 
@@ -705,25 +2411,455 @@ mod tests {
             vec![vec![4, 5], vec![0, 3], vec![1, 2]],
             vec![vec![2, 3], vec![1, 4], vec![0, 5]],
         ];
-        let schedule = Schedule::from_vec(6, 3, &ideal);
+        let schedule = Schedule::from_vec(6, 3, 3, &ideal);
         assert!(schedule.is_ideal());
     }
 
+    #[test]
+    fn rendered_json_loads_again_unless_named() {
+        let mut schedule = Schedule::new(8, 2, 2);
+        schedule.set_teams(2);
+        schedule.normal_fill();
+        let saved = schedule.to_serde_schedule();
+        let loaded: SerdeSchedule =
+            serde_json::from_str(&saved.render(format::Format::Json, &[])).unwrap();
+        assert_eq!(loaded.matches, saved.matches);
+        assert_eq!(loaded.get_teams(), 2);
+        let named = saved.render(format::Format::Csv, &["Ann".to_string()]);
+        assert!(named.starts_with("round,table,players\r\n1,1,Ann+"));
+        assert!(serde_json::from_str::<SerdeSchedule>(
+            &saved.render(format::Format::Json, &["Ann".to_string()])
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn more_than_64_players_supported() {
+        let mut schedule = Schedule::new(150, 12, 12);
+        schedule.normal_fill();
+        assert_eq!(
+            schedule.find_unique_games_played(),
+            schedule.ideal_unique_games
+        );
+        let mut players: Vec<usize> = (0..12)
+            .flat_map(|table| schedule.get_players_from_game(3, table))
+            .collect();
+        players.sort_unstable();
+        assert_eq!(players, (0..150).collect::<Vec<usize>>());
+        let mut rng = rand_xorshift::XorShiftRng::from_seed([7; 16]);
+        schedule.generate_random(&mut rng);
+        let score = schedule.generate_score();
+        let (new_score, _, _) = schedule.improve_table(score, 0, 0, 1, true);
+        assert!(new_score >= score);
+        assert_eq!(new_score, schedule.generate_score());
+    }
+
+    #[test]
+    fn itineraries_follow_player_positions() {
+        let played = [
+            vec![vec![0, 1, 2], vec![3, 4, 5]],
+            vec![vec![0, 3, 4], vec![1, 2, 5]],
+        ];
+        let mut schedule = Schedule::from_vec(6, 2, 2, &played);
+        assert_eq!(
+            schedule.itinerary(2),
+            vec![
+                format::Seat {
+                    table: 0,
+                    players: vec![0, 1, 2]
+                },
+                format::Seat {
+                    table: 1,
+                    players: vec![1, 2, 5]
+                }
+            ]
+        );
+        schedule.make_move(
+            1,
+            Move::Swap {
+                player1: 0,
+                table1: 0,
+                player2: 2,
+                table2: 1,
+            },
+        );
+        assert_eq!(schedule.itinerary(2)[1].table, 0);
+        let itineraries = schedule.itineraries();
+        for (player, seats) in itineraries.iter().enumerate() {
+            for (round, seat) in seats.iter().enumerate() {
+                assert_eq!(
+                    seat.players,
+                    schedule.get_players_from_game(round, seat.table)
+                );
+                assert!(seat.players.contains(&player));
+            }
+        }
+        let cards =
+            schedule
+                .to_serde_schedule()
+                .render_itineraries(format::Format::Html, &[], &[0, 2, 5]);
+        assert_eq!(cards.matches("<section>").count(), 3);
+    }
+
+    #[test]
+    fn rounds_can_differ_from_tables() {
+        let mut schedule = Schedule::new(24, 6, 4);
+        schedule.normal_fill();
+        assert_eq!(schedule.to_serde_schedule().get_rounds(), 4);
+        assert_eq!(schedule.ideal_unique_games, 24 * 4);
+        assert_eq!(
+            schedule.find_unique_games_played(),
+            schedule.ideal_unique_games
+        );
+
+        let mut schedule = Schedule::new(24, 6, 9);
+        schedule.normal_fill();
+        assert_eq!(schedule.ideal_unique_games, 24 * 6);
+        assert_eq!(schedule.ideal_unique_opponents, 24 * 23);
+        assert_eq!(
+            schedule.find_unique_games_played(),
+            schedule.ideal_unique_games
+        );
+    }
+
+    #[test]
+    fn uneven_capacities_are_respected() {
+        let capacities = [(6, 6), (4, 5), (4, 5), (3, 4)];
+        let mut schedule = Schedule::with_capacities(18, &capacities, 4);
+        schedule.normal_fill();
+        let report = schedule
+            .to_serde_schedule()
+            .validate(&validate::Claim::default());
+        assert!(report.is_valid(), "{:?}", report.violations);
+        assert_eq!(schedule.get_players_from_game(2, 0).len(), 6);
+    }
+
+    #[test]
+    fn shapes_a_schedule_cant_hold_are_invalid() {
+        let invalid = |json: &str| {
+            let schedule: SerdeSchedule = serde_json::from_str(json).unwrap();
+            let report = schedule.validate(&validate::Claim::default());
+            !report.is_valid()
+        };
+        assert!(invalid(
+            r#"{"player_count":3,"tables":1,"rounds":1,"matches":[[[0,1,2]]]}"#
+        ));
+        assert!(invalid(
+            r#"{"player_count":3,"tables":2,"rounds":1,"matches":[[[0,1],[2]]]}"#
+        ));
+        assert!(invalid(
+            r#"{"player_count":4,"tables":2,"rounds":0,"matches":[]}"#
+        ));
+        assert!(invalid(
+            r#"{"player_count":5,"tables":2,"rounds":1,"capacities":[[1,4],[1,4]],"matches":[[[0,1,2,3],[4]]]}"#
+        ));
+        assert!(invalid(
+            r#"{"player_count":5,"tables":2,"rounds":1,"teams":3,"matches":[[[0,1,2],[3,4]]]}"#
+        ));
+        assert!(invalid(
+            r#"{"player_count":4,"tables":3,"rounds":1,"capacities":[[2,2],[2,2]],"matches":[[[0,1],[2,3]]]}"#
+        ));
+        let valid =
+            r#"{"player_count":5,"tables":2,"rounds":1,"teams":2,"matches":[[[0,1,2],[3,4]]]}"#;
+        assert!(!invalid(valid));
+        let schedule: SerdeSchedule = serde_json::from_str(valid).unwrap();
+        assert_eq!(schedule.to_schedule().get_teams(), 2);
+    }
+
+    #[test]
+    fn relocations_only_considered_within_capacity() {
+        let round = vec![vec![0, 1, 2], vec![3, 4]];
+        let game = vec![round.clone(), round];
+        let mut schedule = Schedule::with_capacities(5, &[(2, 3), (2, 3)], 2);
+        schedule.import_vec(&game);
+        let score = schedule.generate_score();
+        // 6 swaps, and each player at the first table can move to the second
+        assert_eq!(schedule.improve_table(score, 1, 0, 1, false).2, 9);
+
+        let mut schedule = Schedule::with_capacities(5, &[(3, 3), (2, 2)], 2);
+        schedule.import_vec(&game);
+        let score = schedule.generate_score();
+        assert_eq!(schedule.improve_table(score, 1, 0, 1, true).2, 6);
+        assert_eq!(schedule.get_players_from_game(1, 0).len(), 3);
+    }
+
+    #[test]
+    fn neighbourhood_adds_moves() {
+        let mut schedule = Schedule::with_capacities(10, &[(3, 4); 3], 2);
+        schedule.import_vec(&[
+            vec![vec![0, 1, 2, 3], vec![4, 5, 6], vec![7, 8, 9]],
+            vec![vec![0, 4, 7], vec![1, 5, 8, 2], vec![3, 6, 9]],
+        ]);
+        // 12 swaps, and each player at the first table can move to the second
+        assert_eq!(schedule.moves(0, 0, 1).len(), 16);
+        schedule.set_neighbourhood(Neighbourhood {
+            cycles: true,
+            group_size: 2,
+            round_exchanges: true,
+        });
+        let moves = schedule.moves(0, 0, 1);
+        let count =
+            |matches: fn(&Move) -> bool| moves.iter().filter(|&chosen| matches(chosen)).count();
+        // Both ways around for each player at the three tables
+        assert_eq!(
+            count(|chosen| matches!(chosen, Move::Cycle { .. })),
+            2 * 4 * 3 * 3
+        );
+        assert_eq!(
+            count(|chosen| matches!(chosen, Move::SwapGroups { .. })),
+            6 * 3
+        );
+        // Players 1 and 2 are at the second table in the second round, which has space at the first table
+        assert_eq!(
+            count(|chosen| matches!(chosen, Move::ExchangeRounds { .. })),
+            2
+        );
+        assert!(schedule
+            .moves(0, 1, 2)
+            .iter()
+            .all(|chosen| !matches!(chosen, Move::Cycle { .. })));
+
+        let games_played = schedule.unique_games_played();
+        schedule.make_move(
+            0,
+            Move::ExchangeRounds {
+                player: 1,
+                from: 0,
+                to: 1,
+                round2: 1,
+            },
+        );
+        assert_eq!(schedule.get_players_from_game(0, 1), vec![1, 4, 5, 6]);
+        assert_eq!(schedule.get_players_from_game(1, 0), vec![0, 1, 4, 7]);
+        assert_eq!(schedule.unique_games_played(), games_played);
+    }
+
+    #[test]
+    fn parses_neighbourhoods() {
+        assert_eq!(
+            Neighbourhood::parse("cycles, groups:3"),
+            Ok(Neighbourhood {
+                cycles: true,
+                group_size: 3,
+                round_exchanges: false,
+            })
+        );
+        assert!(Neighbourhood::parse("exchanges").unwrap().round_exchanges);
+        assert!(Neighbourhood::parse("groups:1").is_err());
+        assert!(Neighbourhood::parse("groups").is_err());
+        assert!(Neighbourhood::parse("unknown").is_err());
+    }
+
+    #[test]
+    fn distance_is_zero_only_for_same_pairings() {
+        let mut schedule = Schedule::new(8, 2, 2);
+        schedule.normal_fill();
+        let mut relabelled = schedule.clone();
+        // Swapping every player between two tables in a round only relabels the tables
+        let players1 = relabelled.get_players_from_game(0, 0);
+        let players2 = relabelled.get_players_from_game(0, 1);
+        for (&player1, &player2) in players1.iter().zip(players2.iter()) {
+            relabelled.make_move(
+                0,
+                Move::Swap {
+                    player1,
+                    table1: 0,
+                    player2,
+                    table2: 1,
+                },
+            );
+        }
+        assert_eq!(schedule.distance(&relabelled), 0);
+        let mut changed = schedule.clone();
+        changed.make_move(
+            1,
+            Move::Swap {
+                player1: players1[0],
+                table1: 0,
+                player2: players2[0],
+                table2: 1,
+            },
+        );
+        assert!(schedule.distance(&changed) > 0);
+    }
+
+    #[test]
+    fn fill_from_keeps_original_positions() {
+        let mut original = Schedule::new(12, 3, 3);
+        original.normal_fill();
+        let data = original.to_serde_schedule().matches;
+        let mut same = Schedule::new(12, 3, 3);
+        same.fill_from(&data);
+        assert_eq!(same.to_serde_schedule().matches, data);
+        assert_eq!(same.unchanged_positions(), 36);
+        // Two more players fit without moving anyone
+        let mut bigger = Schedule::new(14, 3, 4);
+        bigger.fill_from(&data);
+        assert_eq!(bigger.unchanged_positions(), 36);
+        assert!(bigger
+            .to_serde_schedule()
+            .validate(&validate::Claim::default())
+            .is_valid());
+    }
+
+    #[test]
+    fn undoing_a_swap_restores_unchanged_positions() {
+        let mut schedule = Schedule::new(4, 2, 1);
+        schedule.fill_from(&[vec![vec![0, 3], vec![1, 2]]]);
+        let swap = Move::Swap {
+            player1: 0,
+            table1: 0,
+            player2: 1,
+            table2: 1,
+        };
+        schedule.make_move(0, swap);
+        assert_eq!(schedule.unchanged_positions(), 2);
+        schedule.make_move(0, swap.inverse());
+        assert_eq!(schedule.unchanged_positions(), 4);
+    }
+
+    #[test]
+    fn history_counts_as_already_met() {
+        let played = vec![vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]];
+        let mut schedule = Schedule::new(8, 2, 1);
+        schedule.set_history(&played);
+        assert_eq!(schedule.ideal_unique_opponents, 8 * 6);
+        assert_eq!(schedule.ideal_unique_games, 8 * 2);
+        schedule.import_vec(&played);
+        assert_eq!(schedule.unique_opponents(), 8 * 3);
+        assert_eq!(schedule.unique_games_played(), 8);
+        schedule.import_vec(&[vec![vec![0, 1, 4, 5], vec![2, 3, 6, 7]]]);
+        assert_eq!(schedule.unique_opponents(), 8 * 5);
+        assert_eq!(schedule.unique_games_played(), 12);
+    }
+
+    #[test]
+    fn teams_count_partners_and_opponents_separately() {
+        let mut schedule = Schedule::new(8, 2, 1);
+        schedule.set_teams(2);
+        assert_eq!(schedule.ideal_unique_partners, 8);
+        assert_eq!(schedule.ideal_unique_opponents, 8 * 2);
+        schedule.import_vec(&[vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]]);
+        assert_eq!(
+            schedule.get_teams_from_game(0, 0),
+            vec![vec![0, 1], vec![2, 3]]
+        );
+        assert_eq!(
+            format_pairing(&schedule.get_teams_from_game(0, 1)),
+            "4+5 vs 6+7"
+        );
+        assert_eq!(schedule.unique_partners(), 8);
+        assert_eq!(schedule.unique_opponents(), 8 * 2);
+        let score = schedule.make_move(
+            0,
+            Move::SwapTeams {
+                player1: 1,
+                player2: 2,
+                table: 0,
+            },
+        );
+        assert_eq!(
+            schedule.get_teams_from_game(0, 0),
+            vec![vec![0, 2], vec![1, 3]]
+        );
+        assert_eq!(score, schedule.generate_score());
+        let serde = schedule.to_serde_schedule();
+        assert_eq!(serde.matches[0][0], vec![0, 2, 1, 3]);
+        assert_eq!(
+            serde.to_schedule().get_teams_from_game(0, 0),
+            vec![vec![0, 2], vec![1, 3]]
+        );
+    }
+
+    #[test]
+    fn pair_history_penalises_meeting_again() {
+        let mut history = PairHistory::new(8);
+        history.add_event(&[vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]], MEETING_WEIGHT);
+        let mut schedule = Schedule::new(8, 2, 1);
+        schedule.set_pair_history(&history);
+        schedule.import_vec(&[vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]]);
+        assert_eq!(schedule.repeated_pairs(), 8 * 3 * MEETING_WEIGHT);
+        let repeated_score = schedule.get_score();
+        schedule.import_vec(&[vec![vec![0, 1, 4, 5], vec![2, 3, 6, 7]]]);
+        assert_eq!(schedule.repeated_pairs(), 8 * MEETING_WEIGHT);
+        assert!(schedule.get_score() > repeated_score);
+        assert_eq!(schedule.unique_opponents(), 8 * 3);
+    }
+
+    #[test]
+    fn ratings_penalise_uneven_tables() {
+        let mut schedule = Schedule::new(8, 2, 1);
+        schedule.set_ratings(&[
+            1700.0, 1700.0, 1700.0, 1700.0, 1300.0, 1300.0, 1300.0, 1300.0,
+        ]);
+        schedule.import_vec(&[vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]]]);
+        assert_eq!(schedule.table_imbalance(), 2 * 4 * 200);
+        let uneven_score = schedule.get_score();
+        schedule.import_vec(&[vec![vec![0, 1, 4, 5], vec![2, 3, 6, 7]]]);
+        assert_eq!(schedule.table_imbalance(), 0);
+        assert!(schedule.get_score() > uneven_score);
+        schedule.make_move(
+            0,
+            Move::Swap {
+                player1: 4,
+                table1: 0,
+                player2: 2,
+                table2: 1,
+            },
+        );
+        assert_eq!(schedule.table_imbalance(), 2 * 400);
+    }
+
+    #[test]
+    fn constraints_outrank_the_objective() {
+        let mut schedule = Schedule::new(8, 2, 2);
+        schedule.set_constraints(&Constraints {
+            pinned: vec![Some(1)],
+            forbidden: vec![(2, 3), (3, 2)],
+        });
+        let ideal = [
+            vec![vec![0, 1, 2, 3], vec![4, 5, 6, 7]],
+            vec![vec![0, 1, 4, 5], vec![2, 3, 6, 7]],
+        ];
+        schedule.import_vec(&ideal);
+        assert_eq!(schedule.constraint_violations(), 4);
+        assert!(!schedule.is_ideal());
+        let broken_score = schedule.get_score();
+        schedule.import_vec(&[
+            vec![vec![2, 4, 5, 6], vec![0, 1, 3, 7]],
+            vec![vec![2, 4, 5, 6], vec![0, 1, 3, 7]],
+        ]);
+        assert_eq!(schedule.constraint_violations(), 0);
+        assert!(schedule.get_score() > broken_score);
+        assert!(schedule.get_score() <= schedule.score_bound());
+
+        // Pinning lowers the unique games played, which mustn't stop the search from keeping to the constraints
+        let mut generator = Generator::new(rand_xorshift::XorShiftRng::seed_from_u64(3), 8, 2, 3);
+        generator.set_constraints(&Constraints {
+            pinned: vec![Some(0), None, Some(1)],
+            forbidden: vec![(0, 1), (4, 5)],
+        });
+        for _ in 0..200 {
+            generator.process();
+        }
+        assert_eq!(generator.best.constraint_violations(), 0);
+    }
+
     #[test]
     #[should_panic]
-    fn too_high_player_count_panics() {
-        Schedule::new(65, 6);
+    fn zero_rounds_panics() {
+        Schedule::new(24, 6, 0);
     }
 
     #[test]
     #[should_panic]
     fn too_low_table_count_panics_0() {
-        Schedule::new(24, 0);
+        Schedule::new(24, 0, 6);
     }
     #[test]
     #[should_panic]
     fn too_low_table_count_panics_1() {
-        Schedule::new(24, 1);
+        Schedule::new(24, 1, 6);
     }
 
     #[derive(Clone, Debug)]
@@ -733,7 +2869,7 @@ mod tests {
     }
 
     impl quickcheck::Arbitrary for Seed {
-        fn arbitrary<G: quickcheck::Gen>(g: &mut G) -> Self {
+        fn arbitrary(g: &mut quickcheck::Gen) -> Self {
             let mut data: [u8; 16] = [0; 16];
             for val in &mut data {
                 *val = u8::arbitrary(g);
@@ -742,48 +2878,108 @@ mod tests {
         }
     }
 
-    quickcheck! {fn get_score_matches_generate_score(tables: i8, player_count: i8, seed: Seed) -> bool{
-        let tables = (tables.abs() % 33).max(2) as usize;
-        let player_count = (player_count.abs() % 65).max((tables * 2) as i8) as usize;
-        let mut schedule = Schedule::new(player_count as usize, tables as usize);
+    quickcheck! {fn get_score_matches_generate_score(tables: u8, player_count: u8, rounds: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 33).max(2);
+        let player_count = (player_count as usize % 161).max(tables * 2);
+        let rounds = (rounds as usize % 41).max(1);
+        let mut schedule = Schedule::new(player_count, tables, rounds);
         let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
         schedule.generate_random(&mut rng);
         schedule.get_score() == schedule.generate_score()
     }}
 
-    quickcheck! {fn unique_games_played_less_equal_ideal(tables: i8, player_count: i8, seed: Seed) -> bool{
-        let tables = (tables.abs() % 33).max(2) as usize;
-        let player_count = (player_count.abs() % 65).max((tables * 2) as i8) as usize;
-        let mut schedule = Schedule::new(player_count as usize, tables as usize);
+    quickcheck! {fn improve_table_keeps_score_cache_for_every_objective(tables: u8, player_count: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 17).max(2);
+        let player_count = (player_count as usize % 97).max(tables * 2);
+        let objectives: Vec<Arc<dyn Objective>> = vec![
+            Arc::new(objective::Weighted::default()),
+            Arc::new(objective::Lexicographic::default()),
+            Arc::new(objective::MaxMin),
+            Arc::new(objective::VariancePenalised { weights: objective::Weighted::default(), penalty: 3 }),
+        ];
+        let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        objectives.into_iter().all(|objective| {
+            let mut schedule = Schedule::new(player_count, tables, tables);
+            schedule.set_objective(objective);
+            schedule.generate_random(&mut rng);
+            let score = schedule.generate_score();
+            let (new_score, _, _) = schedule.improve_table(score, tables - 1, 0, 1, true);
+            new_score >= score && new_score == schedule.get_score() && new_score == schedule.generate_score()
+        })
+    }}
+
+    quickcheck! {fn neighbourhood_moves_keep_caches(tables: u8, player_count: u8, rounds: u8, teams: bool, seed: Seed) -> bool{
+        let tables = (tables as usize % 6).max(3);
+        let player_count = (player_count as usize % 31).max(tables * 4);
+        let rounds = (rounds as usize % 5).max(2);
+        let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut schedule = Schedule::new(player_count, tables, rounds);
+        if teams {
+            schedule.set_teams(2);
+        }
+        schedule.set_neighbourhood(Neighbourhood { cycles: true, group_size: 2, round_exchanges: true });
+        schedule.generate_random(&mut rng);
+        let mut score = schedule.generate_score();
+        for round in 0..rounds {
+            let (new_score, _, _) = schedule.improve_table(score, round, 0, 1 + round % (tables - 1), true);
+            if new_score < score || new_score != schedule.get_score() || new_score != schedule.generate_score() {
+                return false;
+            }
+            score = new_score;
+        }
+        for _ in 0..20 {
+            let (round, chosen) = schedule.random_move(&mut rng);
+            let before = schedule.get_score();
+            schedule.make_move(round, chosen);
+            if schedule.get_score() != schedule.generate_score() {
+                return false;
+            }
+            schedule.make_move(round, chosen.inverse());
+            if schedule.get_score() != before || schedule.get_score() != schedule.generate_score() {
+                return false;
+            }
+            schedule.make_move(round, chosen);
+        }
+        schedule.to_serde_schedule().validate(&validate::Claim::default()).is_valid()
+    }}
+
+    quickcheck! {fn unique_games_played_less_equal_ideal(tables: u8, player_count: u8, rounds: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 33).max(2);
+        let player_count = (player_count as usize % 161).max(tables * 2);
+        let rounds = (rounds as usize % 41).max(1);
+        let mut schedule = Schedule::new(player_count, tables, rounds);
         let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
         schedule.generate_random(&mut rng);
         schedule.find_unique_games_played() <= schedule.ideal_unique_games
     }}
 
-    quickcheck! {fn unique_opponents_played_less_equal_ideal(tables: i8, player_count: i8, seed: Seed) -> bool{
-        let tables = (tables.abs() % 33).max(2) as usize;
-        let player_count = (player_count.abs() % 65).max((tables * 2) as i8) as usize;
-        let mut schedule = Schedule::new(player_count as usize, tables as usize);
+    quickcheck! {fn unique_opponents_played_less_equal_ideal(tables: u8, player_count: u8, rounds: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 33).max(2);
+        let player_count = (player_count as usize % 161).max(tables * 2);
+        let rounds = (rounds as usize % 41).max(1);
+        let mut schedule = Schedule::new(player_count, tables, rounds);
         let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
         schedule.generate_random(&mut rng);
         schedule.find_unique_opponents() <= schedule.ideal_unique_opponents
     }}
 
-    quickcheck! {fn normal_fill_maxes_unique_games(tables: i8, player_count: i8, seed: Seed) -> bool{
-        let tables = (tables.abs() % 33).max(2) as usize;
-        let player_count = (player_count.abs() % 65).max((tables * 2) as i8) as usize;
-        let mut schedule = Schedule::new(player_count as usize, tables as usize);
+    quickcheck! {fn normal_fill_maxes_unique_games(tables: u8, player_count: u8, rounds: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 33).max(2);
+        let player_count = (player_count as usize % 161).max(tables * 2);
+        let rounds = (rounds as usize % 41).max(1);
+        let mut schedule = Schedule::new(player_count, tables, rounds);
         let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
         schedule.generate_random(&mut rng);
         schedule.normal_fill();
         schedule.find_unique_games_played() == schedule.ideal_unique_games
     }}
 
-    quickcheck! {fn game_length_is_expected(tables: i8, player_count: i8, seed: Seed) -> bool{
-        let tables = (tables.abs() % 33).max(2) as usize;
-        let player_count = (player_count.abs() % 65).max((tables * 2) as i8) as usize;
+    quickcheck! {fn game_length_is_expected(tables: u8, player_count: u8, rounds: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 33).max(2);
+        let player_count = (player_count as usize % 161).max(tables * 2);
+        let rounds = (rounds as usize % 41).max(1);
 
-        let mut schedule = Schedule::new(player_count as usize, tables as usize);
+        let mut schedule = Schedule::new(player_count, tables, rounds);
         let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
         schedule.generate_random(&mut rng);
         let game_size = schedule.get_players_from_game(0, 0).len();
@@ -792,25 +2988,202 @@ mod tests {
         }
     }}
 
-    quickcheck! {fn score_doesnt_decrease_after_process(tables: i8, player_count: i8, seed: Seed) -> bool{
-        let tables = (tables.abs() % 33).max(2) as usize;
-        let player_count = (player_count.abs() % 65).max((tables * 2) as i8) as usize;
+    quickcheck! {fn fill_from_keeps_caches_and_capacities(tables: u8, player_count: u8, rounds: u8, old_tables: u8, old_player_count: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 9).max(2);
+        let player_count = (player_count as usize % 41).max(tables * 2);
+        let rounds = (rounds as usize % 9).max(1);
+        let old_tables = (old_tables as usize % 9).max(2);
+        let old_player_count = (old_player_count as usize % 41).max(old_tables * 2);
+        let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut original = Schedule::new(old_player_count, old_tables, rounds);
+        original.generate_random(&mut rng);
+        let mut schedule = Schedule::new(player_count, tables, rounds);
+        schedule.set_objective(Arc::new(objective::MinimiseChanges { weights: objective::Weighted::default(), weight: 2 }));
+        schedule.fill_from(&original.to_serde_schedule().matches);
+        for _ in 0..20 {
+            let (round, chosen) = schedule.random_move(&mut rng);
+            schedule.make_move(round, chosen);
+        }
+        let (unchanged, score) = (schedule.unchanged_positions(), schedule.get_score());
+        unchanged == schedule.find_unchanged_positions()
+            && score == schedule.generate_score()
+            && schedule.to_serde_schedule().validate(&validate::Claim::default()).is_valid()
+    }}
+
+    quickcheck! {fn replan_keeps_caches_and_ideals(tables: u8, player_count: u8, rounds: u8, completed: u8, leaving: Vec<u8>, joining: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 6).max(2);
+        let player_count = (player_count as usize % 41).max(tables * 2);
+        let rounds = (rounds as usize % 7).max(2);
+        let completed = (completed as usize % (rounds - 1)).max(1);
+        let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut event = Schedule::new(player_count, tables, rounds);
+        event.generate_random(&mut rng);
+        let mut players: Vec<Option<usize>> = (0..player_count)
+            .filter(|player| !leaving.iter().any(|&left| left as usize == *player))
+            .map(Some)
+            .collect();
+        players.extend(std::iter::repeat_n(None, joining as usize % 8));
+        if players.len() < tables * 2 {
+            return true;
+        }
+        let mut generator = Generator::replan(
+            rng,
+            Schedule::new(players.len(), tables, rounds - completed),
+            &event.to_serde_schedule().matches,
+            completed,
+            &players,
+        );
+        for _ in 0..20 {
+            generator.process();
+        }
+        let mut best = generator.best.clone();
+        let score = best.get_score();
+        score == best.generate_score()
+            && best.unique_opponents() <= best.ideal_unique_opponents
+            && best.unique_games_played() <= best.ideal_unique_games
+            && best.to_serde_schedule().validate(&validate::Claim::default()).is_valid()
+    }}
+
+    quickcheck! {fn team_moves_keep_caches_and_team_sizes(tables: u8, player_count: u8, rounds: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 6).max(2);
+        let player_count = (player_count as usize % 41).max(tables * 4);
+        let rounds = (rounds as usize % 7).max(1);
+        let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut schedule = Schedule::new(player_count, tables, rounds);
+        schedule.set_teams(2);
+        schedule.generate_random(&mut rng);
+        let sizes = |schedule: &Schedule| -> Vec<usize> {
+            (0..rounds).flat_map(|round| (0..tables).map(move |table| (round, table)))
+                .flat_map(|(round, table)| schedule.get_teams_from_game(round, table))
+                .map(|team| team.len())
+                .collect()
+        };
+        let original_sizes = sizes(&schedule);
+        for _ in 0..20 {
+            let (round, chosen) = schedule.random_move(&mut rng);
+            schedule.make_move(round, chosen);
+        }
+        let score = schedule.get_score();
+        score == schedule.generate_score()
+            && sizes(&schedule) == original_sizes
+            && schedule.unique_partners() <= schedule.ideal_unique_partners
+            && schedule.unique_opponents() <= schedule.ideal_unique_opponents
+    }}
+
+    quickcheck! {fn pair_history_keeps_caches(tables: u8, player_count: u8, rounds: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 6).max(2);
+        let player_count = (player_count as usize % 41).max(tables * 2);
+        let rounds = (rounds as usize % 7).max(1);
+        let mut rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut previous = Schedule::new(player_count, tables, rounds);
+        previous.generate_random(&mut rng);
+        let mut history = PairHistory::new(player_count);
+        history.add_event(&previous.to_serde_schedule().matches, MEETING_WEIGHT);
+        let mut generator = Generator::new(rng, player_count, tables, rounds);
+        generator.set_pair_history(&history);
+        for _ in 0..20 {
+            generator.process();
+        }
+        let mut best = generator.best.clone();
+        let score = best.get_score();
+        let repeated_pairs = best.repeated_pairs();
+        score == best.generate_score() && repeated_pairs == best.repeated_pairs()
+    }}
+
+    quickcheck! {fn ratings_keep_caches(tables: u8, player_count: u8, rounds: u8, ratings: Vec<u16>, seed: Seed) -> bool{
+        let tables = (tables as usize % 6).max(2);
+        let player_count = (player_count as usize % 41).max(tables * 2);
+        let rounds = (rounds as usize % 7).max(1);
+        let ratings: Vec<f64> = (0..player_count)
+            .map(|player| f64::from(ratings.get(player).copied().unwrap_or(1500) % 3000))
+            .collect();
+        let rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut generator = Generator::new(rng, player_count, tables, rounds);
+        generator.set_ratings(&ratings);
+        for _ in 0..20 {
+            generator.process();
+        }
+        let mut best = generator.best.clone();
+        let score = best.get_score();
+        let table_imbalance = best.table_imbalance();
+        score == best.generate_score() && table_imbalance == best.table_imbalance()
+    }}
+
+    quickcheck! {fn constraints_keep_caches(tables: u8, player_count: u8, rounds: u8, pinned: Vec<u8>, forbidden: Vec<(u8, u8)>, seed: Seed) -> bool{
+        let tables = (tables as usize % 6).max(2);
+        let player_count = (player_count as usize % 41).max(tables * 2);
+        let rounds = (rounds as usize % 7).max(1);
+        let constraints = Constraints {
+            pinned: pinned.iter().take(player_count).map(|&table| Some(table as usize % (tables + 2)).filter(|&table| table < tables)).collect(),
+            forbidden: forbidden.iter().map(|&(player1, player2)| (player1 as usize % player_count, player2 as usize % player_count)).filter(|(player1, player2)| player1 != player2).collect(),
+        };
         let rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
-        let mut generator = Generator::new(rng, player_count as usize, tables as usize);
+        let mut generator = Generator::new(rng, player_count, tables, rounds);
+        generator.set_constraints(&constraints);
+        for _ in 0..20 {
+            generator.process();
+        }
+        let mut best = generator.best.clone();
+        let score = best.get_score();
+        let violations = best.constraint_violations();
+        score == best.generate_score() && violations == best.constraint_violations() && score <= best.score_bound()
+    }}
+
+    quickcheck! {fn score_doesnt_decrease_after_process(tables: u8, player_count: u8, rounds: u8, seed: Seed) -> bool{
+        let tables = (tables as usize % 33).max(2);
+        let player_count = (player_count as usize % 161).max(tables * 2);
+        let rounds = (rounds as usize % 41).max(1);
+        let rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut generator = Generator::new(rng, player_count, tables, rounds);
         let old_score = generator.best_score;
         generator.process();
         generator.best_score >= old_score
     }}
 
-    quickcheck! {fn score_doesnt_decrease_after_repeated_process(tables: i8, player_count: i8, seed: Seed, reps: u16) -> bool{
-        let tables = (tables.abs() % 33).max(2) as usize;
-        let player_count = (player_count.abs() % 65).max((tables * 2) as i8) as usize;
+    quickcheck! {fn capacities_respected_after_repeated_process(sizes: Vec<u8>, spare: u8, seed: Seed, reps: u8) -> bool{
+        let mut capacities: Vec<(usize, usize)> = sizes.iter().take(12).map(|&size| {
+            let min = size as usize % 6 + 2;
+            (min, min + size as usize / 64)
+        }).collect();
+        while capacities.len() < 2 {
+            capacities.push((2, 3));
+        }
+        let min_total: usize = capacities.iter().map(|&(min, _)| min).sum();
+        let max_total: usize = capacities.iter().map(|&(_, max)| max).sum();
+        let player_count = min_total + spare as usize % (max_total - min_total + 1);
+        let rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut generator = Generator::with_capacities(rng, player_count, &capacities, 3);
+        for _ in 0..reps {
+            generator.process();
+        }
+        generator.best.to_serde_schedule().validate(&validate::Claim::default()).is_valid()
+    }}
+
+    // quickcheck draws integers from their whole range, so reps is a u8 to keep the number of passes near the generator size the test was written for
+    quickcheck! {fn score_doesnt_decrease_after_repeated_process(tables: u8, player_count: u8, rounds: u8, seed: Seed, reps: u8) -> bool{
+        let tables = (tables as usize % 33).max(2);
+        let player_count = (player_count as usize % 65).max(tables * 2);
+        let rounds = (rounds as usize % 17).max(1);
         let rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
-        let mut generator = Generator::new(rng, player_count as usize, tables as usize);
+        let mut generator = Generator::new(rng, player_count, tables, rounds);
         let old_score = generator.best_score;
     for _ in 0..reps {
             generator.process();
     }
         generator.best_score >= old_score
     }}
+
+    quickcheck! {fn score_doesnt_decrease_after_repeated_process_with_many_players(tables: u8, player_count: u8, rounds: u8, seed: Seed, reps: u8) -> bool{
+        let tables = (tables as usize % 33).max(2);
+        let player_count = 65 + player_count as usize % 96;
+        let rounds = (rounds as usize % 17).max(1);
+        let rng = rand_xorshift::XorShiftRng::from_seed(seed.data);
+        let mut generator = Generator::new(rng, player_count, tables, rounds);
+        let old_score = generator.best_score;
+        // A pass over more than 64 players takes much longer in a debug build, a few are enough to carry the caches from one pass into the next
+    for _ in 0..(reps % 8) {
+            generator.process();
+    }
+        generator.best_score >= old_score
+    }}
 }
@@ -0,0 +1,593 @@
+use rand::Rng;
+use schedule::{Move, Schedule};
+use std::collections::VecDeque;
+
+/** A local search method used by Generator to change the current schedule.
+Generator keeps track of the best schedule found, so strategies are free to make the current schedule worse */
+pub trait Strategy: Send {
+    /**Called when the generator starts on a new current schedule, or is given this strategy*/
+    fn start(&mut self, current_score: u64);
+
+    /**Do a small amount of work, making at most one change to current. When the strategy gives up on current it replaces it using restart.
+    Returns (number of evaluated schedules, number of random starts or perturbations)*/
+    fn process(
+        &mut self,
+        current: &mut Schedule,
+        current_score: &mut u64,
+        restart: &Restart,
+        rng: &mut dyn rand_core::RngCore,
+    ) -> (u32, u32);
+}
+
+/** Where a strategy gets a new current schedule from when it restarts */
+#[derive(Clone, Default)]
+pub enum Restart {
+    /**Start again from a random schedule*/
+    #[default]
+    Random,
+    /**Start again from one of the elite schedules, changed by strength random single-step changes*/
+    Perturb {
+        elites: Vec<Schedule>,
+        strength: usize,
+    },
+}
+
+impl Restart {
+    /**Replace current with a new schedule to search from*/
+    pub fn apply(&self, current: &mut Schedule, mut rng: &mut dyn rand_core::RngCore) {
+        match self {
+            Restart::Perturb { elites, strength } if !elites.is_empty() => {
+                *current = elites[rng.gen_range(0..elites.len())].clone();
+                for _ in 0..*strength {
+                    let (round, chosen) = current.random_move(&mut rng);
+                    current.make_move(round, chosen);
+                }
+            }
+            _ => current.generate_random(&mut rng),
+        }
+    }
+}
+
+/** Position of a loop over every round, and every pair of tables in that round */
+#[derive(Clone, Debug, Default)]
+struct Position {
+    /** Current round in loop, between 0 and less than the number of rounds*/
+    round: usize,
+    /**Current table1 in loop, between 0 and less than table2*/
+    table1: usize,
+    /**Current table1 in loop, between table1 and less than the number of tables*/
+    table2: usize,
+}
+
+impl Position {
+    /**Move to the next round and table pair. Returns true when every pair has been visited, and the loop has started again*/
+    fn advance(&mut self, tables: usize, rounds: usize) -> bool {
+        self.table2 += 1;
+        if self.table2 >= tables {
+            self.table2 = self.table1 + 2;
+            self.table1 += 1;
+            if self.table2 >= tables {
+                self.table1 = 0;
+                self.table2 = 1;
+                self.round += 1;
+                if self.round >= rounds {
+                    self.round = 0;
+                    return true;
+                }
+            }
+        }
+        false
+    }
+}
+
+/** Finds the best single-step change from the current schedule, by checking one pair of tables per call */
+#[derive(Clone, Debug, Default)]
+struct Sweep {
+    position: Position,
+    /**The best scoring round, table1, table2 combination that has been found in the current loop of changes*/
+    next: (usize, usize, usize),
+    /**The score of the best scoring schedule that has been found in the current loop of changes*/
+    next_score: u64,
+    /**The total number of unique games played of the best scoring schedule that has been found in the current loop of changes*/
+    next_unique_games: u32,
+}
+
+impl Sweep {
+    fn start(&mut self, current_score: u64) {
+        self.position = Position::default();
+        self.next = (0, 0, 1);
+        self.next_score = current_score;
+        self.next_unique_games = 0;
+    }
+
+    /**If the best single-step change found is an improvement then apply it. Returns the number of evaluated schedules if it was applied*/
+    fn apply_improvement(
+        &mut self,
+        current: &mut Schedule,
+        current_score: &mut u64,
+    ) -> Option<u32> {
+        if self.next_score > *current_score {
+            let (round, table1, table2) = self.next;
+            let (_, _, ops) = current.improve_table(*current_score, round, table1, table2, true);
+            *current_score = self.next_score;
+            debug_assert_eq!(*current_score, current.generate_score());
+            Some(ops)
+        } else {
+            None
+        }
+    }
+
+    /**Evaluate the changes for the current position. Returns the number of evaluated schedules*/
+    fn evaluate(&mut self, current: &mut Schedule, current_score: u64) -> u32 {
+        let Position {
+            round,
+            table1,
+            table2,
+        } = self.position;
+        let (new_score, new_unique_games_played, ops) =
+            current.improve_table(current_score, round, table1, table2, false);
+        if new_score > self.next_score
+            || (new_score == self.next_score && new_unique_games_played > self.next_unique_games)
+        {
+            self.next = (round, table1, table2);
+            self.next_score = new_score;
+            self.next_unique_games = new_unique_games_played;
+        }
+        ops
+    }
+}
+
+/** Steepest ascent hill climbing, which applies the best single-step change after checking every change,
+and restarts from a random schedule when no change is an improvement */
+#[derive(Clone, Debug, Default)]
+pub struct HillClimbing {
+    sweep: Sweep,
+}
+
+impl Strategy for HillClimbing {
+    fn start(&mut self, current_score: u64) {
+        self.sweep.start(current_score);
+    }
+
+    fn process(
+        &mut self,
+        current: &mut Schedule,
+        current_score: &mut u64,
+        restart: &Restart,
+        rng: &mut dyn rand_core::RngCore,
+    ) -> (u32, u32) {
+        let mut evaluated_schedules = 0;
+        let mut random_starts = 0;
+        if self
+            .sweep
+            .position
+            .advance(current.get_tables(), current.get_rounds())
+        {
+            // Reached end of iteration of all single-step changes from current
+            if let Some(ops) = self.sweep.apply_improvement(current, current_score) {
+                evaluated_schedules += ops;
+            } else {
+                // If best single-step change is not an improvement, then generate a new random schedule
+                random_starts += 1;
+                restart.apply(current, rng);
+                *current_score = current.get_score();
+                evaluated_schedules += 1;
+                self.sweep.next_score = *current_score;
+            }
+        }
+        evaluated_schedules += self.sweep.evaluate(current, *current_score);
+        (evaluated_schedules, random_starts)
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Cooling {
+    /**Multiply the temperature by this factor after every step*/
+    Geometric(f64),
+    /**Subtract this amount from the temperature after every step*/
+    Linear(f64),
+}
+
+/** Makes random single-step changes, accepting worse schedules with a probability that falls as the temperature cools.
+Temperature is relative to the current score, so at a temperature of 0.01 a change which makes the score 1% worse is accepted with probability 1/e.
+Once the temperature drops below min_temperature it is reheated to initial_temperature */
+#[derive(Clone, Debug, PartialEq)]
+pub struct SimulatedAnnealing {
+    pub initial_temperature: f64,
+    pub min_temperature: f64,
+    pub cooling: Cooling,
+    temperature: f64,
+}
+
+impl SimulatedAnnealing {
+    pub fn new(initial_temperature: f64, min_temperature: f64, cooling: Cooling) -> Self {
+        Self {
+            initial_temperature,
+            min_temperature,
+            cooling,
+            temperature: initial_temperature,
+        }
+    }
+}
+
+impl Default for SimulatedAnnealing {
+    fn default() -> Self {
+        Self::new(0.01, 0.00001, Cooling::Geometric(0.9999))
+    }
+}
+
+impl Strategy for SimulatedAnnealing {
+    fn start(&mut self, _current_score: u64) {
+        self.temperature = self.initial_temperature;
+    }
+
+    fn process(
+        &mut self,
+        current: &mut Schedule,
+        current_score: &mut u64,
+        _restart: &Restart,
+        mut rng: &mut dyn rand_core::RngCore,
+    ) -> (u32, u32) {
+        let (round, chosen) = current.random_move(&mut rng);
+        let new_score = current.make_move(round, chosen);
+        let accept = new_score >= *current_score || {
+            let change = (new_score as f64 - *current_score as f64)
+                / (self.temperature * (*current_score).max(1) as f64);
+            rng.gen::<f64>() < change.exp()
+        };
+        if accept {
+            *current_score = new_score;
+        } else {
+            current.make_move(round, chosen.inverse());
+        }
+        self.temperature = match self.cooling {
+            Cooling::Geometric(factor) => self.temperature * factor,
+            Cooling::Linear(step) => self.temperature - step,
+        };
+        if self.temperature < self.min_temperature {
+            self.temperature = self.initial_temperature;
+        }
+        (1, 0)
+    }
+}
+
+/** Applies the best single-step change after checking every change, even if it makes the schedule worse.
+Players who were recently moved in a round can't be moved again in that round, unless it results in the best score this search has seen.
+Restarts from a random schedule after restart_after loops without finding a better score */
+#[derive(Clone, Debug)]
+pub struct TabuSearch {
+    /**How many recently moved (player, round) pairs are tabu*/
+    pub tenure: usize,
+    pub restart_after: usize,
+    tabu: VecDeque<(usize, usize)>,
+    position: Position,
+    /**The best allowed change found in the current loop, as (round, move, resulting score, resulting unique games played)*/
+    candidate: Option<(usize, Move, u64, u32)>,
+    best_score: u64,
+    stagnant: usize,
+}
+
+impl TabuSearch {
+    pub fn new(tenure: usize, restart_after: usize) -> Self {
+        Self {
+            tenure,
+            restart_after,
+            tabu: VecDeque::with_capacity(tenure + 2),
+            position: Position::default(),
+            candidate: None,
+            best_score: 0,
+            stagnant: 0,
+        }
+    }
+
+    /**Forget the search history, keeping the position in the loop*/
+    fn reset(&mut self, current_score: u64) {
+        self.tabu.clear();
+        self.candidate = None;
+        self.best_score = current_score;
+        self.stagnant = 0;
+    }
+}
+
+impl Default for TabuSearch {
+    fn default() -> Self {
+        Self::new(8, 50)
+    }
+}
+
+impl Strategy for TabuSearch {
+    fn start(&mut self, current_score: u64) {
+        self.reset(current_score);
+        self.position = Position::default();
+    }
+
+    fn process(
+        &mut self,
+        current: &mut Schedule,
+        current_score: &mut u64,
+        restart: &Restart,
+        rng: &mut dyn rand_core::RngCore,
+    ) -> (u32, u32) {
+        let mut evaluated_schedules = 0;
+        let mut random_starts = 0;
+        if self
+            .position
+            .advance(current.get_tables(), current.get_rounds())
+        {
+            if let Some((round, chosen, _, _)) = self.candidate.take() {
+                *current_score = current.make_move(round, chosen);
+                for player in chosen.players() {
+                    self.tabu.push_back((player, round));
+                }
+                while self.tabu.len() > self.tenure {
+                    self.tabu.pop_front();
+                }
+                if *current_score > self.best_score {
+                    self.best_score = *current_score;
+                    self.stagnant = 0;
+                } else {
+                    self.stagnant += 1;
+                }
+            } else {
+                // Every change is tabu
+                self.stagnant = self.restart_after;
+            }
+            if self.stagnant >= self.restart_after {
+                random_starts += 1;
+                restart.apply(current, rng);
+                *current_score = current.get_score();
+                evaluated_schedules += 1;
+                self.reset(*current_score);
+            }
+        }
+        let Position {
+            round,
+            table1,
+            table2,
+        } = self.position;
+        let tabu = &self.tabu;
+        let best_score = self.best_score;
+        let (found, ops) = current.best_move(round, table1, table2, |chosen, score| {
+            score > best_score
+                || !chosen
+                    .players()
+                    .iter()
+                    .any(|&player| tabu.contains(&(player, round)))
+        });
+        evaluated_schedules += ops;
+        if let Some((chosen, score, unique_games_played)) = found {
+            let better = match self.candidate {
+                Some((_, _, candidate_score, candidate_unique_games)) => {
+                    score > candidate_score
+                        || (score == candidate_score
+                            && unique_games_played > candidate_unique_games)
+                }
+                None => true,
+            };
+            if better {
+                self.candidate = Some((round, chosen, score, unique_games_played));
+            }
+        }
+        (evaluated_schedules, random_starts)
+    }
+}
+
+/** Hill climbs to a local optimum, then perturbs it with random single-step changes and climbs again.
+Each new local optimum is kept if it is at least as good as the previous one, otherwise the search goes back to the previous one */
+#[derive(Clone)]
+pub struct IteratedLocalSearch {
+    /**Number of random changes in each perturbation*/
+    pub strength: usize,
+    sweep: Sweep,
+    home: Option<(Schedule, u64)>,
+}
+
+impl IteratedLocalSearch {
+    pub fn new(strength: usize) -> Self {
+        Self {
+            strength,
+            sweep: Sweep::default(),
+            home: None,
+        }
+    }
+}
+
+impl Default for IteratedLocalSearch {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+impl Strategy for IteratedLocalSearch {
+    fn start(&mut self, current_score: u64) {
+        self.sweep.start(current_score);
+        self.home = None;
+    }
+
+    fn process(
+        &mut self,
+        current: &mut Schedule,
+        current_score: &mut u64,
+        _restart: &Restart,
+        mut rng: &mut dyn rand_core::RngCore,
+    ) -> (u32, u32) {
+        let mut evaluated_schedules = 0;
+        let mut perturbations = 0;
+        if self
+            .sweep
+            .position
+            .advance(current.get_tables(), current.get_rounds())
+        {
+            if let Some(ops) = self.sweep.apply_improvement(current, current_score) {
+                evaluated_schedules += ops;
+            } else {
+                // Reached a local optimum, decide where to continue from
+                match &self.home {
+                    Some((home, home_score)) if *home_score > *current_score => {
+                        *current = home.clone();
+                        *current_score = *home_score;
+                    }
+                    _ => self.home = Some((current.clone(), *current_score)),
+                }
+                for _ in 0..self.strength {
+                    let (round, chosen) = current.random_move(&mut rng);
+                    current.make_move(round, chosen);
+                }
+                *current_score = current.get_score();
+                perturbations += 1;
+                evaluated_schedules += self.strength as u32;
+                self.sweep.next_score = *current_score;
+            }
+        }
+        evaluated_schedules += self.sweep.evaluate(current, *current_score);
+        (evaluated_schedules, perturbations)
+    }
+}
+
+/** Names of the strategies parse accepts, which give them their default settings*/
+pub const NAMES: [&str; 4] = ["hill-climbing", "annealing", "tabu", "ils"];
+
+/** Parse a strategy from the command line. Accepts
+`hill-climbing`, `annealing[:<initial temperature>,<cooling factor>]`,
+`tabu[:<tenure>,<restart after>]`, and `ils[:<perturbation strength>]`*/
+pub fn parse(text: &str) -> Result<Box<dyn Strategy>, String> {
+    let mut parts = text.splitn(2, ':');
+    let name = parts.next().unwrap_or("");
+    let numbers: Vec<f64> = match parts.next() {
+        Some(args) => args
+            .split(',')
+            .map(|number| {
+                number
+                    .trim()
+                    .parse::<f64>()
+                    .map_err(|_| format!("Invalid number '{}' in strategy '{}'", number, text))
+            })
+            .collect::<Result<_, _>>()?,
+        None => Vec::new(),
+    };
+    match (name, numbers.as_slice()) {
+        ("hill-climbing", &[]) => Ok(Box::new(HillClimbing::default())),
+        ("annealing", &[]) => Ok(Box::new(SimulatedAnnealing::default())),
+        ("annealing", &[initial_temperature, factor]) if factor > 0.0 && factor < 1.0 => {
+            Ok(Box::new(SimulatedAnnealing::new(
+                initial_temperature,
+                initial_temperature / 1000.0,
+                Cooling::Geometric(factor),
+            )))
+        }
+        ("tabu", &[]) => Ok(Box::new(TabuSearch::default())),
+        ("tabu", &[tenure, restart_after]) if tenure >= 0.0 && restart_after >= 1.0 => Ok(
+            Box::new(TabuSearch::new(tenure as usize, restart_after as usize)),
+        ),
+        ("ils", &[]) => Ok(Box::new(IteratedLocalSearch::default())),
+        ("ils", &[strength]) if strength >= 1.0 => {
+            Ok(Box::new(IteratedLocalSearch::new(strength as usize)))
+        }
+        ("hill-climbing", _) | ("annealing", _) | ("tabu", _) | ("ils", _) => {
+            Err(format!("Invalid arguments for strategy '{}'", text))
+        }
+        _ => Err(format!("Unknown strategy '{}'", text)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+    use schedule::Generator;
+
+    fn strategies() -> Vec<Box<dyn Strategy>> {
+        vec![
+            Box::new(HillClimbing::default()),
+            Box::new(SimulatedAnnealing::default()),
+            Box::new(SimulatedAnnealing::new(1.0, 0.5, Cooling::Linear(0.01))),
+            Box::new(TabuSearch::new(4, 3)),
+            Box::new(IteratedLocalSearch::default()),
+        ]
+    }
+
+    #[test]
+    fn parses_strategies() {
+        assert!(parse("hill-climbing").is_ok());
+        assert!(parse("annealing").is_ok());
+        assert!(parse("annealing:0.05,0.999").is_ok());
+        assert!(parse("annealing:0.05,2").is_err());
+        assert!(parse("tabu:10,20").is_ok());
+        assert!(parse("tabu:10").is_err());
+        assert!(parse("ils:4").is_ok());
+        assert!(parse("ils:x").is_err());
+        assert!(parse("unknown").is_err());
+    }
+
+    #[test]
+    fn tabu_search_keeps_moving() {
+        let mut rng = rand_xorshift::XorShiftRng::seed_from_u64(3);
+        let mut schedule = Schedule::new(12, 3, 3);
+        schedule.normal_fill();
+        let mut score = schedule.generate_score();
+        let mut strategy = TabuSearch::new(4, 1000);
+        strategy.start(score);
+        let initial = schedule.clone();
+        for _ in 0..200 {
+            strategy.process(&mut schedule, &mut score, &Restart::Random, &mut rng);
+            assert_eq!(score, schedule.generate_score());
+        }
+        // Tabu search applies the best allowed change even when it isn't an improvement, so it never stays put
+        let games = |schedule: &Schedule| -> Vec<Vec<usize>> {
+            (0..3)
+                .flat_map(|round| (0..3).map(move |table| (round, table)))
+                .map(|(round, table)| schedule.get_players_from_game(round, table))
+                .collect()
+        };
+        assert_ne!(games(&initial), games(&schedule));
+    }
+
+    quickcheck! {fn best_score_doesnt_decrease_for_every_strategy(tables: u8, player_count: u8, rounds: u8, seed: u64) -> bool{
+        let tables = (tables as usize % 6).max(2);
+        let player_count = (player_count as usize % 25).max(tables * 2);
+        let rounds = (rounds as usize % 6).max(1);
+        strategies().into_iter().all(|strategy| {
+            let rng = rand_xorshift::XorShiftRng::seed_from_u64(seed);
+            let mut generator = Generator::new(rng, player_count, tables, rounds);
+            generator.set_strategy(strategy);
+            let mut best_score = generator.best_score;
+            (0..100).all(|_| {
+                generator.process();
+                let ok = generator.best_score >= best_score;
+                best_score = generator.best_score;
+                ok
+            }) && generator.best_score == generator.best.generate_score()
+        })
+    }}
+
+    quickcheck! {fn capacities_respected_for_every_strategy(sizes: Vec<u8>, spare: u8, seed: u64) -> bool{
+        let capacities: Vec<(usize, usize)> = sizes
+            .iter()
+            .take(5)
+            .map(|&size| {
+                let min = 2 + size as usize % 3;
+                (min, min + size as usize / 64)
+            })
+            .collect();
+        if capacities.len() < 2 {
+            return true;
+        }
+        let min: usize = capacities.iter().map(|&(min, _)| min).sum();
+        let max: usize = capacities.iter().map(|&(_, max)| max).sum();
+        let player_count = min + spare as usize % (max - min + 1);
+        strategies().into_iter().all(|strategy| {
+            let rng = rand_xorshift::XorShiftRng::seed_from_u64(seed);
+            let mut generator = Generator::with_capacities(rng, player_count, &capacities, 3);
+            generator.set_strategy(strategy);
+            for _ in 0..100 {
+                generator.process();
+            }
+            (0..3).all(|round| {
+                capacities.iter().enumerate().all(|(table, &(min, max))| {
+                    let size = generator.best.get_players_from_game(round, table).len();
+                    min <= size && size <= max
+                })
+            })
+        })
+    }}
+}
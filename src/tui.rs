@@ -0,0 +1,226 @@
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::style::Print;
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use parallel::Parallel;
+use run::{RunStats, StopCriteria, StopReason, Tracker};
+use schedule::Schedule;
+use std::cell::Cell;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use strategy;
+
+/** Milliseconds between redraws of the screen*/
+const REDRAW_INTERVAL: u128 = 250;
+/** Most scores kept for the sparkline*/
+const HISTORY_LENGTH: usize = 120;
+/** Blocks used by sparkline, from lowest to highest*/
+const SPARKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/** Why the terminal UI stopped searching*/
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Ending {
+    Stopped(StopReason),
+    /**The user pressed q*/
+    Quit,
+}
+
+/** Text shown by the terminal UI, and saving, supplied by the caller so it can use its own formatting and files*/
+pub struct View<'a> {
+    /**Throughput of the run so far*/
+    pub performance: &'a dyn Fn(RunStats) -> String,
+    /**The best schedule with its scores*/
+    pub grid: &'a dyn Fn(&Schedule) -> String,
+    /**Save the best schedule, returning a message saying where it went*/
+    pub save: &'a dyn Fn(&Schedule) -> String,
+}
+
+/** The last width values as a line of blocks, scaled from the lowest of them to the highest*/
+pub fn sparkline(values: &[u64], width: usize) -> String {
+    let values = &values[values.len().saturating_sub(width)..];
+    let low = values.iter().copied().min().unwrap_or(0);
+    let high = values.iter().copied().max().unwrap_or(0);
+    values
+        .iter()
+        .map(|&value| {
+            if high == low {
+                SPARKS[0]
+            } else {
+                SPARKS[((value - low) * (SPARKS.len() as u64 - 1) / (high - low)) as usize]
+            }
+        })
+        .collect()
+}
+
+/** Puts the terminal back how it was when dropped, even if drawing fails*/
+struct Screen;
+
+impl Screen {
+    fn new() -> crossterm::Result<Self> {
+        terminal::enable_raw_mode()?;
+        crossterm::execute!(std::io::stdout(), EnterAlternateScreen, Hide)?;
+        Ok(Self)
+    }
+}
+
+impl Drop for Screen {
+    fn drop(&mut self) {
+        let _ = crossterm::execute!(std::io::stdout(), Show, LeaveAlternateScreen);
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/** Search with the generators in a full screen view which is redrawn in place, until the criteria are met or the user quits.
+The view shows throughput, the best schedule, a sparkline of the best score, and the scores of each thread.
+The keys are p or space to pause and resume, s to save the best schedule, c to change to the next of strategy::NAMES, and q or Esc to quit.
+Time spent paused doesn't count towards a time limit. strategy is the name of the strategy the generators start with*/
+pub fn run<T: rand::Rng + rand_core::RngCore + Send>(
+    generators: &mut Parallel<T>,
+    criteria: &StopCriteria,
+    strategy: &str,
+    view: &View,
+) -> crossterm::Result<(Ending, RunStats)> {
+    let screen = Screen::new()?;
+    let start = Instant::now();
+    let paused_time = Cell::new(0.0);
+    let clock = || start.elapsed().as_secs_f64() * 1000.0 - paused_time.get();
+    let mut tracker = Tracker::new(criteria, &clock);
+    let mut strategy = strategy.to_string();
+    let mut paused_at: Option<Instant> = None;
+    let mut history = vec![generators.best_score];
+    let mut message = String::new();
+    let mut last_redraw: Option<Instant> = None;
+    let ending = loop {
+        let wait = if paused_at.is_some() {
+            Duration::from_millis(100)
+        } else {
+            Duration::from_millis(0)
+        };
+        let mut quit = false;
+        while !quit && event::poll(wait)? {
+            if let Event::Key(key) = event::read()? {
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => quit = true,
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                        quit = true
+                    }
+                    KeyCode::Char('p') | KeyCode::Char(' ') => match paused_at.take() {
+                        Some(paused) => paused_time
+                            .set(paused_time.get() + paused.elapsed().as_secs_f64() * 1000.0),
+                        None => paused_at = Some(Instant::now()),
+                    },
+                    KeyCode::Char('s') => message = (view.save)(&generators.best),
+                    KeyCode::Char('c') => {
+                        let next = strategy::NAMES
+                            .iter()
+                            .position(|&name| name == strategy)
+                            .map_or(0, |index| (index + 1) % strategy::NAMES.len());
+                        strategy = strategy::NAMES[next].to_string();
+                        generators.set_strategy(|| strategy::parse(strategy::NAMES[next]).unwrap());
+                        message = format!("Changed strategy to {}", strategy);
+                    }
+                    _ => {}
+                }
+            }
+            last_redraw = None;
+        }
+        if quit {
+            break Ending::Quit;
+        }
+        if paused_at.is_none() {
+            if let Some(reason) = tracker.stop_reason(&generators.best, generators.best_score) {
+                break Ending::Stopped(reason);
+            }
+            let old_score = generators.best_score;
+            let (evaluations, random_starts) = generators.run_epoch();
+            tracker.record(evaluations, random_starts);
+            if generators.best_score > old_score {
+                tracker.improved();
+            }
+        }
+        if last_redraw.is_none_or(|redraw| redraw.elapsed().as_millis() >= REDRAW_INTERVAL) {
+            if paused_at.is_none() {
+                history.push(generators.best_score);
+                if history.len() > HISTORY_LENGTH {
+                    history.remove(0);
+                }
+            }
+            draw(
+                generators,
+                &tracker,
+                &strategy,
+                paused_at.is_some(),
+                &history,
+                &message,
+                view,
+            )?;
+            last_redraw = Some(Instant::now());
+        }
+    };
+    drop(screen);
+    Ok((ending, tracker.stats()))
+}
+
+/** Redraw the whole screen, cutting lines off at the edges of the terminal*/
+fn draw<T: rand::Rng + rand_core::RngCore + Send>(
+    generators: &Parallel<T>,
+    tracker: &Tracker,
+    strategy: &str,
+    paused: bool,
+    history: &[u64],
+    message: &str,
+    view: &View,
+) -> crossterm::Result<()> {
+    let (columns, rows) = terminal::size()?;
+    let stats = tracker.stats();
+    let mut text = format!(
+        "{} with {} for {:.1} seconds\n",
+        if paused { "Paused" } else { "Searching" },
+        strategy,
+        stats.elapsed / 1000.0
+    );
+    text.push_str(&(view.performance)(stats));
+    text.push_str(&format!(
+        "Best score found: {} / proven upper bound {}\n",
+        generators.best_score,
+        generators.best.score_bound()
+    ));
+    text.push_str(&format!(
+        "Score history: {}\n",
+        sparkline(history, (columns as usize).saturating_sub(15))
+    ));
+    for (thread, generator) in generators.get_generators().iter().enumerate() {
+        text.push_str(&format!(
+            "Thread {}: best {}, current {}\n",
+            thread,
+            generator.best_score,
+            generator.get_current_score()
+        ));
+    }
+    text.push_str("Keys: p pause, s save, c change strategy, q quit");
+    if !message.is_empty() {
+        text.push_str(&format!(" | {}", message));
+    }
+    text.push_str("\n\n");
+    text.push_str(&(view.grid)(&generators.best));
+    let mut stdout = std::io::stdout();
+    crossterm::queue!(stdout, Clear(ClearType::All))?;
+    for (row, line) in text.lines().take(rows as usize).enumerate() {
+        let line: String = line.chars().take(columns as usize).collect();
+        crossterm::queue!(stdout, MoveTo(0, row as u16), Print(line))?;
+    }
+    Ok(stdout.flush()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sparkline_scales_to_the_range_shown() {
+        assert_eq!(sparkline(&[], 10), "");
+        assert_eq!(sparkline(&[5, 5], 10), "▁▁");
+        assert_eq!(sparkline(&[0, 7, 14], 10), "▁▄█");
+        assert_eq!(sparkline(&[100, 0, 7, 14], 3), "▁▄█");
+    }
+}